@@ -1,155 +1,2675 @@
-use crate::{InodeFilter, WalkOptions, WalkResult};
-use failure::Error;
+use crate::{
+    compression,
+    locale::group_digits,
+    ls_colors::{LsStyle, PathKind},
+    ndjson::json_escaped,
+    owner,
+    remote::RemotePath,
+    traverse,
+    walk::{Entry, Filesystem, Walk},
+    ColumnOrder, ErrorAnnotation, ErrorClass, InodeFilter, OutputFormat, OutputOrder, WalkOptions,
+    WalkResult,
+};
+use failure::{bail, Error};
+use itertools::Itertools;
 use std::borrow::Cow;
-use std::{fmt, io, path::Path};
-use termion::color;
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    fmt, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use termion::{color, style};
 
 /// Aggregate the given `paths` and write information about them to `out` in a human-readable format.
-/// If `compute_total` is set, it will write an additional line with the total size across all given `paths`.
-/// If `sort_by_size_in_bytes` is set, we will sort all sizes (ascending) before outputting them.
+/// If `total_line` is set, it will write an additional line with the total size across all given `paths`,
+/// before or after the lines of all paths depending on its `position`, or instead of them if it's the `only` line.
+/// If `options.output_order` is [`OutputOrder::SizeAscending`], we will sort all sizes (ascending) before outputting them,
+/// with paths of equal size in alphabetical order. [`OutputOrder::SizeDescending`] writes the largest path first
+/// instead, and [`OutputOrder::PathAscending`] and [`OutputOrder::EntryCountAscending`] sort paths by their
+/// name or by the amount of entries within them.
+/// If `top_n` is set as well, only the given amount of largest paths will be written, in the order of
+/// `options.output_order`.
+/// Unless `options.count_hard_links` is set, a file with multiple hard links is counted only once across all `paths`,
+/// by the path which is walked first in the given order, so the total is the size of all unique files. Files are told
+/// apart by their device and inode, so paths on different filesystems never share files.
+/// `aggregate_options` choose whether paths are grouped by owner or rolled up, and what else is tracked while walking.
+/// Paths with a label in `options.labels` are written as their label, and paths below `options.relative_to` relative to it.
+/// Unless paths have to be sorted, their sizes are written in a shared unit or a heatmap, or the total comes first, each
+/// path is written and `out` is flushed as soon as the path was walked, before walking the next one.
+/// If `options.shared_unit` is set, all sizes are written only once all paths are known, in the unit of the largest of them.
+/// The same is true for `options.heatmap`, which colors sizes relative to the largest of them, leaving the total green.
+/// With the `ssh` feature, paths like `ssh://host/path` are walked on the given host using the `ssh` program,
+/// which only provides their apparent size.
 pub fn aggregate(
-    mut out: impl io::Write,
+    out: impl io::Write,
     options: WalkOptions,
-    compute_total: bool,
-    sort_by_size_in_bytes: bool,
+    total_line: Option<TotalLine>,
+    top_n: Option<usize>,
+    aggregate_options: AggregateOptions,
+    paths: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> Result<(WalkResult, Statistics), Error> {
+    aggregate_with(
+        &Filesystem,
+        out,
+        options,
+        total_line,
+        top_n,
+        aggregate_options,
+        paths,
+    )
+}
+
+/// Like [`aggregate()`], but with the entries below local `paths` provided by `walk` instead of the filesystem.
+pub fn aggregate_with(
+    walk: &impl Walk,
+    mut out: impl io::Write,
+    mut options: WalkOptions,
+    total_line: Option<TotalLine>,
+    top_n: Option<usize>,
+    aggregate_options: AggregateOptions,
     paths: impl IntoIterator<Item = impl AsRef<Path>>,
 ) -> Result<(WalkResult, Statistics), Error> {
+    let AggregateOptions {
+        by_owner,
+        rollup,
+        skip_empty,
+        statistics,
+    } = aggregate_options;
+    if by_owner && !cfg!(all(unix, feature = "by-owner")) {
+        bail!(
+            "Aggregation by owner is only supported on Unix, with the 'by-owner' feature enabled"
+        );
+    }
     let mut res = WalkResult::default();
-    let mut stats = Statistics::default();
-    stats.smallest_file_in_bytes = u64::max_value();
-    let mut total = 0;
+    let mut stats = Statistics {
+        smallest_file_in_bytes: if statistics { u64::MAX } else { 0 },
+        ..Default::default()
+    };
+    let mut total = PathAggregate::default();
     let mut num_roots = 0;
     let mut aggregates = Vec::new();
+    let mut root_sizes = Vec::new();
+    let mut num_name_bytes = 0;
+    let mut owners = BTreeMap::<u32, PathAggregate>::new();
+    let mut rollups = BTreeMap::<PathBuf, (PathAggregate, PathKind)>::new();
+    let grouped = by_owner || rollup;
+    let subjects = if by_owner {
+        SubjectKind::Owner
+    } else {
+        SubjectKind::Path
+    };
     let mut inodes = InodeFilter::default();
-    for path in paths.into_iter() {
+    let mut warned_about_remote_sizes = false;
+    let mut num_entries_within_directories = 0_u64;
+    let mut worrying_errors = WorryingErrors::default();
+    let sorts_paths = options.output_order != OutputOrder::Unsorted;
+    let total_first = total_line
+        .as_ref()
+        .is_some_and(|t| t.position == TotalPosition::First);
+    let total_only = total_line.as_ref().is_some_and(|t| t.only);
+    let paths: Vec<_> = paths.into_iter().collect();
+    let path_column_width = path_column_width(
+        paths
+            .iter()
+            .filter(|_| !total_only)
+            .map(|p| options.displayed_path(p.as_ref()).to_string_lossy()),
+        total_line.as_ref(),
+    );
+    if has_header(&options) && !grouped && !options.shared_unit {
+        write_header(&mut out, &options, path_column_width, SubjectKind::Path)?;
+    }
+    let deadline = options
+        .max_time
+        .and_then(|max_time| Instant::now().checked_add(max_time));
+    let is_past_deadline = || deadline.is_some_and(|deadline| Instant::now() >= deadline);
+    for path in paths.iter() {
+        if is_past_deadline() {
+            log::info!("the time is up before walking {}", path.as_ref().display());
+            res.is_truncated = true;
+            break;
+        }
         num_roots += 1;
-        let mut num_bytes = 0u64;
-        let mut num_errors = 0u64;
-        for entry in options.iter_from_path(path.as_ref()) {
+        log::debug!("walking {}", path.as_ref().display());
+        let walk_started = Instant::now();
+        let mut aggregate = PathAggregate::default();
+        let mut kind = PathKind::Other;
+        let remote = RemotePath::parse(path.as_ref());
+        if let Some(remote) = &remote {
+            aggregate.num_errors += remote.walk(|entry| {
+                stats.entries_traversed += 1;
+                if aggregate.num_entries > 0 {
+                    num_entries_within_directories += 1;
+                }
+                aggregate.num_entries += 1;
+                if entry.is_dir {
+                    stats.num_directories += 1;
+                } else {
+                    stats.num_files += 1;
+                    if statistics {
+                        stats.largest_file_in_bytes = stats.largest_file_in_bytes.max(entry.size);
+                        stats.smallest_file_in_bytes = stats.smallest_file_in_bytes.min(entry.size);
+                    }
+                    aggregate.add_sizes(entry.size, entry.size, 0);
+                }
+            })?;
+            if !options.apparent_size && !warned_about_remote_sizes {
+                res.warnings.push(
+                    "the disk usage of remote paths is unknown, using their apparent size instead"
+                        .into(),
+                );
+                warned_about_remote_sizes = true;
+            }
+        }
+        let local_entries = match remote {
+            Some(_) => None,
+            None => Some(walk.entries(path.as_ref(), &options)),
+        };
+        for (eid, entry) in local_entries.into_iter().flatten().enumerate() {
+            if eid % CHECK_DEADLINE_EVERY == 0 && eid > 0 && is_past_deadline() {
+                log::info!(
+                    "the time is up after walking {} entries of {}",
+                    eid,
+                    path.as_ref().display()
+                );
+                res.is_truncated = true;
+                break;
+            }
             stats.entries_traversed += 1;
             match entry {
                 Ok(entry) => {
-                    let file_size = match entry.metadata {
-                        Some(Ok(ref m)) if !m.is_dir() && (options.count_hard_links || inodes.add(m)) => {
+                    let num_errors_before = aggregate.num_errors;
+                    aggregate.num_entries += 1;
+                    if eid == 0 {
+                        kind = entry
+                            .metadata
+                            .as_ref()
+                            .map_or(PathKind::Other, PathKind::of);
+                    }
+                    if entry.depth > stats.max_depth || stats.deepest_path.is_none() {
+                        stats.max_depth = entry.depth;
+                        stats.deepest_path = Some(entry.path.clone());
+                    }
+                    if entry.depth > 0 {
+                        num_entries_within_directories += 1;
+                    }
+                    match entry.metadata {
+                        Ok(ref m) if m.is_dir => stats.num_directories += 1,
+                        Ok(ref m) => {
+                            stats.num_files += 1;
+                            if m.is_symlink_loop {
+                                stats.symlink_loops += 1;
+                            }
+                        }
+                        Err(_) => {}
+                    }
+                    let (file_size, apparent_file_size) = match entry.metadata {
+                        Ok(ref m)
+                            if !m.is_dir
+                                && (options.count_hard_links
+                                    || m.file_id_and_links.is_none_or(|(id, nlinks)| {
+                                        inodes.add_inode(id, nlinks)
+                                    })) =>
+                        {
                             if options.apparent_size {
-                                m.len()
+                                (m.apparent_size, m.apparent_size)
                             } else {
-                                filesize::file_real_size_fast(&entry.path(), m)
-                                    .unwrap_or_else(|_| {
-                                        num_errors += 1;
+                                let disk_size = match m.disk_size {
+                                    Ok(disk_size) => disk_size,
+                                    Err(class) => {
+                                        if options.fatal_errors.contains(&class) {
+                                            bail!(
+                                                "Could not determine the size on disk of '{}', which ends the walk: {:?}",
+                                                entry.path.display(),
+                                                class
+                                            );
+                                        }
+                                        worrying_errors.record(
+                                            class,
+                                            &entry.path,
+                                            "could not determine the size on disk",
+                                        );
+                                        aggregate.num_errors += 1;
                                         0
-                                    })
+                                    }
+                                };
+                                (disk_size, m.apparent_size)
                             }
-                        },
-                        Some(Ok(_)) => 0,
-                        Some(Err(_)) => {
-                            num_errors += 1;
-                            0
                         }
-                        None => unreachable!(
-                            "we ask for metadata, so we at least have Some(Err(..))). Issue in jwalk?"
-                        ),
+                        Ok(_) => (0, 0),
+                        Err(ref err) => {
+                            if options.is_fatal(err) {
+                                bail!(
+                                    "Could not read the metadata of '{}', which ends the walk: {}",
+                                    entry.path.display(),
+                                    err
+                                );
+                            }
+                            worrying_errors.record(ErrorClass::of(err), &entry.path, err);
+                            aggregate.num_errors += 1;
+                            (0, 0)
+                        }
+                    };
+                    let (file_size, apparent_file_size) = if options.excludes_file_size(file_size) {
+                        (0, 0)
+                    } else {
+                        (file_size, apparent_file_size)
                     };
-                    stats.largest_file_in_bytes = stats.largest_file_in_bytes.max(file_size);
-                    stats.smallest_file_in_bytes = stats.smallest_file_in_bytes.min(file_size);
-                    num_bytes += file_size;
+                    if statistics {
+                        stats.tree_nodes += 1;
+                        num_name_bytes += if entry.depth == 0 {
+                            entry.path.as_os_str().len()
+                        } else {
+                            entry.path.file_name().map_or(0, |name| name.len())
+                        } as u64;
+                        stats.largest_file_in_bytes = stats.largest_file_in_bytes.max(file_size);
+                        stats.smallest_file_in_bytes = stats.smallest_file_in_bytes.min(file_size);
+                    }
+                    let estimated_savings = if options.estimate_compression {
+                        compression::estimated_savings(&entry.path, file_size).unwrap_or_else(
+                            |_| {
+                                aggregate.num_errors += 1;
+                                0
+                            },
+                        )
+                    } else {
+                        0
+                    };
+                    aggregate.add_sizes(file_size, apparent_file_size, estimated_savings);
+                    if let (true, Ok(ref m)) = (by_owner, &entry.metadata) {
+                        if let Some(uid) = m.uid {
+                            let owner = owners.entry(uid).or_default();
+                            owner.add_sizes(file_size, apparent_file_size, estimated_savings);
+                            owner.num_entries += 1;
+                        }
+                    }
+                    if let (true, Some(group)) = (rollup, rollup_group(path.as_ref(), &entry)) {
+                        let (group, _) = rollups
+                            .entry(options.displayed_path(&group.0).to_owned())
+                            .or_insert((PathAggregate::default(), group.1));
+                        group.add_sizes(file_size, apparent_file_size, estimated_savings);
+                        group.num_entries += 1;
+                        group.num_errors += aggregate.num_errors - num_errors_before;
+                    }
+                }
+                Err(err) => {
+                    if options.is_fatal(&err) {
+                        bail!(
+                            "Could not walk '{}', which ends the walk: {}",
+                            path.as_ref().display(),
+                            err
+                        );
+                    }
+                    worrying_errors.record(ErrorClass::of(&err), path.as_ref(), &err);
+                    aggregate.record_walk_error(eid == 0)
                 }
-                Err(_) => num_errors += 1,
             }
         }
+        log::info!(
+            "walked {} in {:?}: {} bytes in {} entries, {} IO errors",
+            path.as_ref().display(),
+            walk_started.elapsed(),
+            aggregate.num_bytes,
+            aggregate.num_entries,
+            aggregate.num_errors
+        );
+        stats
+            .time_per_root
+            .push((path.as_ref().to_owned(), walk_started.elapsed()));
 
-        if sort_by_size_in_bytes {
-            aggregates.push((path.as_ref().to_owned(), num_bytes, num_errors));
+        if let (true, Some(_)) = (rollup, &remote) {
+            rollups
+                .entry(options.displayed_path(path.as_ref()).to_owned())
+                .or_insert((PathAggregate::default(), PathKind::Other))
+                .0
+                .add(&aggregate);
+        }
+        if grouped || total_only {
+            // paths are not written, only groups or the total
+        } else if skip_empty && aggregate.num_bytes == 0 {
+            // empty paths are only part of the total
+        } else if sorts_paths || options.shared_unit || options.heatmap.is_some() || total_first {
+            aggregates.push((path.as_ref().to_owned(), aggregate.clone(), kind));
         } else {
             write_path(
                 &mut out,
                 &options,
-                &path,
-                num_bytes,
-                num_errors,
-                path_color(&path),
+                path_column_width,
+                Subject::input(options.displayed_path(path.as_ref())),
+                &aggregate,
+                path_color(&options, kind, path.as_ref()),
             )?;
+            // make the line visible right away, as the next path may take long
+            out.flush()?;
         }
-        total += num_bytes;
-        res.num_errors += num_errors;
+        root_sizes.push((
+            options.displayed_path(path.as_ref()).to_owned(),
+            aggregate.num_bytes,
+        ));
+        total.add_path(&aggregate);
+    }
+    res.num_errors = total.num_errors;
+    stats.num_errors = total.num_errors;
+    res.total_bytes = total.num_bytes;
+    if res.is_truncated {
+        res.warnings.push(format!(
+            "the walk was stopped as its time was up, so sizes are too small and {} of {} input paths were walked",
+            num_roots,
+            paths.len()
+        ));
+    }
+    res.warnings.extend(worrying_errors.warning());
+    if total.is_saturated {
+        res.warnings.push(format!(
+            "sizes exceeded {} bytes and were capped to it, so they are too small",
+            u64::MAX
+        ));
     }
 
     if stats.entries_traversed == 0 {
         stats.smallest_file_in_bytes = 0;
     }
+    if statistics {
+        // the virtual root all input paths are attached to
+        stats.tree_nodes += 1;
+        stats.estimated_tree_bytes =
+            traverse::estimated_tree_size(stats.tree_nodes, num_name_bytes);
+    }
+    stats
+        .time_per_root
+        .sort_by(|(_, lhs), (_, rhs)| rhs.cmp(lhs));
+    if stats.num_directories > 0 {
+        stats.files_per_directory = stats.num_files as f64 / stats.num_directories as f64;
+        stats.average_fan_out =
+            num_entries_within_directories as f64 / stats.num_directories as f64;
+    }
+
+    let groups: Option<Vec<_>> = if by_owner {
+        let names = owner::user_names();
+        Some(
+            owners
+                .into_iter()
+                .map(|(uid, aggregate)| {
+                    let name = names.get(&uid).cloned().unwrap_or_else(|| uid.to_string());
+                    (PathBuf::from(name), aggregate, PathKind::Other)
+                })
+                .collect(),
+        )
+    } else if rollup {
+        Some(
+            rollups
+                .into_iter()
+                .map(|(path, (aggregate, kind))| (path, aggregate, kind))
+                .collect(),
+        )
+    } else {
+        None
+    };
+    let groups = groups.map(|groups| {
+        num_roots = groups.len();
+        groups
+            .into_iter()
+            .sorted_by(|(lname, laggregate, _), (rname, raggregate, _)| {
+                raggregate
+                    .num_bytes
+                    .cmp(&laggregate.num_bytes)
+                    .then_with(|| lname.cmp(rname))
+            })
+            .collect::<Vec<_>>()
+    });
+
+    let total_line = total_line.filter(|t| num_roots > 1 || t.only);
+    if sorts_paths {
+        aggregates.sort_by(|(lpath, laggregate, _), (rpath, raggregate, _)| {
+            laggregate
+                .num_bytes
+                .cmp(&raggregate.num_bytes)
+                .then_with(|| lpath.cmp(rpath))
+        });
+        let num_smaller_paths = top_n.map_or(0, |n| aggregates.len().saturating_sub(n));
+        aggregates.drain(..num_smaller_paths);
+        match options.output_order {
+            OutputOrder::SizeDescending => {
+                aggregates.sort_by(|(lpath, laggregate, _), (rpath, raggregate, _)| {
+                    raggregate
+                        .num_bytes
+                        .cmp(&laggregate.num_bytes)
+                        .then_with(|| lpath.cmp(rpath))
+                })
+            }
+            OutputOrder::PathAscending => {
+                aggregates.sort_by(|(lpath, _, _), (rpath, _, _)| lpath.cmp(rpath))
+            }
+            OutputOrder::EntryCountAscending => {
+                aggregates.sort_by(|(lpath, laggregate, _), (rpath, raggregate, _)| {
+                    laggregate
+                        .num_entries
+                        .cmp(&raggregate.num_entries)
+                        .then_with(|| lpath.cmp(rpath))
+                })
+            }
+            OutputOrder::Unsorted | OutputOrder::SizeAscending => {}
+        }
+    }
+
+    if let Some(heatmap) = options.heatmap.as_mut() {
+        heatmap.largest = aggregates
+            .iter()
+            .map(|(_, aggregate, _)| aggregate)
+            .chain(groups.iter().flatten().map(|(_, aggregate, _)| aggregate))
+            .map(|aggregate| aggregate.num_bytes)
+            .max()
+            .unwrap_or(0);
+    }
+    if options.shared_unit {
+        let largest = aggregates
+            .iter()
+            .map(|(_, aggregate, _)| aggregate)
+            .chain(groups.iter().flatten().map(|(_, aggregate, _)| aggregate))
+            .chain(Some(&total).filter(|_| total_line.is_some()))
+            .map(|aggregate| aggregate.num_bytes.max(aggregate.num_apparent_bytes))
+            .max()
+            .unwrap_or(0);
+        options.byte_format = options.byte_format.with_fixed_unit_for(largest);
+        if has_header(&options) && !grouped {
+            write_header(&mut out, &options, path_column_width, SubjectKind::Path)?;
+        }
+    }
+
+    if let (Some(total_line), true, false) = (&total_line, total_first, grouped) {
+        write_total(
+            &mut out,
+            &options,
+            path_column_width,
+            total_line,
+            &root_sizes,
+            &total,
+            subjects,
+        )?;
+    }
+    for (path, aggregate, kind) in aggregates {
+        write_path(
+            &mut out,
+            &options,
+            path_column_width,
+            Subject::input(options.displayed_path(&path)),
+            &aggregate,
+            path_color(&options, kind, &path),
+        )?;
+    }
 
-    if sort_by_size_in_bytes {
-        aggregates.sort_by_key(|&(_, num_bytes, _)| num_bytes);
-        for (path, num_bytes, num_errors) in aggregates.into_iter() {
+    let mut path_column_width = path_column_width;
+    if let Some(groups) = groups {
+        path_column_width = self::path_column_width(
+            groups.iter().map(|(name, _, _)| name.to_string_lossy()),
+            total_line.as_ref(),
+        );
+        if has_header(&options) {
+            write_header(&mut out, &options, path_column_width, subjects)?;
+        }
+        if let (Some(total_line), true) = (&total_line, total_first) {
+            write_total(
+                &mut out,
+                &options,
+                path_column_width,
+                total_line,
+                &root_sizes,
+                &total,
+                subjects,
+            )?;
+        }
+        for (name, aggregate, kind) in groups {
             write_path(
                 &mut out,
                 &options,
-                &path,
-                num_bytes,
-                num_errors,
-                path_color(&path),
+                path_column_width,
+                if by_owner {
+                    Subject::Owner(&name)
+                } else {
+                    Subject::input(&name)
+                },
+                &aggregate,
+                path_color(&options, kind, &name),
             )?;
         }
     }
 
-    if num_roots > 1 && compute_total {
-        write_path(
+    if let (Some(total_line), false) = (&total_line, total_first) {
+        write_total(
             &mut out,
             &options,
-            Path::new("total"),
-            total,
-            res.num_errors,
-            color::Fg(color::Reset),
+            path_column_width,
+            total_line,
+            &root_sizes,
+            &total,
+            subjects,
         )?;
     }
     Ok((res, stats))
 }
 
-fn path_color(path: impl AsRef<Path>) -> Box<dyn fmt::Display> {
-    if path.as_ref().is_file() {
-        Box::new(color::Fg(color::LightBlack))
-    } else {
-        Box::new(color::Fg(color::Reset))
+/// Where to write the line with the total size, relative to the lines of all paths
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TotalPosition {
+    /// Before all paths, right after the header if there is one
+    First,
+    /// After all paths
+    Last,
+}
+
+/// Configures the line with the total size across all paths
+#[derive(Debug, Clone)]
+pub struct TotalLine {
+    /// The text to write in place of a path
+    pub label: String,
+    /// If set, the label is written in bold if colors are enabled
+    pub bold: bool,
+    /// Whether the line comes before or after the lines of all paths
+    pub position: TotalPosition,
+    /// If set, this is the only line written, even for a single path
+    pub only: bool,
+    /// If set, the share of the total of the largest input paths follows the label, like `total (a 60%, b 40%)`
+    pub shares: bool,
+}
+
+impl Default for TotalLine {
+    fn default() -> Self {
+        TotalLine {
+            label: "total".into(),
+            bold: false,
+            position: TotalPosition::Last,
+            only: false,
+            shares: false,
+        }
+    }
+}
+
+/// Configures how [`aggregate()`] groups paths and what it tracks besides their sizes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AggregateOptions {
+    /// If set, instead of the paths, the owners of all files are written along with the space their files consume,
+    /// sorted descending by size and then by name. It's only available on Unix with the `by-owner` feature.
+    pub by_owner: bool,
+    /// If set, instead of the paths, each directory directly within them is written along with the space consumed by
+    /// everything below it, and the files directly within each path are summed up as `<path>/(files)`, sorted like
+    /// owners. Paths which are files and remote paths are written as a whole, and errors reading directories only
+    /// count towards the total.
+    pub rollup: bool,
+    /// If set, paths whose size is zero bytes are not written, but still count towards the total.
+    pub skip_empty: bool,
+    /// Unless set, the smallest and largest file sizes and the size of the tree of interactive mode in the returned
+    /// [`Statistics`] aren't tracked and remain 0, which saves comparisons for each file. Counts of entries and the
+    /// depth are always tracked.
+    pub statistics: bool,
+}
+
+/// The amount of entries to walk before looking at the time again, to not look at it for every entry
+const CHECK_DEADLINE_EVERY: usize = 256;
+
+/// Add `n` to `sum`, or set it to `u64::MAX` and return true if that would overflow.
+fn add_saturating(sum: &mut u64, n: u64) -> bool {
+    match sum.checked_add(n) {
+        Some(new_sum) => {
+            *sum = new_sum;
+            false
+        }
+        None => {
+            *sum = u64::MAX;
+            true
+        }
+    }
+}
+
+/// Enough to align entry counts below a billion
+const ENTRY_COUNT_WIDTH: usize = 9;
+
+/// Sizes and error counts obtained for a single path, or for all of them
+#[derive(Default, Clone, Debug, PartialEq)]
+pub(crate) struct PathAggregate {
+    pub(crate) num_bytes: u64,
+    num_apparent_bytes: u64,
+    /// The amount of bytes we expect to save by compressing large files
+    num_estimated_savings: u64,
+    /// The amount of files and directories, including the path itself
+    pub(crate) num_entries: u64,
+    /// The amount of io::errors, including the ones counted in `num_inaccessible_dirs`
+    pub(crate) num_errors: u64,
+    /// The amount of directories below the path whose contents could not be read.
+    /// If not 0, the sizes only cover the accessible parts of the path.
+    num_inaccessible_dirs: u64,
+    /// The amount of input paths with errors, if this is the total of several of them
+    num_paths_with_errors: u64,
+    /// If set, one of the sizes exceeded `u64::MAX` and was capped to it
+    is_saturated: bool,
+}
+
+/// The errors which hint at a problem with the system rather than with the walked entries, like failing hardware
+#[derive(Debug, Default)]
+struct WorryingErrors {
+    count: u64,
+    /// The first of them along with the path it occurred at
+    first: Option<(PathBuf, String)>,
+}
+
+impl WorryingErrors {
+    /// Remember the error `err` of the given `class` encountered at `path` if it is worrying.
+    fn record(&mut self, class: ErrorClass, path: &Path, err: impl fmt::Display) {
+        if !class.is_worrying() {
+            return;
+        }
+        self.count += 1;
+        if self.first.is_none() {
+            self.first = Some((path.to_owned(), err.to_string()));
+        }
+    }
+
+    /// A message about the worrying errors, if there were any.
+    fn warning(&self) -> Option<String> {
+        self.first.as_ref().map(|(path, err)| {
+            format!(
+                "{} IO {} may indicate failing hardware or a lack of memory, the first at '{}': {}",
+                self.count,
+                if self.count == 1 { "error" } else { "errors" },
+                path.display(),
+                err
+            )
+        })
+    }
+}
+
+impl PathAggregate {
+    /// The aggregate of a single file with the given sizes.
+    pub(crate) fn of_file(
+        num_bytes: u64,
+        num_apparent_bytes: u64,
+        num_estimated_savings: u64,
+    ) -> Self {
+        PathAggregate {
+            num_bytes,
+            num_apparent_bytes,
+            num_estimated_savings,
+            num_entries: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Count an error produced by the walk itself. If `is_root`, the path itself could not be accessed,
+    /// otherwise one of the directories below it could not be read.
+    pub(crate) fn record_walk_error(&mut self, is_root: bool) {
+        self.num_errors += 1;
+        if !is_root {
+            self.num_inaccessible_dirs += 1;
+        }
+    }
+
+    /// Add the sizes of a file, capping them at `u64::MAX` instead of overflowing.
+    pub(crate) fn add_sizes(
+        &mut self,
+        num_bytes: u64,
+        num_apparent_bytes: u64,
+        num_estimated_savings: u64,
+    ) {
+        self.is_saturated |= add_saturating(&mut self.num_bytes, num_bytes)
+            | add_saturating(&mut self.num_apparent_bytes, num_apparent_bytes)
+            | add_saturating(&mut self.num_estimated_savings, num_estimated_savings);
+    }
+
+    pub(crate) fn add(&mut self, other: &PathAggregate) {
+        self.add_sizes(
+            other.num_bytes,
+            other.num_apparent_bytes,
+            other.num_estimated_savings,
+        );
+        self.is_saturated |= other.is_saturated;
+        self.num_entries += other.num_entries;
+        self.num_errors += other.num_errors;
+        self.num_inaccessible_dirs += other.num_inaccessible_dirs;
+        self.num_paths_with_errors += other.num_paths_with_errors;
+    }
+
+    /// Add the aggregate of an input path, counting it as one of the paths with errors if it has any.
+    fn add_path(&mut self, path: &PathAggregate) {
+        self.add(path);
+        if path.num_errors > 0 {
+            self.num_paths_with_errors += 1;
+        }
+    }
+
+    fn error_annotation(&self, style: ErrorAnnotation) -> Cow<'static, str> {
+        match style {
+            ErrorAnnotation::Human => {}
+            ErrorAnnotation::Field => return Cow::Owned(format!("\t{}", self.num_errors)),
+            ErrorAnnotation::None => return Cow::Borrowed(""),
+        }
+        let plural_s = |n| if n > 1 { "s" } else { "" };
+        let across_paths = match self.num_paths_with_errors {
+            0 => String::new(),
+            n => format!(" across {} path{}", n, plural_s(n)),
+        };
+        match (self.num_errors, self.num_inaccessible_dirs) {
+            (0, _) => Cow::Borrowed(""),
+            (num_errors, 0) => Cow::Owned(format!(
+                "  <{} IO Error{}{}>",
+                num_errors,
+                plural_s(num_errors),
+                across_paths
+            )),
+            (num_errors, num_dirs) => Cow::Owned(format!(
+                "  <{} IO Error{}{}, partial: {} inaccessible director{}>",
+                num_errors,
+                plural_s(num_errors),
+                across_paths,
+                num_dirs,
+                if num_dirs > 1 { "ies" } else { "y" }
+            )),
+        }
+    }
+}
+
+/// The group of `entry` below the input path `root` when rolling up, along with the kind of path it is shown as:
+/// the directory directly within `root` containing it, or `<root>/(files)` for files directly within `root`.
+/// `root` is its own group if it is a file, and no group if it is a directory.
+fn rollup_group(root: &Path, entry: &Entry) -> Option<(PathBuf, PathKind)> {
+    let is_dir = matches!(entry.metadata, Ok(ref m) if m.is_dir);
+    match entry.depth {
+        0 if is_dir => None,
+        0 => Some((
+            root.to_owned(),
+            entry.metadata.as_ref().map_or(PathKind::File, PathKind::of),
+        )),
+        1 if is_dir => Some((entry.path.clone(), PathKind::Directory)),
+        1 => Some((root.join("(files)"), PathKind::File)),
+        _ => {
+            let first = entry.path.strip_prefix(root).ok()?.components().next()?;
+            Some((root.join(first), PathKind::Directory))
+        }
+    }
+}
+
+/// The color of `path`, with its `kind` obtained from the metadata of the walk to avoid querying it once more.
+/// Files are dimmed unless `options.ls_colors` are set, which determine the color of all kinds of paths then.
+pub(crate) fn path_color(
+    options: &WalkOptions,
+    kind: PathKind,
+    path: &Path,
+) -> Box<dyn fmt::Display> {
+    match (&options.ls_colors, kind) {
+        (Some(ls_colors), _) => match ls_colors.style_of(kind, path) {
+            Some(style) => Box::new(LsStyle(style.to_owned())),
+            None => Box::new(color::Fg(color::Reset)),
+        },
+        (None, PathKind::File | PathKind::Executable) => Box::new(color::Fg(color::LightBlack)),
+        (None, _) => Box::new(color::Fg(color::Reset)),
+    }
+}
+
+/// The width of the path column needed to align all sizes if paths come first, given the `names` to be written,
+/// the `total_line` if there is one and the header.
+pub(crate) fn path_column_width<'a>(
+    names: impl Iterator<Item = Cow<'a, str>>,
+    total_line: Option<&'a TotalLine>,
+) -> usize {
+    names
+        .chain(total_line.map(|t| Cow::Borrowed(t.label.as_str())))
+        .map(|name| name.chars().count())
+        .chain(std::iter::once("path".len()))
+        .max()
+        .unwrap_or(0)
+}
+
+pub(crate) fn has_header(options: &WalkOptions) -> bool {
+    match options.output_format {
+        OutputFormat::Text => {
+            options.both_sizes || options.show_entry_count || options.estimate_compression
+        }
+        OutputFormat::Json => false,
+        OutputFormat::Csv { .. } => true,
     }
 }
 
-fn write_path<C: fmt::Display>(
+pub(crate) fn write_header(
     out: &mut impl io::Write,
     options: &WalkOptions,
-    path: impl AsRef<Path>,
-    num_bytes: u64,
-    num_errors: u64,
-    path_color: C,
+    path_column_width: usize,
+    subjects: SubjectKind,
 ) -> Result<(), io::Error> {
-    writeln!(
-        out,
-        "{byte_color}{:>byte_column_width$}{byte_color_reset} {path_color}{}{path_color_reset}{}",
-        options.byte_format.display(num_bytes).to_string(), // needed for formatting to work (unless we implement it ourselves)
-        path.as_ref().display(),
-        if num_errors == 0 {
-            Cow::Borrowed("")
+    if let OutputFormat::Csv { delimiter } = options.output_format {
+        return match subjects {
+            SubjectKind::Path => writeln!(
+                out,
+                "path{0}depth{0}collapsed_levels{0}bytes{0}entries{0}errors",
+                delimiter
+            ),
+            SubjectKind::Owner => writeln!(out, "owner{0}bytes{0}entries{0}errors", delimiter),
+        };
+    }
+    let byte_column_width = options.byte_format.width();
+    let mut columns = Vec::new();
+    if options.both_sizes {
+        columns.push(format!("{:>width$}", "apparent", width = byte_column_width));
+    }
+    columns.push(format!(
+        "{:>width$}",
+        if options.apparent_size {
+            "apparent"
         } else {
-            Cow::Owned(format!(
-                "  <{} IO Error{}>",
-                num_errors,
-                if num_errors > 1 { "s" } else { "" }
-            ))
+            "disk"
+        },
+        width = byte_column_width
+    ));
+    if options.estimate_compression {
+        columns.push(format!("{:>width$}", "savings", width = byte_column_width));
+    }
+    if options.show_entry_count {
+        columns.push(format!("{:>width$}", "entries", width = ENTRY_COUNT_WIDTH));
+    }
+    let columns = columns.join(" ");
+    match options.column_order {
+        ColumnOrder::SizePath => writeln!(out, "{} path", columns),
+        ColumnOrder::PathSize => writeln!(
+            out,
+            "{:<path_column_width$} {}",
+            "path",
+            columns,
+            path_column_width = path_column_width
+        ),
+    }
+}
+
+fn write_total(
+    out: &mut impl io::Write,
+    options: &WalkOptions,
+    path_column_width: usize,
+    total_line: &TotalLine,
+    root_sizes: &[(PathBuf, u64)],
+    total: &PathAggregate,
+    subjects: SubjectKind,
+) -> Result<(), io::Error> {
+    match options.output_format {
+        OutputFormat::Text => {}
+        OutputFormat::Json => {
+            return writeln!(
+                out,
+                r#"{{"total":{{"bytes":{},"entries":{},"errors":{}}}}}"#,
+                total.num_bytes, total.num_entries, total.num_errors
+            )
+        }
+        OutputFormat::Csv { delimiter } => {
+            // the label takes the place of the name, leaving the columns describing paths empty
+            let label = csv_quoted(&total_line.label, delimiter);
+            return match subjects {
+                SubjectKind::Path => writeln!(
+                    out,
+                    "{1}{0}{0}{0}{2}{0}{3}{0}{4}",
+                    delimiter, label, total.num_bytes, total.num_entries, total.num_errors
+                ),
+                SubjectKind::Owner => writeln!(
+                    out,
+                    "{1}{0}{2}{0}{3}{0}{4}",
+                    delimiter, label, total.num_bytes, total.num_entries, total.num_errors
+                ),
+            };
+        }
+    }
+    let label_style = if total_line.bold {
+        format!("{}{}", style::Bold, color::Fg(color::Reset))
+    } else {
+        color::Fg(color::Reset).to_string()
+    };
+    write_path(
+        out,
+        &WalkOptions {
+            heatmap: None,
+            ..options.clone()
         },
-        byte_color = options.color.display(color::Fg(color::Green)),
+        path_column_width,
+        Subject::input(Path::new(&match total_line.shares {
+            true if options.error_annotation != ErrorAnnotation::Field => format!(
+                "{}{}",
+                total_line.label,
+                shares_of_total(root_sizes, total.num_bytes)
+            ),
+            _ => total_line.label.clone(),
+        })),
+        total,
+        label_style,
+    )
+}
+
+/// The amount of input paths whose share of the total is written after its label
+const MAX_SHARES: usize = 3;
+
+/// The shares of the largest `root_sizes` in `total_bytes`, like ` (a 60%, b 30%, 2 more 10%)`,
+/// or nothing if there are less than two of them or nothing to share.
+fn shares_of_total(root_sizes: &[(PathBuf, u64)], total_bytes: u64) -> String {
+    if root_sizes.len() < 2 || total_bytes == 0 {
+        return String::new();
+    }
+    let percent = |num_bytes: u64| num_bytes as f64 * 100.0 / total_bytes as f64;
+    let largest = root_sizes
+        .iter()
+        .sorted_by(|(lpath, lbytes), (rpath, rbytes)| {
+            rbytes.cmp(lbytes).then_with(|| lpath.cmp(rpath))
+        })
+        .collect::<Vec<_>>();
+    let mut shares = largest
+        .iter()
+        .take(MAX_SHARES)
+        .map(|(path, num_bytes)| format!("{} {:.0}%", path.display(), percent(*num_bytes)))
+        .collect::<Vec<_>>();
+    if largest.len() > MAX_SHARES {
+        let rest = &largest[MAX_SHARES..];
+        shares.push(format!(
+            "{} more {:.0}%",
+            rest.len(),
+            percent(
+                rest.iter()
+                    .fold(0, |sum, (_, num_bytes)| sum.saturating_add(*num_bytes))
+            )
+        ));
+    }
+    format!(" ({})", shares.join(", "))
+}
+
+/// What a line written by [`write_path()`] is about
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Subject<'a> {
+    /// A path `depth` levels below its input path. Its size includes `collapsed_levels` of directories below it,
+    /// which aren't written on their own.
+    Path {
+        path: &'a Path,
+        depth: usize,
+        collapsed_levels: usize,
+    },
+    /// The files of the user with the given name
+    Owner(&'a Path),
+}
+
+/// The kind of all [`Subject`]s written after a header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SubjectKind {
+    Path,
+    Owner,
+}
+
+impl<'a> Subject<'a> {
+    /// An input path, or any other path standing on its own.
+    pub(crate) fn input(path: &'a Path) -> Self {
+        Subject::Path {
+            path,
+            depth: 0,
+            collapsed_levels: 0,
+        }
+    }
+
+    /// The name written in text, which notes the collapsed levels after the path, like `dir (+3 levels)`.
+    pub(crate) fn text(&self) -> Cow<'a, Path> {
+        match *self {
+            Subject::Path {
+                path,
+                collapsed_levels: 0,
+                ..
+            }
+            | Subject::Owner(path) => Cow::Borrowed(path),
+            Subject::Path {
+                path,
+                collapsed_levels,
+                ..
+            } => {
+                let mut path = OsString::from(path);
+                path.push(format!(
+                    " (+{} level{})",
+                    collapsed_levels,
+                    if collapsed_levels == 1 { "" } else { "s" }
+                ));
+                Cow::Owned(path.into())
+            }
+        }
+    }
+}
+
+/// `s` as value of comma-separated values with `delimiter` in place of the comma, which is quoted if needed.
+fn csv_quoted(s: &str, delimiter: char) -> Cow<'_, str> {
+    if s.contains([delimiter, '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", s.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(s)
+    }
+}
+
+pub(crate) fn write_path<C: fmt::Display>(
+    out: &mut impl io::Write,
+    options: &WalkOptions,
+    path_column_width: usize,
+    subject: Subject,
+    aggregate: &PathAggregate,
+    path_color: C,
+) -> Result<(), io::Error> {
+    match (options.output_format, subject) {
+        (OutputFormat::Text, _) => {}
+        (
+            OutputFormat::Json,
+            Subject::Path {
+                path,
+                depth,
+                collapsed_levels,
+            },
+        ) => {
+            return writeln!(
+                out,
+                r#"{{"path":"{}","depth":{},"collapsed_levels":{},"bytes":{},"entries":{},"errors":{}}}"#,
+                json_escaped(&path.to_string_lossy()),
+                depth,
+                collapsed_levels,
+                aggregate.num_bytes,
+                aggregate.num_entries,
+                aggregate.num_errors
+            )
+        }
+        (OutputFormat::Json, Subject::Owner(name)) => {
+            return writeln!(
+                out,
+                r#"{{"owner":"{}","bytes":{},"entries":{},"errors":{}}}"#,
+                json_escaped(&name.to_string_lossy()),
+                aggregate.num_bytes,
+                aggregate.num_entries,
+                aggregate.num_errors
+            )
+        }
+        (
+            OutputFormat::Csv { delimiter },
+            Subject::Path {
+                path,
+                depth,
+                collapsed_levels,
+            },
+        ) => {
+            return writeln!(
+                out,
+                "{1}{0}{2}{0}{3}{0}{4}{0}{5}{0}{6}",
+                delimiter,
+                csv_quoted(&path.to_string_lossy(), delimiter),
+                depth,
+                collapsed_levels,
+                aggregate.num_bytes,
+                aggregate.num_entries,
+                aggregate.num_errors
+            )
+        }
+        (OutputFormat::Csv { delimiter }, Subject::Owner(name)) => {
+            return writeln!(
+                out,
+                "{1}{0}{2}{0}{3}{0}{4}",
+                delimiter,
+                csv_quoted(&name.to_string_lossy(), delimiter),
+                aggregate.num_bytes,
+                aggregate.num_entries,
+                aggregate.num_errors
+            )
+        }
+    }
+    let apparent_bytes = if options.both_sizes {
+        Cow::Owned(format!(
+            "{:>byte_column_width$} ",
+            options
+                .byte_format
+                .display(aggregate.num_apparent_bytes)
+                .grouped(options.thousands_separator)
+                .to_string(),
+            byte_column_width = options.byte_format.width()
+        ))
+    } else {
+        Cow::Borrowed("")
+    };
+    let savings = if options.estimate_compression {
+        Cow::Owned(format!(
+            " {:>byte_column_width$}",
+            options
+                .byte_format
+                .display(aggregate.num_estimated_savings)
+                .grouped(options.thousands_separator)
+                .to_string(),
+            byte_column_width = options.byte_format.width()
+        ))
+    } else {
+        Cow::Borrowed("")
+    };
+    let entries = if options.show_entry_count {
+        Cow::Owned(format!(
+            " {:>width$}",
+            match options.thousands_separator {
+                Some(separator) => group_digits(aggregate.num_entries, separator),
+                None => aggregate.num_entries.to_string(),
+            },
+            width = ENTRY_COUNT_WIDTH
+        ))
+    } else {
+        Cow::Borrowed("")
+    };
+    let exact_bytes = if options.verbose_size {
+        Cow::Owned(format!(
+            " ({} bytes)",
+            match options.thousands_separator {
+                Some(separator) => group_digits(aggregate.num_bytes, separator),
+                None => aggregate.num_bytes.to_string(),
+            }
+        ))
+    } else {
+        Cow::Borrowed("")
+    };
+    let sizes = format!(
+        "{byte_color}{}{:>byte_column_width$}{}{}{byte_color_reset}{}",
+        apparent_bytes,
+        options
+            .byte_format
+            .display(aggregate.num_bytes)
+            .grouped(options.thousands_separator)
+            .to_string(), // needed for formatting to work (unless we implement it ourselves)
+        exact_bytes,
+        savings,
+        entries,
+        byte_color = options.color.display(match &options.heatmap {
+            Some(heatmap) => color::Fg(heatmap.color_of(aggregate.num_bytes)).to_string(),
+            None => color::Fg(color::Green).to_string(),
+        }),
         byte_color_reset = options.color.display(color::Fg(color::Reset)),
-        path_color = options.color.display(path_color),
-        path_color_reset = options.color.display(color::Fg(color::Reset)),
         byte_column_width = options.byte_format.width()
-    )
+    );
+    let path = subject.text();
+    let path = path.as_ref();
+    let path_color = options.color.display(path_color);
+    let path_color_reset = options.color.display(style::Reset);
+    match options.column_order {
+        ColumnOrder::SizePath => {
+            write!(out, "{} {}", sizes, path_color)?;
+            write_path_bytes(out, path)?;
+            writeln!(
+                out,
+                "{}{}",
+                path_color_reset,
+                aggregate.error_annotation(options.error_annotation)
+            )
+        }
+        ColumnOrder::PathSize => {
+            write!(out, "{}", path_color)?;
+            write_path_bytes(out, path)?;
+            writeln!(
+                out,
+                "{}{:padding$} {}{}",
+                path_color_reset,
+                "",
+                sizes,
+                aggregate.error_annotation(options.error_annotation),
+                padding = path_column_width.saturating_sub(path.to_string_lossy().chars().count())
+            )
+        }
+    }
+}
+
+/// Write `path` as is, without replacing bytes which are not valid UTF-8 like its `Display` implementation does.
+#[cfg(unix)]
+pub(crate) fn write_path_bytes(out: &mut impl io::Write, path: &Path) -> Result<(), io::Error> {
+    use std::os::unix::ffi::OsStrExt;
+    out.write_all(path.as_os_str().as_bytes())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn write_path_bytes(out: &mut impl io::Write, path: &Path) -> Result<(), io::Error> {
+    write!(out, "{}", path.display())
 }
 
 /// Statistics obtained during a filesystem walk
 #[derive(Default, Debug)]
 pub struct Statistics {
-    /// The amount of entries we have seen during filesystem traversal
+    /// The amount of entries we have seen during filesystem traversal, which is the total of the
+    /// per-path entry counts plus the entries that could not be read.
     pub entries_traversed: u64,
-    /// The size of the smallest file encountered in bytes
+    /// The amount of IO errors encountered, like entries which could not be read
+    pub num_errors: u64,
+    /// The size of the smallest file encountered in bytes, only tracked if requested
     pub smallest_file_in_bytes: u64,
-    /// The size of the largest file encountered in bytes
+    /// The size of the largest file encountered in bytes, only tracked if requested
     pub largest_file_in_bytes: u64,
+    /// The amount of directories between an input path and the deepest entry below it, which is 0 for the input path itself
+    pub max_depth: usize,
+    /// The first entry encountered at `max_depth`, or `None` if no local path was walked
+    pub deepest_path: Option<PathBuf>,
+    /// The amount of entries which are not directories, like files and symbolic links
+    pub num_files: u64,
+    /// The amount of directories, including the input paths which are directories
+    pub num_directories: u64,
+    /// The amount of symbolic links to directories which weren't followed as they would have led to a directory
+    /// containing them or walked already
+    pub symlink_loops: u64,
+    /// `num_files` divided by `num_directories`, or 0 if there are no directories
+    pub files_per_directory: f64,
+    /// The average amount of entries directly within a directory, or 0 if there are no directories
+    pub average_fan_out: f64,
+    /// The time it took to walk each input path, the slowest first
+    pub time_per_root: Vec<(PathBuf, Duration)>,
+    /// The amount of nodes the tree of interactive mode would hold for the local input paths, one per readable entry
+    /// plus the root they are attached to. Only tracked if requested.
+    pub tree_nodes: u64,
+    /// An estimate of the memory in bytes these nodes would take, see [`traverse::estimated_tree_size()`]
+    pub estimated_tree_bytes: u64,
+}
+
+/// One line per statistic, like `files: 4`, with sizes in bytes.
+impl fmt::Display for Statistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        writeln!(f, "entries traversed: {}", self.entries_traversed)?;
+        writeln!(f, "IO errors: {}", self.num_errors)?;
+        writeln!(f, "files: {}", self.num_files)?;
+        writeln!(f, "directories: {}", self.num_directories)?;
+        writeln!(f, "symbolic link loops: {}", self.symlink_loops)?;
+        writeln!(f, "smallest file: {} bytes", self.smallest_file_in_bytes)?;
+        writeln!(f, "largest file: {} bytes", self.largest_file_in_bytes)?;
+        writeln!(f, "files per directory: {:.2}", self.files_per_directory)?;
+        writeln!(f, "average fan-out: {:.2}", self.average_fan_out)?;
+        write!(f, "max depth: {}", self.max_depth)?;
+        if let Some(ref path) = self.deepest_path {
+            write!(f, " at {}", path.display())?;
+        }
+        write!(
+            f,
+            "\ntree nodes: {} (about {} bytes in interactive mode)",
+            self.tree_nodes, self.estimated_tree_bytes
+        )?;
+        for (path, elapsed) in &self.time_per_root {
+            write!(f, "\ntime walking {}: {:?}", path.display(), elapsed)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{test_options, TempDir},
+        walk::{self, EntryMetadata, FakeFilesystem},
+        ByteFormat, Color, FileId, Heatmap, LsColors, Symlinks,
+    };
+
+    fn options(color: Color) -> WalkOptions {
+        WalkOptions {
+            color,
+            ..test_options()
+        }
+    }
+
+    #[test]
+    fn sizes_on_disk_hard_links_and_errors_are_taken_from_the_walk() -> Result<(), Error> {
+        let link = Some((
+            FileId {
+                device: 1,
+                inode: 1,
+            },
+            2,
+        ));
+        let file = |apparent_size, disk_size: Option<u64>, file_id_and_links| EntryMetadata {
+            apparent_size,
+            disk_size: disk_size.ok_or(ErrorClass::Other),
+            file_id_and_links,
+            ..Default::default()
+        };
+        let fs = FakeFilesystem::default()
+            .dir("root")
+            .file("root/file", file(10, Some(4096), None))
+            .dir("root/dir")
+            .file("root/dir/link", file(10, Some(8192), link))
+            .file("root/dir/unsized", file(5, None, None))
+            .unreadable("root/dir/unreadable")
+            .file("other/link", file(10, Some(8192), link));
+
+        let mut out = Vec::new();
+        let (res, stats) = aggregate_with(
+            &fs,
+            &mut out,
+            WalkOptions {
+                apparent_size: false,
+                ..options(Color::None)
+            },
+            Some(TotalLine::default()),
+            None,
+            AggregateOptions {
+                statistics: true,
+                ..Default::default()
+            },
+            ["root", "other"],
+        )?;
+
+        assert_eq!(res.num_errors, 2);
+        assert_eq!(stats.entries_traversed, 7);
+        assert_eq!((stats.num_files, stats.num_directories), (4, 2));
+        assert_eq!(stats.files_per_directory, 2.0);
+        assert_eq!(
+            stats.average_fan_out, 3.0,
+            "unreadable entries are within a directory, but neither file nor directory"
+        );
+        assert_eq!(stats.deepest_path, Some(PathBuf::from("root/dir/link")));
+        assert_eq!(res.to_string(), "12288 bytes, 2 IO Errors");
+        let mut roots: Vec<_> = stats.time_per_root.iter().map(|(p, _)| p).collect();
+        roots.sort();
+        assert_eq!(roots, vec![Path::new("other"), Path::new("root")]);
+        assert!(
+            stats.time_per_root[0].1 >= stats.time_per_root[1].1,
+            "the slowest root comes first"
+        );
+        let stats = Statistics {
+            time_per_root: vec![("root".into(), Duration::from_millis(3))],
+            estimated_tree_bytes: 1000,
+            ..stats
+        };
+        assert_eq!(
+            stats.to_string(),
+            "entries traversed: 7\n\
+             IO errors: 2\n\
+             files: 4\n\
+             directories: 2\n\
+             symbolic link loops: 0\n\
+             smallest file: 0 bytes\n\
+             largest file: 8192 bytes\n\
+             files per directory: 2.00\n\
+             average fan-out: 3.00\n\
+             max depth: 2 at root/dir/link\n\
+             tree nodes: 8 (about 1000 bytes in interactive mode)\n\
+             time walking root: 3ms"
+        );
+        assert_eq!(
+            String::from_utf8(out)?
+                .lines()
+                .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+                .collect::<Vec<_>>(),
+            vec![
+                "12288 b root <2 IO Errors>",
+                "0 b other",
+                "12288 b total <2 IO Errors across 1 path>"
+            ],
+            "the hard link in 'other' was already counted in 'root'"
+        );
+
+        let (_, stats) = aggregate_with(
+            &fs,
+            Vec::new(),
+            options(Color::None),
+            None,
+            None,
+            AggregateOptions::default(),
+            ["root", "other"],
+        )?;
+        assert_eq!(
+            (stats.smallest_file_in_bytes, stats.largest_file_in_bytes),
+            (0, 0),
+            "file sizes are only tracked when statistics are requested"
+        );
+        assert_eq!(stats.num_files, 4);
+        Ok(())
+    }
+
+    #[test]
+    fn errors_are_classified_and_only_the_fatal_kinds_end_the_walk() -> Result<(), Error> {
+        let fs = FakeFilesystem::default()
+            .dir("root")
+            .file(
+                "root/file",
+                EntryMetadata {
+                    apparent_size: 10,
+                    ..Default::default()
+                },
+            )
+            .unreadable("root/private")
+            .failing("root/vanished", ErrorClass::NotFound)
+            .failing("root/bad-sector", ErrorClass::Hardware)
+            .failing("root/huge", ErrorClass::OutOfMemory);
+        let walk = |fatal_errors: Vec<ErrorClass>| {
+            aggregate_with(
+                &fs,
+                Vec::new(),
+                WalkOptions {
+                    fatal_errors,
+                    ..options(Color::None)
+                },
+                None,
+                None,
+                AggregateOptions::default(),
+                ["root"],
+            )
+        };
+
+        let (res, _) = walk(Vec::new())?;
+        assert_eq!(
+            (res.num_errors, res.total_bytes),
+            (4, 10),
+            "all errors are counted by default"
+        );
+        let (res, _) = walk(vec![ErrorClass::Other])?;
+        assert_eq!(res.num_errors, 4, "errors of other kinds are still counted");
+
+        for (fatal, path) in &[
+            (ErrorClass::PermissionDenied, "root/private"),
+            (ErrorClass::NotFound, "root/vanished"),
+            (ErrorClass::Hardware, "root/bad-sector"),
+            (ErrorClass::OutOfMemory, "root/huge"),
+        ] {
+            let err = match walk(vec![ErrorClass::Other, *fatal]) {
+                Ok(_) => panic!("a fatal error ends the walk"),
+                Err(err) => err,
+            };
+            assert!(
+                err.to_string()
+                    .starts_with(&format!("Could not read the metadata of '{}'", path)),
+                "the message names the entry, but was: {}",
+                err
+            );
+        }
+
+        let fs = FakeFilesystem::default().dir("root").file(
+            "root/unsized",
+            EntryMetadata {
+                disk_size: Err(ErrorClass::Hardware),
+                ..Default::default()
+            },
+        );
+        let walk = |fatal_errors: Vec<ErrorClass>| {
+            aggregate_with(
+                &fs,
+                Vec::new(),
+                WalkOptions {
+                    apparent_size: false,
+                    fatal_errors,
+                    ..options(Color::None)
+                },
+                None,
+                None,
+                AggregateOptions::default(),
+                ["root"],
+            )
+        };
+        assert_eq!(walk(Vec::new())?.0.num_errors, 1);
+        match walk(vec![ErrorClass::Hardware]) {
+            Ok(_) => panic!("failing to determine the size on disk is fatal as well"),
+            Err(err) => assert!(err
+                .to_string()
+                .starts_with("Could not determine the size on disk of 'root/unsized'")),
+        }
+
+        let mut worrying = WorryingErrors::default();
+        for class in &[ErrorClass::PermissionDenied, ErrorClass::NotFound] {
+            let err = walk::error_of(*class);
+            worrying.record(ErrorClass::of(&err), Path::new("a"), &err);
+        }
+        assert_eq!(worrying.warning(), None, "these are expected");
+        for (class, path) in &[(ErrorClass::Hardware, "b"), (ErrorClass::OutOfMemory, "c")] {
+            let err = walk::error_of(*class);
+            worrying.record(ErrorClass::of(&err), Path::new(path), &err);
+        }
+        assert_eq!(
+            worrying.warning().map(|w| w.starts_with(
+                "2 IO errors may indicate failing hardware or a lack of memory, the first at 'b': "
+            )),
+            Some(true)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn json_lines_hold_sizes_in_bytes_regardless_of_the_format() -> Result<(), Error> {
+        let fs = FakeFilesystem::default()
+            .dir("a \"quoted\"")
+            .file(
+                "a \"quoted\"/file",
+                EntryMetadata {
+                    apparent_size: 1_500_000,
+                    uid: Some(0),
+                    ..Default::default()
+                },
+            )
+            .unreadable("b");
+
+        let mut out = Vec::new();
+        aggregate_with(
+            &fs,
+            &mut out,
+            WalkOptions {
+                byte_format: ByteFormat::Metric,
+                both_sizes: true,
+                output_format: OutputFormat::Json,
+                ..options(Color::Terminal)
+            },
+            Some(TotalLine::default()),
+            None,
+            AggregateOptions::default(),
+            ["a \"quoted\"", "b"],
+        )?;
+        assert_eq!(
+            String::from_utf8(out)?,
+            "{\"path\":\"a \\\"quoted\\\"\",\"depth\":0,\"collapsed_levels\":0,\"bytes\":1500000,\"entries\":2,\"errors\":0}\n\
+             {\"path\":\"b\",\"depth\":0,\"collapsed_levels\":0,\"bytes\":0,\"entries\":1,\"errors\":1}\n\
+             {\"total\":{\"bytes\":1500000,\"entries\":3,\"errors\":1}}\n",
+            "neither headers nor colors are written"
+        );
+
+        let mut out = Vec::new();
+        aggregate_with(
+            &fs,
+            &mut out,
+            WalkOptions {
+                output_format: OutputFormat::Json,
+                ..options(Color::None)
+            },
+            None,
+            None,
+            AggregateOptions {
+                by_owner: true,
+                ..Default::default()
+            },
+            ["a \"quoted\""],
+        )?;
+        assert!(
+            String::from_utf8(out)?.starts_with("{\"owner\":"),
+            "owners aren't written as paths"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn csv_rows_quote_paths_which_need_it_and_end_with_the_total() -> Result<(), Error> {
+        let file = |apparent_size| EntryMetadata {
+            apparent_size,
+            ..Default::default()
+        };
+        let fs = FakeFilesystem::default()
+            .file("plain", file(1_500_000))
+            .file("with;delimiter", file(2))
+            .file("with,comma \"and quotes\"", file(3))
+            .file("line\nbreak", file(4));
+
+        let mut out = Vec::new();
+        aggregate_with(
+            &fs,
+            &mut out,
+            WalkOptions {
+                byte_format: ByteFormat::Metric,
+                output_format: OutputFormat::Csv { delimiter: ';' },
+                ..options(Color::Terminal)
+            },
+            Some(TotalLine::default()),
+            None,
+            AggregateOptions::default(),
+            [
+                "plain",
+                "with;delimiter",
+                "with,comma \"and quotes\"",
+                "line\nbreak",
+            ],
+        )?;
+        assert_eq!(
+            String::from_utf8(out)?,
+            "path;depth;collapsed_levels;bytes;entries;errors\n\
+             plain;0;0;1500000;1;0\n\
+             \"with;delimiter\";0;0;2;1;0\n\
+             \"with,comma \"\"and quotes\"\"\";0;0;3;1;0\n\
+             \"line\nbreak\";0;0;4;1;0\n\
+             total;;;1500009;4;0\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn unsorted_paths_are_written_and_flushed_before_walking_the_next_one() -> Result<(), Error> {
+        use std::{cell::RefCell, rc::Rc};
+
+        #[derive(Clone, Default)]
+        struct SharedOut(Rc<RefCell<Vec<u8>>>);
+        impl io::Write for SharedOut {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.borrow_mut().write(buf)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        /// Remembers what was written when each path started to be walked
+        struct ObservingWalk {
+            fs: FakeFilesystem,
+            out: SharedOut,
+            written_before_walking: RefCell<Vec<String>>,
+        }
+        impl Walk for ObservingWalk {
+            fn entries<'a>(
+                &'a self,
+                path: &'a Path,
+                options: &'a WalkOptions,
+            ) -> Box<dyn Iterator<Item = Result<Entry, io::Error>> + 'a> {
+                self.written_before_walking
+                    .borrow_mut()
+                    .push(String::from_utf8_lossy(&self.out.0.borrow()).into_owned());
+                self.fs.entries(path, options)
+            }
+        }
+
+        let out = SharedOut::default();
+        let file = |apparent_size| EntryMetadata {
+            apparent_size,
+            ..Default::default()
+        };
+        let walk = ObservingWalk {
+            fs: FakeFilesystem::default()
+                .dir("large")
+                .file("large/file", file(1000))
+                .file("small", file(1)),
+            out: out.clone(),
+            written_before_walking: Default::default(),
+        };
+        aggregate_with(
+            &walk,
+            io::BufWriter::new(out),
+            options(Color::None),
+            Some(TotalLine::default()),
+            None,
+            AggregateOptions::default(),
+            ["large", "small"],
+        )?;
+        assert_eq!(
+            walk.written_before_walking.into_inner(),
+            vec!["", "      1000 b large\n"],
+            "the line of the first path isn't held back in the buffer"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rollups_sum_up_directories_within_the_paths_and_their_files() -> Result<(), Error> {
+        let file = |apparent_size| EntryMetadata {
+            apparent_size,
+            ..Default::default()
+        };
+        let fs = FakeFilesystem::default()
+            .dir("root")
+            .file("root/loose", file(1))
+            .dir("root/small")
+            .file("root/small/file", file(2))
+            .dir("root/large")
+            .dir("root/large/sub")
+            .file("root/large/sub/file", file(5))
+            .unreadable("root/large/unreadable")
+            .file("root/other-loose", file(3))
+            .file("single", file(7));
+
+        let mut out = Vec::new();
+        aggregate_with(
+            &fs,
+            &mut out,
+            options(Color::None),
+            Some(TotalLine::default()),
+            None,
+            AggregateOptions {
+                rollup: true,
+                ..Default::default()
+            },
+            ["root", "single"],
+        )?;
+        assert_eq!(
+            String::from_utf8(out)?
+                .lines()
+                .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+                .collect::<Vec<_>>(),
+            vec![
+                "7 b single",
+                "5 b root/large <1 IO Error>",
+                "4 b root/(files)",
+                "2 b root/small",
+                "18 b total <1 IO Error across 1 path>"
+            ],
+            "groups are sorted descending by size, and input files are their own group"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn heatmaps_color_sizes_relative_to_the_largest_path_but_not_the_total() -> Result<(), Error> {
+        let file = |apparent_size| EntryMetadata {
+            apparent_size,
+            ..Default::default()
+        };
+        let fs = FakeFilesystem::default()
+            .file("small", file(1))
+            .file("medium", file(5))
+            .file("large", file(9));
+
+        let run = |color| -> Result<Vec<String>, Error> {
+            let mut out = Vec::new();
+            aggregate_with(
+                &fs,
+                &mut out,
+                WalkOptions {
+                    heatmap: Some(Heatmap::default()),
+                    ..options(color)
+                },
+                Some(TotalLine::default()),
+                None,
+                AggregateOptions::default(),
+                ["small", "medium", "large"],
+            )?;
+            Ok(String::from_utf8(out)?
+                .lines()
+                .map(ToOwned::to_owned)
+                .collect())
+        };
+        let lines = run(Color::Terminal)?;
+        let byte_color =
+            |line: &str, value| line.contains(&color::Fg(color::AnsiValue(value)).to_string());
+        assert!(byte_color(&lines[0], 2), "small sizes are green");
+        assert!(byte_color(&lines[1], 3), "medium sizes are yellow");
+        assert!(byte_color(&lines[2], 1), "the largest size is red");
+        assert!(
+            lines[3].contains(&color::Fg(color::Green).to_string()),
+            "the total keeps its color"
+        );
+        assert!(
+            !run(Color::None)?.concat().contains('\x1b'),
+            "heatmaps are disabled without color"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn path_colors_are_derived_from_the_walk_without_querying_paths_again() -> Result<(), Error> {
+        let dir = TempDir::new("path-color")?;
+        std::fs::create_dir_all(dir.join("dir"))?;
+        let file = dir.join("file");
+        std::fs::write(&file, b"content")?;
+        let missing = dir.join("removed-before-printing");
+
+        let run = |color| -> Result<String, Error> {
+            let mut out = Vec::new();
+            aggregate(
+                &mut out,
+                options(color),
+                None,
+                None,
+                AggregateOptions::default(),
+                [&file, &dir.join("dir"), &missing],
+            )?;
+            Ok(String::from_utf8(out)?)
+        };
+        let colored = run(Color::Terminal);
+        let uncolored = run(Color::None);
+
+        let colored = colored?;
+        let lines: Vec<_> = colored.lines().collect();
+        let file_color = color::Fg(color::LightBlack).to_string();
+        assert!(lines[0].contains(&file_color), "files are dimmed");
+        assert!(!lines[1].contains(&file_color), "directories are not");
+        assert!(
+            !lines[2].contains(&file_color),
+            "paths without metadata are not considered files"
+        );
+        assert!(
+            !uncolored?.contains('\x1b'),
+            "no escape sequences are written without color"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn paths_are_colored_by_kind_and_name_with_ls_colors() -> Result<(), Error> {
+        let fs = FakeFilesystem::default()
+            .dir("dir")
+            .file("archive.tar", EntryMetadata::default())
+            .file(
+                "run.tar",
+                EntryMetadata {
+                    is_executable: true,
+                    ..Default::default()
+                },
+            )
+            .file("plain", EntryMetadata::default());
+        let mut out = Vec::new();
+        aggregate_with(
+            &fs,
+            &mut out,
+            WalkOptions {
+                ls_colors: Some(LsColors::parse("di=01;34:ex=01;32:*.tar=31")),
+                output_order: OutputOrder::Unsorted,
+                ..options(Color::Terminal)
+            },
+            None,
+            None,
+            AggregateOptions::default(),
+            ["dir", "archive.tar", "run.tar", "plain"],
+        )?;
+        let out = String::from_utf8(out)?;
+        let lines: Vec<_> = out.lines().collect();
+        assert!(lines[0].contains("\x1b[01;34mdir"), "{:?}", lines[0]);
+        assert!(lines[1].contains("\x1b[31marchive.tar"), "{:?}", lines[1]);
+        assert!(
+            lines[2].contains("\x1b[01;32mrun.tar"),
+            "executables keep their color whatever their name"
+        );
+        assert!(
+            lines[3].contains(&format!("{}plain", color::Fg(color::Reset))),
+            "files without a configured color aren't dimmed"
+        );
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symbolic_links_are_followed_as_configured() -> Result<(), Error> {
+        use std::os::unix::fs::symlink;
+
+        let dir = TempDir::new("symlinks")?;
+        std::fs::create_dir_all(dir.join("data/dir"))?;
+        std::fs::create_dir_all(dir.join("walked"))?;
+        std::fs::write(dir.join("data/dir/file"), [0; 100])?;
+        std::fs::write(dir.join("data/file"), [0; 10])?;
+        symlink("../data/dir", dir.join("walked/dir-link"))?;
+        symlink("../data/file", dir.join("walked/file-link"))?;
+        symlink("..", dir.join("walked/loop"))?;
+        symlink("walked", dir.join("root-link"))?;
+
+        let mut totals = Vec::new();
+        for &follow_root in &[false, true] {
+            for &follow_dirs in &[false, true] {
+                for &follow_files in &[false, true] {
+                    let symlinks = Symlinks {
+                        follow_root,
+                        follow_dirs,
+                        follow_files,
+                    };
+                    let res = aggregate(
+                        Vec::new(),
+                        WalkOptions {
+                            symlinks,
+                            ..options(Color::None)
+                        },
+                        None,
+                        None,
+                        AggregateOptions::default(),
+                        [dir.join("root-link")],
+                    );
+                    totals.push(res.map(|(res, _)| (symlinks, res.total_bytes)));
+                }
+            }
+        }
+
+        let totals = totals.into_iter().collect::<Result<Vec<_>, _>>()?;
+        let total = |follow_root, follow_dirs, follow_files| {
+            totals
+                .iter()
+                .find(|(s, _)| {
+                    *s == Symlinks {
+                        follow_root,
+                        follow_dirs,
+                        follow_files,
+                    }
+                })
+                .map(|(_, total)| *total)
+        };
+        for &follow_dirs in &[false, true] {
+            for &follow_files in &[false, true] {
+                assert_eq!(
+                    total(false, follow_dirs, follow_files),
+                    Some("walked".len() as u64),
+                    "an unfollowed root is only the link itself"
+                );
+            }
+        }
+        let links = ("../data/dir".len() + "../data/file".len() + "..".len()) as u64;
+        assert_eq!(
+            total(true, false, false),
+            Some(links),
+            "links below the root are counted by the length of the path they contain"
+        );
+        assert_eq!(
+            total(true, true, false),
+            Some(links - "../data/dir".len() as u64 + 100),
+            "linked directories are walked, but not the one containing the link"
+        );
+        assert_eq!(
+            total(true, false, true),
+            Some(links - "../data/file".len() as u64 + 10),
+            "linked files count with their size"
+        );
+        assert_eq!(
+            total(true, true, true),
+            Some(2 + 100 + 10),
+            "only the loop is left as link"
+        );
+
+        std::fs::create_dir_all(dir.join("mutual/x"))?;
+        std::fs::create_dir_all(dir.join("mutual/y"))?;
+        std::fs::write(dir.join("mutual/x/file"), [0; 10])?;
+        std::fs::write(dir.join("mutual/y/file"), [0; 100])?;
+        symlink("../y", dir.join("mutual/x/link"))?;
+        symlink("../x", dir.join("mutual/y/link"))?;
+        let mutual_total = |path: PathBuf| -> Result<(u64, u64), Error> {
+            let options = WalkOptions {
+                symlinks: Symlinks {
+                    follow_root: true,
+                    follow_dirs: true,
+                    follow_files: false,
+                },
+                ..options(Color::None)
+            };
+            let (res, stats) = aggregate(
+                Vec::new(),
+                options,
+                None,
+                None,
+                AggregateOptions::default(),
+                [path],
+            )?;
+            Ok((res.total_bytes, stats.symlink_loops))
+        };
+        assert_eq!(
+            mutual_total(dir.join("mutual"))?,
+            (10 + 100 + 2 * "../x".len() as u64, 2),
+            "directories linking to each other are walked once, leaving both links as they are and counting them as loops"
+        );
+        assert_eq!(
+            mutual_total(dir.join("mutual/x"))?,
+            (10 + 100 + "../x".len() as u64, 1),
+            "a linked directory is walked once, and the link back to the walked one is left as it is"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn cache_directories_are_skipped_only_if_their_tag_has_the_signature() -> Result<(), Error> {
+        let dir = TempDir::new("exclude-caches")?;
+        for (name, tag) in &[
+            (
+                "cache",
+                "Signature: 8a477f597d28d172789f06886806bc55\n# a cache",
+            ),
+            ("not-a-cache", "Signature: something else"),
+        ] {
+            std::fs::create_dir_all(dir.join(name))?;
+            std::fs::write(dir.join(name).join("CACHEDIR.TAG"), tag)?;
+            std::fs::write(dir.join(name).join("file"), b"content")?;
+        }
+
+        let run = |path: &Path| -> Result<String, Error> {
+            let mut out = Vec::new();
+            aggregate(
+                &mut out,
+                WalkOptions {
+                    exclude_caches: true,
+                    ..options(Color::None)
+                },
+                None,
+                None,
+                AggregateOptions::default(),
+                [path],
+            )?;
+            Ok(String::from_utf8(out)?)
+        };
+        let with_cache = run(&dir);
+        let cache_itself = run(&dir.join("cache"));
+
+        assert_eq!(
+            with_cache?.split_whitespace().next(),
+            Some("32"),
+            "only the tag and the file of the directory which isn't a cache are counted"
+        );
+        assert_eq!(
+            cache_itself?.split_whitespace().next(),
+            Some("60"),
+            "walked paths are never skipped"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn entry_counts_include_directories_and_are_totalled() -> Result<(), Error> {
+        let dir = TempDir::new("entry-count")?;
+        std::fs::create_dir_all(dir.join("sub"))?;
+        std::fs::write(dir.join("sub").join("file"), b"content")?;
+        std::fs::write(dir.join("file"), b"content")?;
+
+        let mut out = Vec::new();
+        let res = aggregate(
+            &mut out,
+            WalkOptions {
+                show_entry_count: true,
+                ..options(Color::None)
+            },
+            Some(TotalLine::default()),
+            None,
+            AggregateOptions::default(),
+            [&dir, &dir.join("sub")],
+        );
+        res?;
+
+        let out = String::from_utf8(out)?;
+        let entry_counts: Vec<_> = out
+            .lines()
+            .map(|line| line[ByteFormat::Bytes.width() + 1..][..ENTRY_COUNT_WIDTH].trim())
+            .collect();
+        assert_eq!(entry_counts, vec!["entries", "4", "2", "6"]);
+        Ok(())
+    }
+
+    #[test]
+    fn verbose_sizes_are_followed_by_the_exact_amount_of_bytes() -> Result<(), Error> {
+        let dir = TempDir::new("verbose-size")?;
+        std::fs::write(dir.join("file"), vec![0; 1500])?;
+
+        let mut out = Vec::new();
+        let res = aggregate(
+            &mut out,
+            WalkOptions {
+                byte_format: ByteFormat::Metric,
+                verbose_size: true,
+                thousands_separator: Some(','),
+                ..options(Color::None)
+            },
+            Some(TotalLine::default()),
+            None,
+            AggregateOptions::default(),
+            [&dir, &dir.join("file")],
+        );
+        res?;
+
+        let out = String::from_utf8(out)?;
+        let sizes: Vec<_> = out
+            .lines()
+            .map(|line| line.rsplit_once(' ').expect("a path").0.trim())
+            .collect();
+        assert_eq!(
+            sizes,
+            vec![
+                "1.50 KB (1,500 bytes)",
+                "1.50 KB (1,500 bytes)",
+                "3.00 KB (3,000 bytes)"
+            ],
+            "the total line follows the same format"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn paths_are_sorted_as_configured_after_picking_the_largest() -> Result<(), Error> {
+        let file = |apparent_size| EntryMetadata {
+            apparent_size,
+            ..Default::default()
+        };
+        let fs = FakeFilesystem::default()
+            .dir("a")
+            .file("a/1", file(5))
+            .file("a/2", file(5))
+            .file("a/3", file(5))
+            .file("b", file(20))
+            .dir("c")
+            .file("c/1", file(10))
+            .file("d", file(10))
+            .file("e", file(1));
+        let names = |output_order, top_n| -> Result<Vec<String>, Error> {
+            let mut out = Vec::new();
+            aggregate_with(
+                &fs,
+                &mut out,
+                WalkOptions {
+                    output_order,
+                    ..options(Color::None)
+                },
+                None,
+                top_n,
+                AggregateOptions::default(),
+                ["e", "d", "c", "b", "a"],
+            )?;
+            Ok(String::from_utf8(out)?
+                .lines()
+                .map(|line| line.rsplit(' ').next().expect("a path").to_owned())
+                .collect())
+        };
+
+        assert_eq!(
+            names(OutputOrder::SizeAscending, None)?,
+            vec!["e", "c", "d", "a", "b"]
+        );
+        assert_eq!(
+            names(OutputOrder::SizeDescending, None)?,
+            vec!["b", "a", "c", "d", "e"],
+            "paths of equal size are still in alphabetical order"
+        );
+        assert_eq!(
+            names(OutputOrder::PathAscending, None)?,
+            vec!["a", "b", "c", "d", "e"]
+        );
+        assert_eq!(
+            names(OutputOrder::EntryCountAscending, None)?,
+            vec!["b", "d", "e", "c", "a"]
+        );
+        assert_eq!(names(OutputOrder::SizeDescending, Some(2))?, vec!["b", "a"]);
+        assert_eq!(
+            names(OutputOrder::PathAscending, Some(3))?,
+            vec!["a", "b", "d"],
+            "the largest paths are picked before sorting them"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn paths_of_equal_size_are_sorted_alphabetically() -> Result<(), Error> {
+        let dir = TempDir::new("equal-sizes")?;
+        for name in &["c", "a", "d", "b"] {
+            std::fs::create_dir_all(dir.join(name))?;
+            std::fs::write(dir.join(name).join("file"), b"content")?;
+        }
+        std::fs::write(dir.join("d").join("other"), b"more")?;
+
+        let mut out = Vec::new();
+        let res = aggregate(
+            &mut out,
+            WalkOptions {
+                output_order: OutputOrder::SizeAscending,
+                ..options(Color::None)
+            },
+            None,
+            None,
+            AggregateOptions::default(),
+            ["d", "c", "a", "b"].iter().map(|name| dir.join(name)),
+        );
+        res?;
+
+        let names: Vec<_> = String::from_utf8(out)?
+            .lines()
+            .map(|line| line.rsplit('/').next().expect("a path").to_owned())
+            .collect();
+        assert_eq!(names, vec!["a", "b", "c", "d"]);
+        Ok(())
+    }
+
+    #[test]
+    fn sizes_saturate_instead_of_wrapping_around() {
+        let mut total = PathAggregate::default();
+        total.add_sizes(u64::MAX - 1, 1, 0);
+        assert!(!total.is_saturated, "u64::MAX itself can be represented");
+
+        let mut aggregate = PathAggregate::default();
+        aggregate.add_sizes(2, u64::MAX, 0);
+        total.add(&aggregate);
+        assert_eq!(total.num_bytes, u64::MAX);
+        assert_eq!(total.num_apparent_bytes, u64::MAX);
+        assert!(total.is_saturated);
+
+        let mut other_total = PathAggregate::default();
+        other_total.add(&total);
+        assert!(other_total.is_saturated, "saturation is passed on");
+    }
+
+    #[test]
+    fn statistics_contain_the_first_of_the_deepest_paths() -> Result<(), Error> {
+        let dir = TempDir::new("max-depth")?;
+        std::fs::create_dir_all(dir.join("a").join("b"))?;
+        std::fs::create_dir_all(dir.join("c").join("d"))?;
+        std::fs::write(dir.join("a").join("b").join("file"), b"content")?;
+        std::fs::write(dir.join("c").join("d").join("file"), b"content")?;
+
+        let res = aggregate(
+            Vec::new(),
+            options(Color::None),
+            None,
+            None,
+            AggregateOptions::default(),
+            [dir.join("c"), dir.join("a")],
+        );
+        let (_, stats) = res?;
+
+        assert_eq!(
+            stats.max_depth, 2,
+            "the depth is relative to each input path"
+        );
+        assert_eq!(
+            stats.deepest_path,
+            Some(dir.join("c").join("d").join("file")),
+            "later paths at the same depth don't replace it"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn statistics_estimate_the_size_of_the_tree_of_interactive_mode() -> Result<(), Error> {
+        let dir = TempDir::new("tree-size")?;
+        std::fs::create_dir_all(dir.join("sub"))?;
+        std::fs::write(dir.join("sub").join("file"), b"content")?;
+        std::fs::write(dir.join("other"), b"content")?;
+
+        let stats_of = |statistics| {
+            aggregate(
+                io::sink(),
+                options(Color::None),
+                None,
+                None,
+                AggregateOptions {
+                    statistics,
+                    ..Default::default()
+                },
+                [&dir, &dir.join("sub")],
+            )
+            .map(|(_, stats)| stats)
+        };
+        let stats = stats_of(true);
+        let untracked = stats_of(false);
+        let tree = traverse::Traversal::from_walk(
+            options(Color::None),
+            vec![dir.clone(), dir.join("sub")],
+            |_| Ok(()),
+        );
+
+        let stats = stats?;
+        assert_eq!(stats.tree_nodes, tree?.tree.node_count() as u64);
+        let num_name_bytes = 2 * dir.as_os_str().len()
+            + "sub".len()
+            + "/sub".len()
+            + 2 * "file".len()
+            + "other".len();
+        assert_eq!(
+            stats.estimated_tree_bytes,
+            traverse::estimated_tree_size(stats.tree_nodes, num_name_bytes as u64)
+        );
+        let untracked = untracked?;
+        assert_eq!(
+            (untracked.tree_nodes, untracked.estimated_tree_bytes),
+            (0, 0)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn only_the_total_is_written_if_it_is_the_only_line() -> Result<(), Error> {
+        let dir = TempDir::new("total-only")?;
+        std::fs::write(dir.join("file"), b"content")?;
+
+        let mut out = Vec::new();
+        let res = aggregate(
+            &mut out,
+            options(Color::None),
+            Some(TotalLine {
+                only: true,
+                ..Default::default()
+            }),
+            None,
+            AggregateOptions::default(),
+            [dir.join("file"), dir.join("missing")],
+        );
+        assert_eq!(res?.0.num_errors, 1);
+
+        assert_eq!(
+            String::from_utf8(out)?,
+            format!(
+                "{:>width$} total  <1 IO Error across 1 path>\n",
+                "7 b",
+                width = ByteFormat::Bytes.width()
+            ),
+            "errors of all paths are noted on the total line"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn the_total_notes_the_shares_of_the_largest_paths() -> Result<(), Error> {
+        let dir = TempDir::new("total-shares")?;
+        let paths = [("a", 20), ("b", 60), ("c", 4), ("d", 10), ("e", 6)]
+            .iter()
+            .map(|(name, len)| {
+                std::fs::write(dir.join(name), vec![0; *len])?;
+                Ok(dir.join(name))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let total_of = |paths: &[PathBuf], error_annotation| -> Result<String, Error> {
+            let mut out = Vec::new();
+            aggregate(
+                &mut out,
+                WalkOptions {
+                    apparent_size: true,
+                    error_annotation,
+                    ..options(Color::None)
+                },
+                Some(TotalLine {
+                    only: true,
+                    shares: true,
+                    ..Default::default()
+                }),
+                None,
+                AggregateOptions::default(),
+                paths,
+            )?;
+            let out = String::from_utf8(out)?;
+            Ok(out
+                .trim()
+                .splitn(3, ' ')
+                .last()
+                .unwrap_or_default()
+                .to_owned())
+        };
+        let all = total_of(&paths, ErrorAnnotation::Human);
+        let two = total_of(&paths[..2], ErrorAnnotation::Human);
+        let one = total_of(&paths[..1], ErrorAnnotation::Human);
+        let porcelain = total_of(&paths, ErrorAnnotation::Field);
+
+        let name = |name: &str| dir.join(name).display().to_string();
+        assert_eq!(
+            all?,
+            format!(
+                "total ({} 60%, {} 20%, {} 10%, 2 more 10%)",
+                name("b"),
+                name("a"),
+                name("d")
+            ),
+            "only the largest paths are named"
+        );
+        assert_eq!(
+            two?,
+            format!("total ({} 75%, {} 25%)", name("b"), name("a"))
+        );
+        assert_eq!(one?, "total", "a single path is all of the total");
+        assert_eq!(
+            porcelain?, "total\t0",
+            "shares don't get in the way of parsing the output"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn files_outside_of_the_size_band_are_not_counted() -> Result<(), Error> {
+        let dir = TempDir::new("size-band")?;
+        for (name, len) in &[("small", 10), ("medium", 100), ("large", 1000)] {
+            std::fs::write(dir.join(name), vec![0; *len])?;
+        }
+
+        let size_of = |larger_than, smaller_than| -> Result<(u64, u64), Error> {
+            let (res, stats) = aggregate(
+                io::sink(),
+                WalkOptions {
+                    apparent_size: true,
+                    exclude_larger_than: larger_than,
+                    exclude_smaller_than: smaller_than,
+                    ..options(Color::None)
+                },
+                None,
+                None,
+                AggregateOptions::default(),
+                [&dir],
+            )?;
+            Ok((res.total_bytes, stats.entries_traversed))
+        };
+        let unfiltered = size_of(None, None);
+        let small = size_of(Some(500), None);
+        let large = size_of(None, Some(500));
+        let band = size_of(Some(500), Some(50));
+
+        assert_eq!(unfiltered?, (1110, 4));
+        assert_eq!(small?, (110, 4), "excluded files are still visited");
+        assert_eq!(large?, (1000, 4));
+        assert_eq!(band?, (100, 4), "both bounds select a band of sizes");
+        Ok(())
+    }
+
+    #[test]
+    fn walks_stop_once_their_time_is_up() -> Result<(), Error> {
+        let dir = TempDir::new("max-time")?;
+        std::fs::write(dir.join("file"), b"content")?;
+
+        let walk = |max_time| {
+            let mut out = Vec::new();
+            aggregate(
+                &mut out,
+                WalkOptions {
+                    apparent_size: true,
+                    max_time,
+                    ..options(Color::None)
+                },
+                Some(TotalLine::default()),
+                None,
+                AggregateOptions::default(),
+                [&dir, &dir],
+            )
+            .map(|(res, _)| (res, out))
+        };
+        let unlimited = walk(None);
+        let generous = walk(Some(Duration::from_secs(3600)));
+        let no_time = walk(Some(Duration::from_secs(0)));
+
+        let (res, _) = unlimited?;
+        assert_eq!((res.is_truncated, res.total_bytes), (false, 14));
+        assert!(res.warnings.is_empty());
+        let (res, _) = generous?;
+        assert_eq!((res.is_truncated, res.total_bytes), (false, 14));
+        let (res, out) = no_time?;
+        assert_eq!(
+            (res.is_truncated, res.total_bytes, String::from_utf8(out)?),
+            (true, 0, String::new()),
+            "paths which weren't reached are left out"
+        );
+        assert_eq!(
+            res.warnings,
+            vec!["the walk was stopped as its time was up, so sizes are too small and 0 of 2 input paths were walked"],
+            "the warning is left to the caller to show"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn empty_paths_are_skipped_but_counted_in_the_total() -> Result<(), Error> {
+        let dir = TempDir::new("skip-empty")?;
+        std::fs::create_dir_all(dir.join("empty"))?;
+        std::fs::create_dir_all(dir.join("full"))?;
+        std::fs::write(dir.join("full").join("file"), b"content")?;
+
+        let mut out = Vec::new();
+        let res = aggregate(
+            &mut out,
+            WalkOptions {
+                show_entry_count: true,
+                ..options(Color::None)
+            },
+            Some(TotalLine::default()),
+            None,
+            AggregateOptions {
+                skip_empty: true,
+                ..Default::default()
+            },
+            [dir.join("empty"), dir.join("full")],
+        );
+        res?;
+
+        let lines: Vec<_> = String::from_utf8(out)?
+            .lines()
+            .skip(1)
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                format!("7 b 2 {}", dir.join("full").display()),
+                "7 b 3 total".to_owned()
+            ],
+            "the entries of the empty path are part of the total"
+        );
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn paths_which_are_not_utf8_are_written_as_is() -> Result<(), Error> {
+        use std::{ffi::OsStr, fs, os::unix::ffi::OsStrExt};
+
+        let dir = TempDir::new("non-utf8")?;
+        let path = dir.join(OsStr::from_bytes(b"invalid-\xff\xfe-utf8"));
+        fs::write(&path, b"content")?;
+
+        let mut out = Vec::new();
+        let res = aggregate(
+            &mut out,
+            options(Color::None),
+            Some(TotalLine::default()),
+            None,
+            AggregateOptions::default(),
+            [&path],
+        );
+        res?;
+
+        let mut expected =
+            format!("{:>width$} ", "7 b", width = ByteFormat::Bytes.width()).into_bytes();
+        expected.extend_from_slice(path.as_os_str().as_bytes());
+        expected.push(b'\n');
+        assert_eq!(out, expected);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hard_links_across_roots_are_counted_once_by_the_root_walked_first() -> Result<(), Error> {
+        use std::fs;
+
+        let dir = TempDir::new("hard-links")?;
+        fs::create_dir_all(dir.join("first"))?;
+        fs::create_dir_all(dir.join("second"))?;
+        fs::write(dir.join("first").join("file"), b"content")?;
+        fs::hard_link(
+            dir.join("first").join("file"),
+            dir.join("second").join("link"),
+        )?;
+        fs::write(dir.join("second").join("other"), b"12")?;
+
+        let run = |count_hard_links| -> Result<Vec<String>, Error> {
+            let mut out = Vec::new();
+            aggregate(
+                &mut out,
+                WalkOptions {
+                    count_hard_links,
+                    ..options(Color::None)
+                },
+                Some(TotalLine::default()),
+                None,
+                AggregateOptions::default(),
+                [dir.join("second"), dir.join("first")],
+            )?;
+            Ok(String::from_utf8(out)?
+                .lines()
+                .map(|line| line[..ByteFormat::Bytes.width()].trim().to_owned())
+                .collect())
+        };
+        let unique = run(false);
+        let all = run(true);
+
+        assert_eq!(
+            unique?,
+            vec!["9 b", "0 b", "9 b"],
+            "the link is counted by 'second' as it is walked first, and the total is the sum of unique inodes"
+        );
+        assert_eq!(all?, vec!["9 b", "7 b", "16 b"]);
+        Ok(())
+    }
+
+    #[test]
+    fn walk_errors_below_the_root_mark_the_path_as_partial() {
+        let mut aggregate = PathAggregate::default();
+        aggregate.record_walk_error(true);
+        assert_eq!(aggregate.num_errors, 1);
+        assert_eq!(
+            aggregate.num_inaccessible_dirs, 0,
+            "an inaccessible root is not a partial result"
+        );
+        assert_eq!(
+            aggregate.error_annotation(ErrorAnnotation::Human),
+            "  <1 IO Error>"
+        );
+
+        let mut aggregate = PathAggregate {
+            num_bytes: 1000,
+            num_errors: 1,
+            ..Default::default()
+        };
+        aggregate.record_walk_error(false);
+        aggregate.record_walk_error(false);
+        assert_eq!(aggregate.num_errors, 3);
+        assert_eq!(aggregate.num_inaccessible_dirs, 2);
+        assert_eq!(
+            aggregate.error_annotation(ErrorAnnotation::Human),
+            "  <3 IO Errors, partial: 2 inaccessible directories>"
+        );
+        assert_eq!(aggregate.error_annotation(ErrorAnnotation::Field), "\t3");
+        assert_eq!(aggregate.error_annotation(ErrorAnnotation::None), "");
+        assert_eq!(
+            PathAggregate::default().error_annotation(ErrorAnnotation::Field),
+            "\t0",
+            "the field is written even without errors to keep the amount of fields stable"
+        );
+    }
+
+    #[test]
+    fn totals_carry_partial_paths() {
+        let mut partial = PathAggregate::default();
+        partial.record_walk_error(false);
+        let mut total = PathAggregate {
+            num_bytes: 5,
+            ..Default::default()
+        };
+        total.add(&partial);
+        total.add(&PathAggregate {
+            num_bytes: 10,
+            num_errors: 1,
+            ..Default::default()
+        });
+        assert_eq!(
+            total,
+            PathAggregate {
+                num_bytes: 15,
+                num_apparent_bytes: 0,
+                num_estimated_savings: 0,
+                num_entries: 0,
+                num_errors: 2,
+                num_inaccessible_dirs: 1,
+                num_paths_with_errors: 0,
+                is_saturated: false,
+            }
+        );
+        assert_eq!(
+            total.error_annotation(ErrorAnnotation::Human),
+            "  <2 IO Errors, partial: 1 inaccessible directory>"
+        );
+    }
+
+    #[test]
+    fn totals_of_input_paths_count_the_ones_with_errors() {
+        let mut total = PathAggregate::default();
+        let mut partial = PathAggregate::default();
+        partial.record_walk_error(false);
+        partial.num_errors += 10;
+        total.add_path(&partial);
+        total.add_path(&PathAggregate {
+            num_bytes: 10,
+            ..Default::default()
+        });
+        total.add_path(&PathAggregate {
+            num_errors: 1,
+            ..Default::default()
+        });
+        assert_eq!(total.num_paths_with_errors, 2);
+        assert_eq!(
+            total.error_annotation(ErrorAnnotation::Human),
+            "  <12 IO Errors across 2 paths, partial: 1 inaccessible directory>"
+        );
+        assert_eq!(
+            total.error_annotation(ErrorAnnotation::Field),
+            "\t12",
+            "the amount of paths is no field of its own"
+        );
+
+        let mut other_total = PathAggregate::default();
+        other_total.add(&total);
+        assert_eq!(
+            other_total.num_paths_with_errors, 2,
+            "totals of totals keep the amount of paths with errors"
+        );
+    }
 }
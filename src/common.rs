@@ -1,7 +1,27 @@
-use crate::traverse::{EntryData, Tree, TreeIndex};
+use crate::{
+    glob::Glob,
+    heatmap::Heatmap,
+    inodefilter::FileId,
+    locale::group_digits,
+    ls_colors::LsColors,
+    time::TimeStyle,
+    traverse::{EntryData, Tree, TreeIndex},
+};
 use byte_unit::{n_gb_bytes, n_gib_bytes, n_mb_bytes, n_mib_bytes, ByteUnit};
-use jwalk::WalkDir;
-use std::{fmt, path::Path};
+use jwalk::{DirEntry, ReadDirSpec, WalkDir};
+use std::{
+    collections::HashSet,
+    fmt, fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// The file marking a directory as cache, as described in <https://bford.info/cachedir/>
+const CACHEDIR_TAG: &str = "CACHEDIR.TAG";
+/// The beginning of each valid `CACHEDIR.TAG` file, to not skip directories with a tag file of the same name by accident
+const CACHEDIR_TAG_SIGNATURE: &[u8] = b"Signature: 8a477f597d28d172789f06886806bc55";
 
 pub fn get_entry_or_panic(tree: &Tree, node_idx: TreeIndex) -> &EntryData {
     tree.node_weight(node_idx)
@@ -13,7 +33,7 @@ pub(crate) fn get_size_or_panic(tree: &Tree, node_idx: TreeIndex) -> u64 {
 }
 
 /// Specifies a way to format bytes
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ByteFormat {
     /// metric format, based on 1000.
     Metric,
@@ -53,17 +73,49 @@ impl ByteFormat {
             }
             + THE_SPACE_BETWEEN_UNIT_AND_NUMBER
     }
+    /// Turn a format with a unit that adapts to each byte count into the one with the fixed unit appropriate for `bytes`,
+    /// so that byte counts up to `bytes` are displayed in the same unit. Formats with a fixed unit are returned as is.
+    pub fn with_fixed_unit_for(self, bytes: u64) -> ByteFormat {
+        use ByteFormat::*;
+        match self {
+            Metric if u128::from(bytes) >= n_gb_bytes!(1) => GB,
+            Metric => MB,
+            Binary if u128::from(bytes) >= n_gib_bytes!(1) => GiB,
+            Binary => MiB,
+            format => format,
+        }
+    }
+    /// Display `bytes` in this format, exactly like `dua` writes sizes but without padding.
+    ///
+    /// ```
+    /// use dua::ByteFormat;
+    ///
+    /// assert_eq!(ByteFormat::Metric.display(1_500_000).to_string(), "1.50 MB");
+    /// assert_eq!(ByteFormat::Binary.display(1_500_000).to_string(), "1.43 MiB");
+    /// assert_eq!(ByteFormat::Bytes.display(1_500_000).grouped(Some(',')).to_string(), "1,500,000 b");
+    /// ```
     pub fn display(self, bytes: u64) -> ByteFormatDisplay {
         ByteFormatDisplay {
             format: self,
             bytes,
+            thousands_separator: None,
         }
     }
 }
 
+/// A byte count displayed in a [`ByteFormat`], as obtained by [`ByteFormat::display()`]
 pub struct ByteFormatDisplay {
     format: ByteFormat,
     bytes: u64,
+    thousands_separator: Option<char>,
+}
+
+impl ByteFormatDisplay {
+    /// If set, group the digits of plain byte counts by thousands using `separator`.
+    pub fn grouped(mut self, separator: Option<char>) -> Self {
+        self.thousands_separator = separator;
+        self
+    }
 }
 
 impl fmt::Display for ByteFormatDisplay {
@@ -72,7 +124,12 @@ impl fmt::Display for ByteFormatDisplay {
         use ByteFormat::*;
 
         let format = match self.format {
-            Bytes => return write!(f, "{} b", self.bytes),
+            Bytes => {
+                return match self.thousands_separator {
+                    Some(separator) => write!(f, "{} b", group_digits(self.bytes, separator)),
+                    None => write!(f, "{} b", self.bytes),
+                }
+            }
             Binary => (true, None),
             Metric => (false, None),
             GB => (false, Some((n_gb_bytes!(1), ByteUnit::GB))),
@@ -106,11 +163,105 @@ impl fmt::Display for ByteFormatDisplay {
     }
 }
 
-/// Identify the kind of sorting to apply during filesystem iteration
-#[derive(Clone)]
-pub enum TraversalSorting {
+/// `bytes` formatted like `dua` writes sizes but without padding, for use without walking anything.
+///
+/// ```
+/// use dua::{format_bytes, ByteFormat};
+///
+/// assert_eq!(format_bytes(1_500_000, ByteFormat::Metric), "1.50 MB");
+/// assert_eq!(format_bytes(1_500_000, ByteFormat::Binary), "1.43 MiB");
+/// assert_eq!(format_bytes(1_500_000, ByteFormat::GiB), "0.00 GiB");
+/// assert_eq!(format_bytes(1_500_000, ByteFormat::Bytes), "1500000 b");
+/// ```
+pub fn format_bytes(bytes: u64, format: ByteFormat) -> String {
+    format.display(bytes).to_string()
+}
+
+/// Specify the order in which aggregated paths are written, regardless of the order they were walked in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputOrder {
+    /// Paths are written in the order they were given
+    Unsorted,
+    /// Paths are written in the order of their size, ascending
+    SizeAscending,
+    /// Like `SizeAscending`, but the largest path comes first
+    SizeDescending,
+    /// Paths are written in alphabetical order
+    PathAscending,
+    /// Paths are written in the order of the amount of entries within them, ascending
+    EntryCountAscending,
+}
+
+/// Specify the order of the columns when writing a line per path
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColumnOrder {
+    /// The size(s) followed by the path, like `du` does
+    SizePath,
+    /// The path followed by the size(s), with paths padded to align the size columns
+    PathSize,
+}
+
+/// Specify how to note IO errors encountered while walking a path, at the end of its line
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorAnnotation {
+    /// A description like `  <2 IO Errors>`, only if there were errors
+    Human,
+    /// The amount of errors as the last field separated by a tab, on every line
+    Field,
+    /// Nothing at all
     None,
-    AlphabeticalByFileName,
+}
+
+/// Specify how to write the line of each path
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    /// Columns for humans, as configured by the other options
+    Text,
+    /// A JSON object per line like `{"path":"a","bytes":4096,"errors":0}` with sizes in bytes, and
+    /// `{"total":{"bytes":4096,"errors":0}}` for the total
+    Json,
+    /// Comma-separated values like `a,4096,0` with sizes in bytes, headed by `path,bytes,errors`, with the given
+    /// character in place of the comma. Paths containing it, quotes or line breaks are quoted.
+    Csv { delimiter: char },
+}
+
+/// What an IO error encountered while walking may indicate, to tell expected errors from worrying ones
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ErrorClass {
+    /// Permissions are lacking, which is expected when walking directories of other users
+    PermissionDenied,
+    /// The entry was removed while walking
+    NotFound,
+    /// The system ran out of memory
+    OutOfMemory,
+    /// The device failed to read, which may indicate failing hardware, like `EIO`
+    Hardware,
+    /// Anything else
+    Other,
+}
+
+impl ErrorClass {
+    /// The class of `err`.
+    pub fn of(err: &io::Error) -> Self {
+        /// The code of `EIO`, which is the same on all Unix systems we know of
+        #[cfg(unix)]
+        const EIO: i32 = 5;
+        #[cfg(unix)]
+        if err.raw_os_error() == Some(EIO) {
+            return ErrorClass::Hardware;
+        }
+        match err.kind() {
+            io::ErrorKind::PermissionDenied => ErrorClass::PermissionDenied,
+            io::ErrorKind::NotFound => ErrorClass::NotFound,
+            io::ErrorKind::OutOfMemory => ErrorClass::OutOfMemory,
+            _ => ErrorClass::Other,
+        }
+    }
+
+    /// If set, errors of this class hint at a problem with the system rather than with the walked entries.
+    pub fn is_worrying(self) -> bool {
+        matches!(self, ErrorClass::OutOfMemory | ErrorClass::Hardware)
+    }
 }
 
 /// Specify the kind of color to use
@@ -145,6 +296,96 @@ where
     }
 }
 
+/// Which symbolic links are followed, instead of counting the size of the link itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Symlinks {
+    /// Walk what the input paths point to if they are symbolic links
+    pub follow_root: bool,
+    /// Walk the directories symbolic links below the input paths point to, unless they contain the link
+    pub follow_dirs: bool,
+    /// Count the size of the files symbolic links below the input paths point to
+    pub follow_files: bool,
+}
+
+/// Follow symbolic links given as input paths only, like `du -H`.
+impl Default for Symlinks {
+    fn default() -> Self {
+        Symlinks {
+            follow_root: true,
+            follow_dirs: false,
+            follow_files: false,
+        }
+    }
+}
+
+impl Symlinks {
+    /// The metadata of what the symbolic link at `path` below an input path points to, if it is to be followed.
+    /// Links to directories containing them are never followed, as they would be walked forever.
+    pub(crate) fn followed_target(&self, path: &Path) -> Option<fs::Metadata> {
+        if !(self.follow_dirs || self.follow_files) {
+            return None;
+        }
+        let target = fs::metadata(path).ok()?;
+        if target.is_dir() {
+            if !self.follow_dirs {
+                return None;
+            }
+            let leads_to_parent =
+                match (path.parent().map(fs::canonicalize), fs::canonicalize(path)) {
+                    (Some(Ok(parent)), Ok(target)) => parent.starts_with(target),
+                    _ => true,
+                };
+            if leads_to_parent {
+                log::warn!(
+                    "not following the symbolic link {} as it leads to a directory containing it",
+                    path.display()
+                );
+                return None;
+            }
+            Some(target)
+        } else {
+            Some(target).filter(|_| self.follow_files)
+        }
+    }
+
+    /// Make `entry` describe what it points to if it is a symbolic link to be followed, and have it walked if that
+    /// is a directory which isn't among the `visited` ones yet. Links to directories walked already, like those
+    /// linking to each other, are left as they are so nothing is counted twice.
+    fn follow(&self, entry: &mut DirEntry, visited: &Mutex<HashSet<FileId>>) {
+        if !entry.file_type.as_ref().is_ok_and(|t| t.is_symlink()) {
+            return;
+        }
+        let path = entry.path();
+        match self.followed_target(&path) {
+            Some(target) if target.is_dir() && !Self::visit(visited, &target) => {
+                log::warn!(
+                    "not following the symbolic link {} as it leads to a directory walked already",
+                    path.display()
+                );
+            }
+            Some(target) => {
+                log::debug!("following the symbolic link {}", path.display());
+                if target.is_dir() {
+                    entry.content_spec = Some(Arc::new(ReadDirSpec::new(path, entry.depth, None)));
+                }
+                entry.file_type = Ok(target.file_type());
+                entry.metadata = Some(Ok(target));
+            }
+            None => log::trace!("not following the symbolic link {}", path.display()),
+        }
+    }
+
+    /// Add the directory `metadata` belongs to to the `visited` ones, and return false if it was among them already.
+    pub(crate) fn visit(visited: &Mutex<HashSet<FileId>>, metadata: &fs::Metadata) -> bool {
+        FileId::of(metadata).is_none_or(|id| {
+            visited
+                .lock()
+                .expect("no panic while holding the lock")
+                .insert(id)
+        })
+    }
+}
+
 /// Configures a filesystem walk, including output and formatting options.
 #[derive(Clone)]
 pub struct WalkOptions {
@@ -154,26 +395,200 @@ pub struct WalkOptions {
     pub byte_format: ByteFormat,
     pub count_hard_links: bool,
     pub apparent_size: bool,
+    /// If set, the apparent size is shown next to the disk usage. Implies `apparent_size` is not set.
+    pub both_sizes: bool,
+    /// If set, all sizes are displayed in the unit of the largest of them, as determined by [`ByteFormat::with_fixed_unit_for()`].
+    pub shared_unit: bool,
+    /// If set, the amount of files and directories is shown next to the size.
+    pub show_entry_count: bool,
+    /// If set, the space which could be saved by compressing large files is estimated by compressing their beginning.
+    pub estimate_compression: bool,
+    /// If set, the exact amount of bytes is written in parentheses after each formatted size, e.g. `1.29 GB (1288490188 bytes)`.
+    pub verbose_size: bool,
+    /// If set, plain byte counts and entry counts are grouped by thousands with this separator.
+    pub thousands_separator: Option<char>,
+    pub column_order: ColumnOrder,
+    pub error_annotation: ErrorAnnotation,
+    pub output_format: OutputFormat,
+    /// The way to display points in time, used by everything showing them for consistency.
+    pub time_style: TimeStyle,
     pub color: Color,
-    pub sorting: TraversalSorting,
+    pub output_order: OutputOrder,
+    /// Entries below the walked paths matching any of these patterns are skipped, along with everything below them.
+    pub exclude: Vec<Glob>,
+    /// If set, directories below the walked paths containing a `CACHEDIR.TAG` file with the standard signature are
+    /// skipped, along with everything below them.
+    pub exclude_caches: bool,
+    /// Files larger than this amount of bytes don't contribute to the sizes of aggregated paths.
+    pub exclude_larger_than: Option<u64>,
+    /// Files smaller than this amount of bytes don't contribute to the sizes of aggregated paths.
+    pub exclude_smaller_than: Option<u64>,
+    /// Names to display instead of the given input paths, like `Photos` for `/mnt/media/photos`.
+    pub labels: Vec<(PathBuf, String)>,
+    /// If set, paths below it are displayed relative to it, like `photos/2020` for `/mnt/media/photos/2020` and
+    /// `/mnt/media`. Other paths are displayed unchanged.
+    pub relative_to: Option<PathBuf>,
+    /// If set, sizes are colored by their magnitude instead of all in green, if colors are used at all
+    pub heatmap: Option<Heatmap>,
+    /// The symbolic links to follow
+    pub symlinks: Symlinks,
+    /// If set, paths are colored like `ls` does instead of dimming files
+    pub ls_colors: Option<LsColors>,
+    /// If set, [`aggregate()`](crate::aggregate()) stops walking once this much time has passed and writes what it
+    /// has seen until then, noting it in [`WalkResult::is_truncated`].
+    pub max_time: Option<Duration>,
+    /// The classes of IO errors which end the walk with an error, instead of being counted like all others
+    pub fatal_errors: Vec<ErrorClass>,
 }
 
 impl WalkOptions {
-    pub(crate) fn iter_from_path(&self, path: &Path) -> WalkDir {
-        WalkDir::new(path)
+    /// If set, `err` belongs to one of the `fatal_errors` and has to end the walk instead of being counted.
+    pub(crate) fn is_fatal(&self, err: &io::Error) -> bool {
+        self.fatal_errors.contains(&ErrorClass::of(err))
+    }
+
+    /// If set, a file of `num_bytes` is outside of the sizes to count, as given by `exclude_larger_than` and
+    /// `exclude_smaller_than`.
+    pub(crate) fn excludes_file_size(&self, num_bytes: u64) -> bool {
+        self.exclude_larger_than.is_some_and(|max| num_bytes > max)
+            || self.exclude_smaller_than.is_some_and(|min| num_bytes < min)
+    }
+
+    /// The label of the input `path` if there is one, or `path` relative to `relative_to` if it is below it, or
+    /// `path` itself.
+    pub(crate) fn displayed_path<'a>(&'a self, path: &'a Path) -> &'a Path {
+        if let Some((_, label)) = self.labels.iter().find(|(labelled, _)| labelled == path) {
+            return Path::new(label);
+        }
+        match self
+            .relative_to
+            .as_ref()
+            .and_then(|base| path.strip_prefix(base).ok())
+        {
+            Some(relative) if relative.as_os_str().is_empty() => Path::new("."),
+            Some(relative) => relative,
+            None => path,
+        }
+    }
+
+    /// All entries of `path` including itself, each directory before the entries within it. If `path` is a symbolic
+    /// link which isn't followed, it is the only entry.
+    pub(crate) fn iter_from_path(
+        &self,
+        path: &Path,
+    ) -> impl Iterator<Item = Result<DirEntry, io::Error>> {
+        let unfollowed_root = fs::symlink_metadata(path)
+            .ok()
+            .filter(|m| !self.symlinks.follow_root && m.file_type().is_symlink());
+        let walk = self.walk_dir(path);
+        let walk = match unfollowed_root {
+            Some(_) => {
+                log::debug!(
+                    "not following the symbolic link {} given as input path",
+                    path.display()
+                );
+                walk.max_depth(0)
+            }
+            None => walk,
+        };
+        walk.into_iter().map(move |entry| {
+            let mut entry = entry?;
+            if let (0, Some(link)) = (entry.depth, &unfollowed_root) {
+                entry.content_spec = None;
+                entry.file_type = Ok(link.file_type());
+                entry.metadata = Some(Ok(link.clone()));
+            }
+            Ok(entry)
+        })
+    }
+
+    fn walk_dir(&self, path: &Path) -> WalkDir {
+        let walk = WalkDir::new(path)
             .preload_metadata(true)
-            .sort(match self.sorting {
-                TraversalSorting::None => false,
-                TraversalSorting::AlphabeticalByFileName => true,
-            })
+            // the order of entries decides which hard link of a file is counted, and the order of written entries
+            .sort(true)
             .skip_hidden(false)
-            .num_threads(self.threads)
+            .num_threads(self.threads);
+        if self.exclude.is_empty() && !self.exclude_caches && self.symlinks == Symlinks::default() {
+            return walk;
+        }
+        let exclude = self.exclude.clone();
+        let exclude_caches = self.exclude_caches;
+        let symlinks = self.symlinks;
+        let visited = Mutex::new(HashSet::new());
+        if let Ok(root) = fs::metadata(path) {
+            Symlinks::visit(&visited, &root);
+        }
+        walk.process_entries(move |entries| {
+            if symlinks.follow_dirs {
+                // directories are visited before links to them, which are followed only if they weren't seen yet
+                for entry in entries.iter_mut().flatten() {
+                    let is_dir = entry.file_type.as_ref().is_ok_and(|t| t.is_dir());
+                    if let Some(Ok(metadata)) = entry.metadata.as_ref().filter(|_| is_dir) {
+                        if !Symlinks::visit(&visited, metadata) {
+                            entry.content_spec = None;
+                        }
+                    }
+                }
+            }
+            for entry in entries.iter_mut().flatten() {
+                symlinks.follow(entry, &visited);
+            }
+            entries.retain(|entry| match entry {
+                Ok(entry) => {
+                    let path = entry.path();
+                    if exclude.iter().any(|glob| glob.is_match(&path)) {
+                        log::debug!("excluding {} as it matches a pattern", path.display());
+                        false
+                    } else if exclude_caches
+                        && entry.file_type.as_ref().is_ok_and(|t| t.is_dir())
+                        && is_cache_dir(&path)
+                    {
+                        log::debug!("excluding {} as it is a cache directory", path.display());
+                        false
+                    } else {
+                        true
+                    }
+                }
+                Err(_) => true,
+            })
+        })
     }
 }
 
+/// True if `dir` contains a `CACHEDIR.TAG` file starting with the standard signature.
+pub fn is_cache_dir(dir: &Path) -> bool {
+    let mut signature = [0; CACHEDIR_TAG_SIGNATURE.len()];
+    fs::File::open(dir.join(CACHEDIR_TAG))
+        .and_then(|mut tag| tag.read_exact(&mut signature))
+        .is_ok_and(|_| signature == CACHEDIR_TAG_SIGNATURE)
+}
+
 /// Information we gather during a filesystem walk
 #[derive(Default)]
 pub struct WalkResult {
     /// The amount of io::errors we encountered. Can happen when fetching meta-data, or when reading the directory contents.
     pub num_errors: u64,
+    /// The total amount of bytes across all paths, as written in the line with the total by [`aggregate()`](crate::aggregate()).
+    /// Files are counted once unless hard links are counted, also by walks which write only some of them, like
+    /// [`largest_files()`](crate::largest_files()). [`find_inode()`](crate::find_inode()) sets it to the size the
+    /// matching paths share.
+    pub total_bytes: u64,
+    /// If set, the walk was stopped early as `max_time` of the [`WalkOptions`] passed, so sizes are too small
+    pub is_truncated: bool,
+    /// Messages about results which may be misleading, like sizes which are too small, to be shown to the user
+    pub warnings: Vec<String>,
+}
+
+/// A single line like `12288 bytes, 2 IO Errors`.
+impl fmt::Display for WalkResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "{} bytes, {} IO Error{}",
+            self.total_bytes,
+            self.num_errors,
+            if self.num_errors == 1 { "" } else { "s" }
+        )
+    }
 }
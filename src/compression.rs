@@ -0,0 +1,63 @@
+//! Estimate how much space could be saved by compressing files, by compressing a sample of them.
+use std::{fs::File, io, io::Read, path::Path};
+
+/// Files smaller than this are not sampled, as compressing them saves little in absolute terms
+pub const MIN_SAMPLED_FILE_SIZE: u64 = 1024 * 1024;
+/// The amount of bytes at the beginning of a file to compress
+const SAMPLE_SIZE: u64 = 64 * 1024;
+/// The fastest compression level, as we are after an estimate
+const COMPRESSION_LEVEL: u8 = 1;
+
+/// Return the amount of bytes we expect to save by compressing the file at `path` of `size` bytes, extrapolated from
+/// compressing its beginning. Files smaller than [`MIN_SAMPLED_FILE_SIZE`] are not read and yield 0.
+pub fn estimated_savings(path: &Path, size: u64) -> Result<u64, io::Error> {
+    if size < MIN_SAMPLED_FILE_SIZE {
+        return Ok(0);
+    }
+    let mut sample = Vec::with_capacity(SAMPLE_SIZE as usize);
+    File::open(path)?
+        .take(SAMPLE_SIZE)
+        .read_to_end(&mut sample)?;
+    Ok(savings_extrapolated_from(&sample, size))
+}
+
+fn savings_extrapolated_from(sample: &[u8], size: u64) -> u64 {
+    if sample.is_empty() {
+        return 0;
+    }
+    let compressed = miniz_oxide::deflate::compress_to_vec(sample, COMPRESSION_LEVEL);
+    let saved_in_sample = sample.len().saturating_sub(compressed.len()) as u64;
+    (saved_in_sample as f64 / sample.len() as f64 * size as f64) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn savings_are_extrapolated_from_the_sample() {
+        let repetitive = vec![b'a'; SAMPLE_SIZE as usize];
+        let savings = savings_extrapolated_from(&repetitive, 10 * SAMPLE_SIZE);
+        assert!(
+            savings > 9 * SAMPLE_SIZE,
+            "repetitive content compresses very well, got {}",
+            savings
+        );
+
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let noise: Vec<u8> = (0..SAMPLE_SIZE)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        assert_eq!(
+            savings_extrapolated_from(&noise, 10 * SAMPLE_SIZE),
+            0,
+            "random content doesn't compress, and savings are never negative"
+        );
+        assert_eq!(savings_extrapolated_from(&[], 10), 0);
+    }
+}
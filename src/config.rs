@@ -0,0 +1,343 @@
+//! Defaults for command-line options and key bindings of the interactive mode, read from a configuration file.
+use crate::{
+    interactive::{default_key_of, parse_key, ACTIONS},
+    options::{parse_size, parse_threads, ByteFormat},
+};
+use failure::{bail, Error, ResultExt};
+use std::{env, fs, io, path::PathBuf};
+use termion::event::Key;
+
+/// Settings read from a file in a subset of TOML, like
+///
+/// ```toml
+/// format = "binary"
+/// threads = 4
+/// color = false
+/// large-entry-size = "1GB"
+///
+/// [keys]
+/// toggle-mark-and-move = "x"
+/// page-down = "pagedown"
+/// ```
+///
+/// Options given on the command-line take precedence, followed by the environment variables read by
+/// [`Config::from_env()`].
+#[derive(Debug, Default, PartialEq)]
+pub struct Config {
+    pub format: Option<ByteFormat>,
+    pub threads: Option<usize>,
+    /// If `Some(false)`, output is never colored, even if it goes to a terminal
+    pub color: Option<bool>,
+    /// Entries larger than this are highlighted in interactive mode
+    pub large_entry_size: Option<u64>,
+    /// Pairs of a custom key and the default key of the action it triggers
+    pub key_bindings: Vec<(Key, Key)>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Value {
+    String(String),
+    Integer(u64),
+    Boolean(bool),
+}
+
+impl Config {
+    /// Read the configuration from `path`, or from [`default_path()`] if unset. If the file at the default
+    /// location doesn't exist, the default configuration is returned.
+    pub fn load(path: Option<PathBuf>) -> Result<Config, Error> {
+        let (path, is_explicit) = match path {
+            Some(path) => (path, true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Config::default()),
+            },
+        };
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound && !is_explicit => {
+                return Ok(Config::default())
+            }
+            Err(err) => {
+                return Err(err)
+                    .with_context(|_| {
+                        format!("Could not read configuration file '{}'", path.display())
+                    })
+                    .map_err(Into::into)
+            }
+        };
+        Ok(Config::parse(&content)
+            .with_context(|_| format!("Invalid configuration file '{}'", path.display()))?)
+    }
+
+    /// Read the format from `DUA_FORMAT` and the amount of threads from `DUA_THREADS`, if set. Invalid values are
+    /// ignored after warning about them on stderr.
+    pub fn from_env() -> Config {
+        Config::from_vars(
+            |name| env::var(name).ok(),
+            |warning| eprintln!("{}", warning),
+        )
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>, mut warn: impl FnMut(String)) -> Config {
+        let var = |name| var(name).filter(|value: &String| !value.is_empty());
+        Config {
+            format: var("DUA_FORMAT").and_then(|format| {
+                format
+                    .parse()
+                    .map_err(|_| {
+                        warn(format!(
+                            "Ignoring unknown format '{}' in DUA_FORMAT, valid values: {}",
+                            format,
+                            ByteFormat::variants().join(", ")
+                        ))
+                    })
+                    .ok()
+            }),
+            threads: var("DUA_THREADS").and_then(|threads| {
+                parse_threads(&threads)
+                    .map_err(|err| warn(format!("Ignoring DUA_THREADS: {}", err)))
+                    .ok()
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Each setting of ours, or the one of `fallback` if we don't have it. Key bindings of both are used, with ours
+    /// taking precedence.
+    pub fn or(mut self, fallback: Config) -> Config {
+        self.key_bindings.extend(fallback.key_bindings);
+        Config {
+            format: self.format.or(fallback.format),
+            threads: self.threads.or(fallback.threads),
+            color: self.color.or(fallback.color),
+            large_entry_size: self.large_entry_size.or(fallback.large_entry_size),
+            key_bindings: self.key_bindings,
+        }
+    }
+
+    fn parse(content: &str) -> Result<Config, Error> {
+        let mut config = Config::default();
+        let mut section = String::new();
+        for (line_number, line) in content.lines().enumerate().map(|(idx, l)| (idx + 1, l)) {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_owned();
+                if section != "keys" {
+                    bail!("Unknown section '[{}]' in line {}", section, line_number);
+                }
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), parse_value(value.trim(), line_number)?),
+                None => bail!("Expected 'name = value' in line {}", line_number),
+            };
+            match (section.as_str(), key, value) {
+                ("", "format", Value::String(format)) => {
+                    config.format = Some(format.parse().map_err(|_| {
+                        failure::err_msg(format!(
+                            "Unknown format '{}' in line {}, valid values: {}",
+                            format,
+                            line_number,
+                            ByteFormat::variants().join(", ")
+                        ))
+                    })?)
+                }
+                ("", "threads", Value::Integer(threads)) => {
+                    config.threads = Some(parse_threads(&threads.to_string()).map_err(|err| {
+                        failure::err_msg(format!("{} in line {}", err, line_number))
+                    })?)
+                }
+                ("", "color", Value::Boolean(color)) => config.color = Some(color),
+                ("", "large-entry-size", Value::String(size)) => {
+                    config.large_entry_size = Some(parse_size(&size).map_err(|err| {
+                        failure::err_msg(format!("{} in line {}", err, line_number))
+                    })?)
+                }
+                ("", "large-entry-size", Value::Integer(size)) => {
+                    config.large_entry_size = Some(size)
+                }
+                ("keys", action, Value::String(key)) => match default_key_of(action) {
+                    Some(default) => config.key_bindings.push((
+                        parse_key(&key).with_context(|_| format!("In line {}", line_number))?,
+                        default,
+                    )),
+                    None => bail!(
+                        "Unknown action '{}' in line {}, valid actions: {}",
+                        action,
+                        line_number,
+                        ACTIONS
+                            .iter()
+                            .map(|(name, _)| *name)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                },
+                (_, key, value) => bail!(
+                    "Unknown setting '{}' or unexpected value {:?} in line {}",
+                    key,
+                    value,
+                    line_number
+                ),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Parse a quoted string, a number or a boolean, optionally followed by a comment.
+fn parse_value(value: &str, line_number: usize) -> Result<Value, Error> {
+    let (value, rest) = match value.strip_prefix('"') {
+        Some(quoted) => match quoted.split_once('"') {
+            Some((string, rest)) => (Value::String(string.to_owned()), rest),
+            None => bail!("Missing closing '\"' in line {}", line_number),
+        },
+        None => {
+            let (value, rest) = value.split_at(value.find('#').unwrap_or(value.len()));
+            let value = match value.trim() {
+                "true" => Value::Boolean(true),
+                "false" => Value::Boolean(false),
+                number => match number.parse() {
+                    Ok(n) => Value::Integer(n),
+                    Err(_) => bail!(
+                        "Expected a quoted string, a number or a boolean in line {}",
+                        line_number
+                    ),
+                },
+            };
+            (value, rest)
+        }
+    };
+    let rest = rest.trim();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        bail!(
+            "Unexpected '{}' after the value in line {}",
+            rest,
+            line_number
+        );
+    }
+    Ok(value)
+}
+
+/// The location of the configuration file, `$XDG_CONFIG_HOME/dua/config.toml` or `~/.config/dua/config.toml`.
+pub fn default_path() -> Option<PathBuf> {
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join("dua").join("config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_and_key_bindings_are_parsed() -> Result<(), Error> {
+        let config = Config::parse(
+            r#"
+# a comment
+format = "binary"  # trailing comment
+threads = 4
+color = false
+large-entry-size = "1 KiB"
+
+[keys]
+toggle-mark-and-move = "x"
+page-down = "ctrl-f"
+"#,
+        )?;
+        assert_eq!(
+            config,
+            Config {
+                format: Some(ByteFormat::Binary),
+                threads: Some(4),
+                color: Some(false),
+                large_entry_size: Some(1024),
+                key_bindings: vec![
+                    (Key::Char('x'), Key::Char('d')),
+                    (Key::Ctrl('f'), Key::Ctrl('d'))
+                ],
+            }
+        );
+        assert_eq!(Config::parse("")?, Config::default());
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_settings_are_rejected_with_their_line() {
+        for (content, expected) in &[
+            ("threads = \"4\"", "line 1"),
+            ("format = \"kb\"", "Unknown format 'kb' in line 1"),
+            ("\n[keys]\nfly = \"f\"", "Unknown action 'fly' in line 3"),
+            ("[colors]", "Unknown section '[colors]'"),
+            ("color = \"auto", "Missing closing"),
+            ("threads = 4 2", "line 1"),
+            ("threads", "Expected 'name = value' in line 1"),
+            (
+                "threads = 1000",
+                "invalid amount of threads '1000', expected 1 to 256 in line 1",
+            ),
+            ("large-entry-size = \"1 QB\"", "invalid size '1 QB'"),
+        ] {
+            let err = Config::parse(content).expect_err(content).to_string();
+            assert!(err.contains(expected), "{:?} for {:?}", err, content);
+        }
+    }
+
+    #[test]
+    fn environment_variables_take_precedence_over_the_file_unless_invalid() {
+        let from_vars = |format: &'static str, threads: &'static str| {
+            let mut warnings = Vec::new();
+            let config = Config::from_vars(
+                |name| match name {
+                    "DUA_FORMAT" => Some(format.to_owned()),
+                    "DUA_THREADS" => Some(threads.to_owned()),
+                    _ => None,
+                },
+                |warning| warnings.push(warning),
+            );
+            (config, warnings)
+        };
+        let file = || Config {
+            format: Some(ByteFormat::Bytes),
+            threads: Some(2),
+            color: Some(false),
+            ..Default::default()
+        };
+
+        let (config, warnings) = from_vars("binary", "4");
+        assert!(warnings.is_empty());
+        assert_eq!(
+            config.or(file()),
+            Config {
+                format: Some(ByteFormat::Binary),
+                threads: Some(4),
+                color: Some(false),
+                ..Default::default()
+            }
+        );
+
+        let (config, warnings) = from_vars("kb", "0");
+        assert_eq!(config.or(file()), file(), "invalid values are ignored");
+        assert_eq!(warnings.len(), 2);
+        assert!(
+            warnings[0].contains("unknown format 'kb'"),
+            "{}",
+            warnings[0]
+        );
+        assert!(warnings[1].contains("DUA_THREADS"), "{}", warnings[1]);
+
+        let (config, warnings) = from_vars("", "");
+        assert_eq!(config, Config::default(), "empty values count as unset");
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn a_missing_file_is_an_error_only_if_given_explicitly() {
+        let missing = std::env::temp_dir().join("dua-config-which-does-not-exist.toml");
+        assert!(Config::load(Some(missing)).is_err());
+    }
+}
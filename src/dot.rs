@@ -0,0 +1,151 @@
+//! Export the tree of a walk as a graph in the DOT language of Graphviz.
+use crate::{
+    traverse::{Traversal, Tree, TreeIndex},
+    WalkOptions, WalkResult,
+};
+use failure::Error;
+use itertools::Itertools;
+use petgraph::Direction;
+use std::{io, path::PathBuf};
+
+/// Walk the given `paths` and write their tree to `out` as a directed graph in the DOT language, with an edge from
+/// each directory to each of its entries. Nodes are labelled with their name and size, and the input paths come with
+/// their full path.
+///
+/// Entries deeper than `max_depth` below the input paths, if set, and entries smaller than `min_size` are left out
+/// along with everything below them, which keeps graphs of large trees readable. Input paths are always written.
+pub fn export_dot(
+    mut out: impl io::Write,
+    options: WalkOptions,
+    max_depth: Option<usize>,
+    min_size: u64,
+    paths: Vec<PathBuf>,
+) -> Result<WalkResult, Error> {
+    let byte_format = options.byte_format;
+    let thousands_separator = options.thousands_separator;
+    let traversal = Traversal::from_walk(options, paths, |_| Ok(()))?;
+    let tree = &traversal.tree;
+
+    writeln!(out, "digraph \"disk usage\" {{")?;
+    writeln!(out, "    node [shape=box];")?;
+    let mut pending: Vec<_> = children_of(tree, traversal.root_index)
+        .into_iter()
+        .rev()
+        .map(|index| (index, 0))
+        .collect();
+    while let Some((index, depth)) = pending.pop() {
+        let entry = &tree[index];
+        writeln!(
+            out,
+            "    n{} [label=\"{}\\n{}\"];",
+            index.index(),
+            dot_escaped(&entry.name.to_string_lossy()),
+            byte_format
+                .display(entry.size)
+                .grouped(thousands_separator)
+                .to_string()
+                .trim()
+        )?;
+        if max_depth.is_some_and(|max_depth| depth >= max_depth) {
+            continue;
+        }
+        let children: Vec<_> = children_of(tree, index)
+            .into_iter()
+            .filter(|child| tree[*child].size >= min_size)
+            .collect();
+        for child in &children {
+            writeln!(out, "    n{} -> n{};", index.index(), child.index())?;
+        }
+        pending.extend(children.into_iter().rev().map(|child| (child, depth + 1)));
+    }
+    writeln!(out, "}}")?;
+
+    Ok(WalkResult {
+        num_errors: traversal.io_errors,
+        total_bytes: traversal.total_bytes.unwrap_or(0),
+        ..Default::default()
+    })
+}
+
+/// The entries of the directory at `index`, largest first.
+fn children_of(tree: &Tree, index: TreeIndex) -> Vec<TreeIndex> {
+    tree.neighbors_directed(index, Direction::Outgoing)
+        .sorted_by(|&lhs, &rhs| {
+            tree[rhs]
+                .size
+                .cmp(&tree[lhs].size)
+                .then_with(|| tree[lhs].name.cmp(&tree[rhs].name))
+        })
+        .collect()
+}
+
+/// `s` with `"` and `\` escaped, to be placed within a quoted string of the DOT language.
+fn dot_escaped(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{test_options, TempDir};
+
+    #[test]
+    fn trees_are_written_as_graphs_limited_by_depth_and_size() -> Result<(), Error> {
+        let dir = TempDir::new("export-dot")?;
+        std::fs::create_dir_all(dir.join("sub").join("deeper"))?;
+        std::fs::write(dir.join("sub").join("deeper").join("file"), vec![0; 100])?;
+        std::fs::write(dir.join("sub").join("tiny \"quoted\""), b"1")?;
+        std::fs::write(dir.join("small"), vec![0; 10])?;
+
+        let dot_of = |max_depth, min_size| -> Result<String, Error> {
+            let mut out = Vec::new();
+            export_dot(
+                &mut out,
+                test_options(),
+                max_depth,
+                min_size,
+                vec![dir.clone()],
+            )?;
+            let out = String::from_utf8(out)?;
+            // node indices depend on the order of the walk, so only names and edges are compared
+            Ok(out.lines().map(|line| line.trim().to_owned()).join("\n"))
+        };
+        let all = dot_of(None, 0);
+        let limited = dot_of(Some(1), 10);
+
+        let all = all?;
+        let label_of = |name: &str| {
+            all.lines()
+                .find(|line| line.contains(name))
+                .and_then(|line| line.split_once(' ').map(|(_, rest)| rest.to_owned()))
+        };
+        assert!(all.starts_with("digraph \"disk usage\" {\nnode [shape=box];\n"));
+        assert!(all.ends_with("\n}"));
+        assert_eq!(
+            label_of("deeper"),
+            Some("[label=\"deeper\\n100 b\"];".to_owned())
+        );
+        assert_eq!(
+            label_of("tiny"),
+            Some("[label=\"tiny \\\"quoted\\\"\\n1 b\"];".to_owned()),
+            "quotes within names are escaped"
+        );
+        assert_eq!(all.matches(" -> ").count(), 5, "one edge per entry");
+
+        let limited = limited?;
+        assert_eq!(
+            limited
+                .lines()
+                .filter_map(|line| line.split('"').nth(1))
+                .skip(1)
+                .collect::<Vec<_>>(),
+            vec![
+                format!("{}\\n111 b", dot_escaped(&dir.to_string_lossy())).as_str(),
+                "sub\\n101 b",
+                "small\\n10 b"
+            ],
+            "entries are written largest first, down to the given depth and size"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,257 @@
+//! Find files with identical contents which aren't hard links of each other, as candidates for deduplication.
+use crate::{aggregate::write_path_bytes, FileId, WalkOptions, WalkResult};
+use failure::Error;
+use itertools::Itertools;
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, BTreeSet, HashMap},
+    fs,
+    hash::Hasher,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+/// A file with all the paths referring to it, which are several if it has hard links
+struct File {
+    paths: BTreeSet<PathBuf>,
+    /// The size counted for the file, on disk or apparent
+    num_bytes: u64,
+}
+
+/// Walk the given `paths` and write each group of files with identical contents to `out`, followed by the space which
+/// could be reclaimed by keeping only one of them, the largest amount first. Each group starts with a line like
+/// `10 b reclaimable from 3 identical files of 5 b` followed by the paths of its files, one per line, and the last line
+/// holds the space reclaimable across all groups.
+///
+/// Files are grouped by their length first, and only files sharing their length with another one are read to hash their
+/// contents. Files with the same hash are compared byte by byte before they are grouped. All hard links of a file are
+/// listed along with it, but they count as a single file as they already share their contents. Empty files are ignored.
+///
+/// The returned `total_bytes` are the bytes reclaimable across all groups.
+pub fn find_duplicates(
+    mut out: impl io::Write,
+    options: WalkOptions,
+    paths: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> Result<WalkResult, Error> {
+    let mut res = WalkResult::default();
+    let mut files_by_length = BTreeMap::<u64, Vec<File>>::new();
+    let mut file_indices = HashMap::<FileId, usize>::new();
+    for path in paths {
+        let path = path.as_ref();
+        for entry in options.iter_from_path(path) {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(_) => {
+                    res.num_errors += 1;
+                    continue;
+                }
+            };
+            let m = match entry.metadata {
+                Some(Ok(ref m)) => m,
+                Some(Err(_)) => {
+                    res.num_errors += 1;
+                    continue;
+                }
+                None => unreachable!(
+                    "we ask for metadata, so we at least have Some(Err(..))). Issue in jwalk?"
+                ),
+            };
+            if !m.is_file() || m.len() == 0 {
+                continue;
+            }
+            let file_path = if entry.depth == 0 {
+                path.to_owned()
+            } else {
+                entry.path()
+            };
+            let files = files_by_length.entry(m.len()).or_default();
+            if let Some(&index) = FileId::of(m).and_then(|id| file_indices.get(&id)) {
+                files[index].paths.insert(file_path);
+                continue;
+            }
+            let num_bytes = if options.apparent_size {
+                m.len()
+            } else {
+                match filesize::file_real_size_fast(&file_path, m) {
+                    Ok(num_bytes) => num_bytes,
+                    Err(_) => {
+                        res.num_errors += 1;
+                        continue;
+                    }
+                }
+            };
+            if let Some(id) = FileId::of(m) {
+                file_indices.insert(id, files.len());
+            }
+            files.push(File {
+                paths: Some(file_path).into_iter().collect(),
+                num_bytes,
+            });
+        }
+    }
+
+    let mut groups = Vec::new();
+    for (_, files) in files_by_length {
+        if files.len() < 2 {
+            continue;
+        }
+        let mut files_by_hash = BTreeMap::<u64, Vec<File>>::new();
+        for file in files {
+            match hash_of(first_path(&file)) {
+                Ok(hash) => files_by_hash.entry(hash).or_default().push(file),
+                Err(_) => res.num_errors += 1,
+            }
+        }
+        for (_, files) in files_by_hash {
+            if files.len() < 2 {
+                continue;
+            }
+            let mut identical: Vec<Vec<File>> = Vec::new();
+            for file in files {
+                let mut group = None;
+                for (index, candidates) in identical.iter().enumerate() {
+                    match have_same_contents(first_path(&candidates[0]), first_path(&file)) {
+                        Ok(true) => {
+                            group = Some(index);
+                            break;
+                        }
+                        Ok(false) => {}
+                        Err(_) => res.num_errors += 1,
+                    }
+                }
+                match group {
+                    Some(index) => identical[index].push(file),
+                    None => identical.push(vec![file]),
+                }
+            }
+            groups.extend(identical.into_iter().filter(|files| files.len() > 1));
+        }
+    }
+
+    let reclaimable = |files: &[File]| -> u64 {
+        let largest = files.iter().map(|f| f.num_bytes).max().unwrap_or(0);
+        files.iter().map(|f| f.num_bytes).sum::<u64>() - largest
+    };
+    let format = |num_bytes| {
+        options
+            .byte_format
+            .display(num_bytes)
+            .grouped(options.thousands_separator)
+    };
+    let groups = groups.into_iter().sorted_by(|lhs, rhs| {
+        reclaimable(rhs)
+            .cmp(&reclaimable(lhs))
+            .then_with(|| first_path(&lhs[0]).cmp(first_path(&rhs[0])))
+    });
+    let mut num_groups = 0;
+    for files in groups {
+        let num_bytes = reclaimable(&files);
+        res.total_bytes += num_bytes;
+        num_groups += 1;
+        writeln!(
+            out,
+            "{} reclaimable from {} identical files of {}",
+            format(num_bytes),
+            files.len(),
+            format(files.iter().map(|f| f.num_bytes).max().unwrap_or(0))
+        )?;
+        for path in files.iter().flat_map(|f| &f.paths) {
+            write_path_bytes(&mut out, options.displayed_path(path))?;
+            writeln!(out)?;
+        }
+        writeln!(out)?;
+    }
+    writeln!(
+        out,
+        "{} reclaimable in {} {}",
+        format(res.total_bytes),
+        num_groups,
+        if num_groups == 1 { "group" } else { "groups" }
+    )?;
+    Ok(res)
+}
+
+fn first_path(file: &File) -> &Path {
+    file.paths
+        .iter()
+        .next()
+        .expect("at least one path per file")
+}
+
+/// The hash of the contents of the file at `path`.
+fn hash_of(path: &Path) -> Result<u64, io::Error> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = DefaultHasher::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        match file.read(&mut buf)? {
+            0 => return Ok(hasher.finish()),
+            n => hasher.write(&buf[..n]),
+        }
+    }
+}
+
+/// Whether the files at `lhs` and `rhs` have the same contents.
+fn have_same_contents(lhs: &Path, rhs: &Path) -> Result<bool, io::Error> {
+    let (mut lhs, mut rhs) = (fs::File::open(lhs)?, fs::File::open(rhs)?);
+    let (mut lbuf, mut rbuf) = (vec![0; 64 * 1024], vec![0; 64 * 1024]);
+    loop {
+        let n = lhs.read(&mut lbuf)?;
+        if n == 0 {
+            return Ok(rhs.read(&mut rbuf[..1])? == 0);
+        }
+        if rhs.read_exact(&mut rbuf[..n]).is_err() || lbuf[..n] != rbuf[..n] {
+            return Ok(false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{test_options, TempDir};
+    use std::fs;
+
+    #[test]
+    fn files_with_identical_contents_are_grouped_with_their_hard_links() -> Result<(), Error> {
+        let root = TempDir::new("duplicates-test")?;
+        fs::create_dir_all(root.join("dir"))?;
+        fs::write(root.join("a"), "12345")?;
+        fs::write(root.join("dir/b"), "12345")?;
+        fs::write(root.join("same-length"), "54321")?;
+        fs::write(root.join("c"), "123")?;
+        fs::write(root.join("dir/c"), "123")?;
+        fs::write(root.join("unique"), "1234567")?;
+        fs::write(root.join("empty"), "")?;
+        fs::write(root.join("dir/empty"), "")?;
+        let has_links = cfg!(unix);
+        if has_links {
+            fs::hard_link(root.join("a"), root.join("link"))?;
+        }
+
+        let mut out = Vec::new();
+        let res = find_duplicates(&mut out, test_options(), [&root]);
+        let res = res?;
+        assert_eq!((res.num_errors, res.total_bytes), (0, 8));
+
+        let path = |p: &str| root.join(p).display().to_string();
+        assert_eq!(
+            String::from_utf8(out)?,
+            format!(
+                "5 b reclaimable from 2 identical files of 5 b\n{}\n{}{}\n\n\
+                 3 b reclaimable from 2 identical files of 3 b\n{}\n{}\n\n\
+                 8 b reclaimable in 2 groups\n",
+                path("a"),
+                if has_links {
+                    format!("{}\n", path("link"))
+                } else {
+                    String::new()
+                },
+                path("dir/b"),
+                path("c"),
+                path("dir/c"),
+            ),
+            "hard links are listed along with the file, but count once"
+        );
+        Ok(())
+    }
+}
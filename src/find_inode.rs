@@ -0,0 +1,119 @@
+//! Find all hard links of a file, identified by its device and inode.
+use crate::{aggregate::write_path_bytes, FileId, WalkOptions, WalkResult};
+use failure::{bail, Error};
+use std::{io, path::Path};
+
+/// Walk the given `paths` and write each path whose device and inode equal `id` to `out`, one per line, followed by a
+/// line with the size all of them share and their amount.
+///
+/// The size is counted only once, as all paths refer to the same data. It is an error if no path matches.
+pub fn find_inode(
+    mut out: impl io::Write,
+    options: WalkOptions,
+    id: FileId,
+    paths: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> Result<WalkResult, Error> {
+    let mut res = WalkResult::default();
+    let mut num_matches = 0_u64;
+    let mut shared_size = None;
+    for path in paths {
+        let path = path.as_ref();
+        for entry in options.iter_from_path(path) {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(_) => {
+                    res.num_errors += 1;
+                    continue;
+                }
+            };
+            let m = match entry.metadata {
+                Some(Ok(ref m)) => m,
+                Some(Err(_)) => {
+                    res.num_errors += 1;
+                    continue;
+                }
+                None => unreachable!(
+                    "we ask for metadata, so we at least have Some(Err(..))). Issue in jwalk?"
+                ),
+            };
+            if FileId::of(m) != Some(id) {
+                continue;
+            }
+            let matched_path = if entry.depth == 0 {
+                path.to_owned()
+            } else {
+                entry.path()
+            };
+            if shared_size.is_none() {
+                shared_size = Some(if options.apparent_size {
+                    m.len()
+                } else {
+                    filesize::file_real_size_fast(&matched_path, m).unwrap_or_else(|_| {
+                        res.num_errors += 1;
+                        0
+                    })
+                });
+            }
+            num_matches += 1;
+            write_path_bytes(&mut out, options.displayed_path(&matched_path))?;
+            writeln!(out)?;
+        }
+    }
+    res.total_bytes = shared_size.unwrap_or(0);
+    match shared_size {
+        Some(size) => writeln!(
+            out,
+            "{} shared by {} {}",
+            options
+                .byte_format
+                .display(size)
+                .grouped(options.thousands_separator),
+            num_matches,
+            if num_matches == 1 { "path" } else { "paths" }
+        )?,
+        None => bail!("No path refers to device and inode {}", id),
+    }
+    Ok(res)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::testing::{test_options, TempDir};
+    use std::fs;
+
+    #[test]
+    fn all_hard_links_of_a_file_are_found_and_its_size_is_counted_once() -> Result<(), Error> {
+        let root = TempDir::new("find-inode-test")?;
+        fs::create_dir_all(root.join("dir"))?;
+        fs::write(root.join("file"), "12345")?;
+        fs::hard_link(root.join("file"), root.join("dir/link"))?;
+        fs::write(root.join("other"), "12345")?;
+        let id = FileId::of(&fs::metadata(root.join("file"))?).expect("unix has inodes");
+
+        let mut out = Vec::new();
+        let res = find_inode(&mut out, test_options(), id, [&root]);
+        let missing = find_inode(
+            Vec::new(),
+            test_options(),
+            FileId {
+                inode: u64::MAX,
+                ..id
+            },
+            [&root],
+        );
+        let res = res?;
+        assert_eq!((res.num_errors, res.total_bytes), (0, 5));
+        assert!(missing.is_err(), "it's an error if nothing matches");
+
+        assert_eq!(
+            String::from_utf8(out)?,
+            format!(
+                "{}\n{}\n5 b shared by 2 paths\n",
+                root.join("dir/link").display(),
+                root.join("file").display()
+            )
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,309 @@
+//! Match paths against shell-like patterns, to exclude them from a walk or to expand them into the paths to walk.
+use failure::{bail, Error};
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Char(char),
+    /// `?`, any character but `/`
+    AnyChar,
+    /// `*`, any amount of characters but `/`
+    Star,
+    /// `**`, any amount of characters including `/`
+    DoubleStar,
+    /// `[...]`, any of the given character ranges, or none of them if `negated`
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Token {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Token::Char(expected) => *expected == c,
+            Token::AnyChar => c != '/',
+            Token::Class { negated, ranges } => {
+                c != '/' && ranges.iter().any(|(from, to)| (*from..=*to).contains(&c)) != *negated
+            }
+            Token::Star | Token::DoubleStar => unreachable!("handled when matching"),
+        }
+    }
+}
+
+/// A pattern like `*.log`, `target/**` or `[._]cache`, as known from shells and `rsync`.
+///
+/// A pattern without `/` matches the name of an entry. Otherwise it matches the trailing path components of an entry,
+/// so `target/debug` matches `./project/target/debug`. `*` and `?` never match a path separator, while `**` does, and
+/// `\` escapes the character following it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Glob {
+    tokens: Vec<Token>,
+}
+
+impl FromStr for Glob {
+    type Err = Error;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        let mut tokens = Vec::new();
+        let mut chars = pattern.chars().peekable();
+        while let Some(c) = chars.next() {
+            tokens.push(match c {
+                '?' => Token::AnyChar,
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    Token::DoubleStar
+                }
+                '*' => Token::Star,
+                '\\' => match chars.next() {
+                    Some(c) => Token::Char(c),
+                    None => bail!("Pattern '{}' ends with an unescaped '\\'", pattern),
+                },
+                '[' => {
+                    let negated = chars.next_if(|c| *c == '!' || *c == '^').is_some();
+                    let mut ranges = Vec::new();
+                    loop {
+                        let from = match chars.next() {
+                            Some(']') if !ranges.is_empty() => break,
+                            Some(c) => c,
+                            None => bail!("Pattern '{}' lacks the ']' closing a '['", pattern),
+                        };
+                        let to = match chars.next_if_eq(&'-') {
+                            Some(_) => match chars.next_if(|c| *c != ']') {
+                                Some(to) => to,
+                                None => {
+                                    ranges.push(('-', '-'));
+                                    from
+                                }
+                            },
+                            None => from,
+                        };
+                        ranges.push((from, to));
+                    }
+                    Token::Class { negated, ranges }
+                }
+                c => Token::Char(c),
+            });
+        }
+        Ok(Glob { tokens })
+    }
+}
+
+impl Glob {
+    /// Parse patterns from `content`, one per line, ignoring empty lines and lines starting with `#`.
+    pub fn parse_lines(content: &str) -> Result<Vec<Glob>, Error> {
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::parse)
+            .collect()
+    }
+
+    /// Returns true if the trailing components of `path` match this pattern.
+    ///
+    /// The components are separated by `/` when matching, whichever separator the platform uses, so `*` doesn't match
+    /// `\` separating them on Windows.
+    pub fn is_match(&self, path: &Path) -> bool {
+        let mut text = Vec::new();
+        let mut component_starts = vec![0];
+        for component in path.components() {
+            if let Component::RootDir = component {
+                text.push('/');
+                continue;
+            }
+            if text.last().is_some_and(|c| *c != '/') {
+                text.push('/');
+            }
+            component_starts.push(text.len());
+            text.extend(component.as_os_str().to_string_lossy().chars());
+        }
+        matches(&self.tokens, &text, &component_starts)
+    }
+}
+
+/// Expand `pattern` into the paths it matches like a shell does, sorted by name, or `pattern` itself if it contains no
+/// `*`, `?` or `[`. Each component of `pattern` matches the names of the entries in the directory its predecessors
+/// lead to, and names starting with `.` are only matched by a component starting with `.` as well.
+///
+/// Directories which can't be read match nothing, hence an empty list means nothing matched.
+pub fn expand_glob(pattern: &Path) -> Result<Vec<PathBuf>, Error> {
+    let is_pattern = |component: &str| component.contains(['*', '?', '[']);
+    if !pattern.to_str().is_some_and(is_pattern) {
+        return Ok(vec![pattern.to_owned()]);
+    }
+    let mut paths = vec![PathBuf::new()];
+    for component in pattern.components() {
+        let name = match component {
+            Component::Normal(name) => name.to_str().filter(|name| is_pattern(name)),
+            _ => None,
+        };
+        let name = match name {
+            Some(name) => name,
+            None => {
+                for path in &mut paths {
+                    path.push(component);
+                }
+                continue;
+            }
+        };
+        let glob: Glob = name.parse()?;
+        let mut matches = Vec::new();
+        for dir in &paths {
+            let entries = match fs::read_dir(if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir
+            }) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            let mut names: Vec<_> = entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.file_name()))
+                .filter(|entry_name| {
+                    let entry_name = Path::new(entry_name);
+                    (name.starts_with('.') || !entry_name.to_string_lossy().starts_with('.'))
+                        && glob.is_match(entry_name)
+                })
+                .collect();
+            names.sort();
+            matches.extend(names.into_iter().map(|entry_name| dir.join(entry_name)));
+        }
+        paths = matches;
+    }
+    Ok(paths)
+}
+
+/// Returns true if `tokens` match `text` from any of the `starts` to its end.
+///
+/// Instead of backtracking, which takes exponential time for patterns like `*a*a*a*b`, it keeps track of all positions
+/// in `text` the tokens seen so far can end at, taking time proportional to the product of both lengths.
+fn matches(tokens: &[Token], text: &[char], starts: &[usize]) -> bool {
+    let mut ends = vec![false; text.len() + 1];
+    for start in starts {
+        ends[*start] = true;
+    }
+    for token in tokens {
+        let mut next = vec![false; text.len() + 1];
+        for idx in 0..=text.len() {
+            next[idx] = match token {
+                Token::Star => ends[idx] || (idx > 0 && next[idx - 1] && text[idx - 1] != '/'),
+                Token::DoubleStar => ends[idx] || (idx > 0 && next[idx - 1]),
+                token => idx > 0 && ends[idx - 1] && token.matches(text[idx - 1]),
+            };
+        }
+        ends = next;
+    }
+    ends[text.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn is_match(pattern: &str, path: &str) -> bool {
+        pattern
+            .parse::<Glob>()
+            .expect("valid pattern")
+            .is_match(Path::new(path))
+    }
+
+    #[test]
+    fn patterns_without_slash_match_names() {
+        assert!(is_match("*.log", "./logs/today.log"));
+        assert!(is_match("*.log", "today.log"));
+        assert!(!is_match("*.log", "./today.log/file"));
+        assert!(is_match("file?", "dir/file1"));
+        assert!(!is_match("file?", "dir/file"));
+        assert!(is_match("[._]cache", "home/.cache"));
+        assert!(is_match("[!.]cache", "home/_cache"));
+        assert!(!is_match("[!.]cache", "home/.cache"));
+        assert!(is_match("[a-c]", "dir/b"));
+        assert!(!is_match("[a-c]", "dir/d"));
+        assert!(is_match("[]]", "dir/]"));
+        assert!(is_match(r"\*", "dir/*"));
+        assert!(!is_match(r"\*", "dir/a"));
+        assert!(!is_match("cache", "dir/.cache"));
+    }
+
+    #[test]
+    fn patterns_with_slash_match_trailing_components() {
+        assert!(is_match("target/debug", "./project/target/debug"));
+        assert!(!is_match("target/debug", "./project/mytarget/debug"));
+        assert!(!is_match("target/*", "./target/debug/deps"));
+        assert!(is_match("target/**", "./target/debug/deps"));
+        assert!(is_match("/tmp/*", "/tmp/file"));
+        assert!(!is_match("/tmp/*", "/var/tmp/file"));
+        if cfg!(windows) {
+            assert!(is_match("target/debug", r".\project\target\debug"));
+            assert!(!is_match("target/*", r".\target\debug\deps"));
+        }
+    }
+
+    #[test]
+    fn many_stars_take_no_time_to_not_match() {
+        let pattern = "*a".repeat(30) + "b";
+        assert!(!is_match(&pattern, &"a".repeat(100)));
+        assert!(!is_match(&"**a".repeat(30), &"a/".repeat(20)));
+        assert!(is_match(
+            &"**a".repeat(30),
+            "a/".repeat(50).trim_end_matches('/')
+        ));
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        assert!("[abc".parse::<Glob>().is_err());
+        assert!("abc\\".parse::<Glob>().is_err());
+    }
+
+    #[test]
+    fn patterns_expand_to_the_paths_they_match() -> Result<(), Error> {
+        let root = TempDir::new("glob-expand")?;
+        for dir in &["project-a", "project-b", "other"] {
+            fs::create_dir_all(root.join(dir))?;
+            fs::write(root.join(dir).join("x.log"), "")?;
+        }
+        fs::write(root.join(".hidden.log"), "")?;
+        fs::write(root.join("today.log"), "")?;
+
+        let expanded = |pattern: &str| expand_glob(&root.join(pattern));
+        let results = (
+            expanded("*.log"),
+            expanded(".*.log"),
+            expanded("project-*/*.log"),
+            expanded("*.txt"),
+            expanded("missing"),
+        );
+
+        assert_eq!(
+            results.0?,
+            vec![root.join("today.log")],
+            "hidden entries need a pattern starting with a dot"
+        );
+        assert_eq!(results.1?, vec![root.join(".hidden.log")]);
+        assert_eq!(
+            results.2?,
+            vec![root.join("project-a/x.log"), root.join("project-b/x.log")]
+        );
+        assert_eq!(results.3?, Vec::<PathBuf>::new());
+        assert_eq!(
+            results.4?,
+            vec![root.join("missing")],
+            "paths without patterns are left as they are"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn patterns_are_read_one_per_line_skipping_comments() -> Result<(), Error> {
+        let patterns = Glob::parse_lines("# build output\ntarget\n\n  *.log  \n#*.txt\n")?;
+        assert_eq!(patterns, vec!["target".parse()?, "*.log".parse()?]);
+        Ok(())
+    }
+}
@@ -0,0 +1,98 @@
+//! Color sizes by their magnitude, to make the largest ones stand out.
+use termion::color::AnsiValue;
+
+/// Colors sizes by how large they are compared to the largest size written, using an equal share of the range from
+/// zero to the largest size for each color of the palette.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heatmap {
+    /// The ANSI values of the colors to use, from the one for the smallest sizes to the one for the largest
+    pub palette: Vec<u8>,
+    /// The largest size, which is only known once all sizes to be written are
+    pub(crate) largest: u64,
+}
+
+impl Default for Heatmap {
+    /// Green for small sizes, yellow for medium ones and red for the largest
+    fn default() -> Self {
+        Heatmap::new(vec![2, 3, 1])
+    }
+}
+
+impl Heatmap {
+    /// Use the given `palette`, which needs at least one color.
+    pub fn new(palette: Vec<u8>) -> Self {
+        assert!(!palette.is_empty(), "a heatmap needs colors");
+        Heatmap {
+            palette,
+            largest: 0,
+        }
+    }
+
+    /// The color of `bytes`, with everything from the largest size onward getting the last color.
+    pub(crate) fn color_of(&self, bytes: u64) -> AnsiValue {
+        let last = self.palette.len() - 1;
+        let index = match self.largest {
+            0 => last,
+            largest => {
+                (u128::from(bytes) * self.palette.len() as u128 / u128::from(largest)) as usize
+            }
+        };
+        AnsiValue(self.palette[index.min(last)])
+    }
+}
+
+/// Parse a comma-separated list of colors like 'green,yellow,red' into their ANSI values, each being the name of one of
+/// the 16 colors of the terminal, optionally prefixed with 'light-', or a number from 0 to 255.
+pub fn parse_palette(palette: &str) -> Result<Vec<u8>, String> {
+    const NAMES: [&str; 8] = [
+        "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    ];
+    palette
+        .split(',')
+        .map(|name| {
+            let name = name.trim();
+            let (offset, base) = match name.strip_prefix("light-") {
+                Some(base) => (8, base),
+                None => (0, name),
+            };
+            NAMES
+                .iter()
+                .position(|&known| known == base)
+                .map(|index| offset + index as u8)
+                .or_else(|| name.parse().ok())
+                .ok_or_else(|| {
+                    format!(
+                        "invalid color '{}', expected one of {} optionally prefixed with 'light-', or 0 to 255",
+                        name,
+                        NAMES.join(", ")
+                    )
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_get_the_color_of_their_share_of_the_largest_size() {
+        let heatmap = Heatmap {
+            largest: 300,
+            ..Heatmap::default()
+        };
+        let colors: Vec<_> = [0, 99, 100, 299, 300, 1000]
+            .iter()
+            .map(|&bytes| heatmap.color_of(bytes).0)
+            .collect();
+        assert_eq!(colors, vec![2, 2, 3, 1, 1, 1]);
+    }
+
+    #[test]
+    fn palettes_consist_of_names_and_numbers() {
+        assert_eq!(parse_palette("green, light-red,208"), Ok(vec![2, 9, 208]));
+        assert!(parse_palette("green,orange").is_err());
+        assert!(parse_palette("256").is_err());
+        assert!(parse_palette("").is_err());
+    }
+}
@@ -1,53 +1,124 @@
 #![cfg_attr(windows, feature(windows_by_handle))]
 
-use std::collections::HashMap;
-
-#[derive(Debug, Default, Clone)]
-pub struct InodeFilter {
-    inner: HashMap<u64, u64>,
+use failure::{bail, Error};
+use std::{collections::HashMap, fmt, str::FromStr};
+
+/// The identity of a file shared by all of its hard links, the device it is stored on and its inode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileId {
+    pub device: u64,
+    pub inode: u64,
 }
 
-impl InodeFilter {
+impl FileId {
+    /// The identity of the file `metadata` belongs to, or `None` if the platform doesn't provide one.
     #[cfg(unix)]
-    pub fn add(&mut self, metadata: &std::fs::Metadata) -> bool {
+    pub fn of(metadata: &std::fs::Metadata) -> Option<FileId> {
         use std::os::unix::fs::MetadataExt;
 
-        self.add_inode(metadata.ino(), metadata.nlink())
+        Some(FileId {
+            device: metadata.dev(),
+            inode: metadata.ino(),
+        })
     }
 
-    #[cfg(windows)]
-    pub fn add(&mut self, metadata: &std::fs::Metadata) -> bool {
-        use std::os::windows::fs::MetadataExt;
+    #[cfg(not(unix))]
+    pub fn of(_metadata: &std::fs::Metadata) -> Option<FileId> {
+        None
+    }
+}
 
-        if let (Some(inode), Some(nlinks)) = (metadata.file_index(), metadata.number_of_links()) {
-            self.add_inode(inode, nlinks as u64)
-        } else {
-            true
+/// Parses `DEV:INO` as printed by `stat -c %d:%i`.
+impl FromStr for FileId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s
+            .split_once(':')
+            .map(|(device, inode)| (device.parse(), inode.parse()))
+        {
+            Some((Ok(device), Ok(inode))) => Ok(FileId { device, inode }),
+            _ => bail!("Expected DEVICE:INODE as decimal numbers, got '{}'", s),
         }
     }
+}
+
+impl fmt::Display for FileId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.device, self.inode)
+    }
+}
+
+/// Tracks the inodes of files with multiple hard links, to count the size of each of them only once.
+///
+/// It's only used by the thread consuming a walk, which keeps it deterministic which path a hard linked file is counted
+/// for. Sizes don't have to be computed in parallel for it: on unix they are derived from the metadata the walk already
+/// read on its own threads.
+#[derive(Debug, Default, Clone)]
+pub struct InodeFilter {
+    inner: HashMap<FileId, u64>,
+}
+
+/// The identity of the file `metadata` belongs to and its amount of hard links, if the platform provides them.
+#[cfg(unix)]
+pub fn file_id_and_links(metadata: &std::fs::Metadata) -> Option<(FileId, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    FileId::of(metadata).map(|id| (id, metadata.nlink()))
+}
 
-    #[cfg(not(any(unix, windows)))]
+#[cfg(windows)]
+pub fn file_id_and_links(metadata: &std::fs::Metadata) -> Option<(FileId, u64)> {
+    use std::os::windows::fs::MetadataExt;
+
+    match (
+        metadata.volume_serial_number(),
+        metadata.file_index(),
+        metadata.number_of_links(),
+    ) {
+        (Some(device), Some(inode), Some(nlinks)) => Some((
+            FileId {
+                device: device as u64,
+                inode,
+            },
+            nlinks as u64,
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn file_id_and_links(_metadata: &std::fs::Metadata) -> Option<(FileId, u64)> {
+    None
+}
+
+impl InodeFilter {
     pub fn add(&mut self, metadata: &std::fs::Metadata) -> bool {
-        true
+        match file_id_and_links(metadata) {
+            Some((id, nlinks)) => self.add_inode(id, nlinks),
+            None => true,
+        }
     }
 
-    pub fn add_inode(&mut self, inode: u64, nlinks: u64) -> bool {
+    /// Returns true if the file `id` with `nlinks` hard links wasn't seen before, and so has to be counted. Inodes are
+    /// only unique within their device, so the same inode on two devices belongs to two different files.
+    pub fn add_inode(&mut self, id: FileId, nlinks: u64) -> bool {
         if nlinks <= 1 {
             return true;
         }
 
-        match self.inner.get_mut(&inode) {
+        match self.inner.get_mut(&id) {
             Some(count) => {
                 *count -= 1;
 
                 if *count == 0 {
-                    self.inner.remove(&inode);
+                    self.inner.remove(&id);
                 }
 
                 false
             }
             None => {
-                self.inner.insert(inode, nlinks - 1);
+                self.inner.insert(id, nlinks - 1);
                 true
             }
         }
@@ -58,18 +129,52 @@ impl InodeFilter {
 mod tests {
     use super::*;
 
+    fn file(device: u64, inode: u64) -> FileId {
+        FileId { device, inode }
+    }
+
     #[test]
     fn it_filters_inodes() {
         let mut inodes = InodeFilter::default();
 
-        assert!(inodes.add_inode(1, 2));
-        assert!(!inodes.add_inode(1, 2));
+        assert!(inodes.add_inode(file(1, 1), 2));
+        assert!(!inodes.add_inode(file(1, 1), 2));
 
-        assert!(inodes.add_inode(1, 3));
-        assert!(!inodes.add_inode(1, 3));
-        assert!(!inodes.add_inode(1, 3));
+        assert!(inodes.add_inode(file(1, 1), 3));
+        assert!(!inodes.add_inode(file(1, 1), 3));
+        assert!(!inodes.add_inode(file(1, 1), 3));
 
-        assert!(inodes.add_inode(1, 1));
-        assert!(inodes.add_inode(1, 1));
+        assert!(inodes.add_inode(file(1, 1), 1));
+        assert!(inodes.add_inode(file(1, 1), 1));
+    }
+
+    #[test]
+    fn equal_inodes_on_different_devices_are_different_files() {
+        let mut inodes = InodeFilter::default();
+
+        assert!(inodes.add_inode(file(1, 7), 2));
+        assert!(
+            inodes.add_inode(file(2, 7), 2),
+            "the inode on the second device wasn't seen yet"
+        );
+        assert!(!inodes.add_inode(file(2, 7), 2));
+        assert!(!inodes.add_inode(file(1, 7), 2));
+    }
+
+    #[test]
+    fn file_ids_are_parsed_from_device_and_inode() -> Result<(), Error> {
+        let id: FileId = "2049:131".parse()?;
+        assert_eq!(
+            id,
+            FileId {
+                device: 2049,
+                inode: 131
+            }
+        );
+        assert_eq!(id.to_string(), "2049:131");
+        assert!("2049".parse::<FileId>().is_err());
+        assert!("2049:".parse::<FileId>().is_err());
+        assert!("a:1".parse::<FileId>().is_err());
+        Ok(())
     }
 }
@@ -1,11 +1,14 @@
+use crate::interactive::ELLIPSIS;
 use dua::{ByteFormat, WalkOptions};
 use std::fmt;
+use termion::event::Key;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Default)]
 pub enum ByteVisualization {
     Percentage,
     Bar,
     LongBar,
+    #[default]
     PercentageAndBar,
 }
 
@@ -14,12 +17,6 @@ pub struct DisplayByteVisualization {
     percentage: f32,
 }
 
-impl Default for ByteVisualization {
-    fn default() -> Self {
-        ByteVisualization::PercentageAndBar
-    }
-}
-
 impl ByteVisualization {
     pub fn cycle(&mut self) {
         use ByteVisualization::*;
@@ -78,11 +75,28 @@ impl DisplayByteVisualization {
     }
 }
 
+/// The amount of marked paths shown before deleting them, unless configured otherwise
+pub const NUM_PATHS_TO_CONFIRM: usize = 5;
+
 /// Options to configure how we display things
 #[derive(Clone, Copy)]
 pub struct DisplayOptions {
     pub byte_format: ByteFormat,
     pub byte_vis: ByteVisualization,
+    /// Shown in place of the parts of paths which don't fit
+    pub ellipsis: &'static str,
+    /// The amount of marked paths to show before deleting them
+    pub num_paths_to_confirm: usize,
+    /// Pairs of a custom key and the default key of the action it triggers, see [`translate()`](super::translate())
+    pub key_bindings: &'static [(Key, Key)],
+    /// If set, the size of entries larger than this is highlighted
+    pub large_entry_size: Option<u64>,
+    /// If set, the own size of directories is shown next to the size of everything within them
+    pub show_own_size: bool,
+    /// If set, quitting while entries are marked asks for confirmation first, as the marks would be lost
+    pub confirm_quit_with_marks: bool,
+    /// If set, a line explaining the colors and glyphs in use is shown above the footer
+    pub show_legend: bool,
 }
 
 impl From<WalkOptions> for DisplayOptions {
@@ -90,6 +104,13 @@ impl From<WalkOptions> for DisplayOptions {
         DisplayOptions {
             byte_format,
             byte_vis: ByteVisualization::default(),
+            ellipsis: ELLIPSIS,
+            num_paths_to_confirm: NUM_PATHS_TO_CONFIRM,
+            key_bindings: &[],
+            large_entry_size: None,
+            show_own_size: false,
+            confirm_quit_with_marks: true,
+            show_legend: false,
         }
     }
 }
@@ -4,8 +4,9 @@ use itertools::Itertools;
 use petgraph::Direction;
 use unicode_segmentation::UnicodeSegmentation;
 
-#[derive(Debug, Copy, Clone, PartialOrd, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialOrd, PartialEq, Eq, Default)]
 pub enum SortMode {
+    #[default]
     SizeDescending,
     SizeAscending,
 }
@@ -20,12 +21,6 @@ impl SortMode {
     }
 }
 
-impl Default for SortMode {
-    fn default() -> Self {
-        SortMode::SizeDescending
-    }
-}
-
 pub struct EntryDataBundle {
     pub index: TreeIndex,
     pub data: EntryData,
@@ -33,6 +28,7 @@ pub struct EntryDataBundle {
     pub exists: bool,
 }
 
+/// The children of `node_idx` sorted by size according to `sorting`, and by name if their size is equal.
 pub fn sorted_entries(tree: &Tree, node_idx: TreeIndex, sorting: SortMode) -> Vec<EntryDataBundle> {
     use SortMode::*;
     tree.neighbors_directed(node_idx, Direction::Outgoing)
@@ -44,29 +40,37 @@ pub fn sorted_entries(tree: &Tree, node_idx: TreeIndex, sorting: SortMode) -> Ve
                     index: idx,
                     data: w.clone(),
                     exists: pm.is_ok(),
-                    is_dir: pm.ok().map_or(false, |m| m.is_dir()),
+                    is_dir: pm.ok().is_some_and(|m| m.is_dir()),
                 }
             })
         })
-        .sorted_by(|l, r| match sorting {
-            SizeDescending => r.data.size.cmp(&l.data.size),
-            SizeAscending => l.data.size.cmp(&r.data.size),
+        .sorted_by(|l, r| {
+            match sorting {
+                SizeDescending => r.data.size.cmp(&l.data.size),
+                SizeAscending => l.data.size.cmp(&r.data.size),
+            }
+            .then_with(|| l.data.name.cmp(&r.data.name))
         })
         .collect()
 }
 
+/// The string shown in place of graphemes which were removed to make a string fit
+pub const ELLIPSIS: &str = "…";
+
+/// Shorten `s` to `desired_graphemes` by replacing its beginning with `ellipsis`, which may span multiple graphemes.
 pub fn fit_string_graphemes_with_ellipsis(
     s: impl Into<String>,
     path_graphemes_count: usize,
     mut desired_graphemes: usize,
+    ellipsis: &str,
 ) -> (String, usize) {
-    const ELLIPSIS: usize = 1;
     const MIN_GRAPHEMES_ON_SIDE: usize = 1;
-    const MIN_LEN: usize = ELLIPSIS + MIN_GRAPHEMES_ON_SIDE;
     const USE_EXTENDED: bool = true;
+    let ellipsis_len = ellipsis.graphemes(USE_EXTENDED).count();
+    let min_len = ellipsis_len + MIN_GRAPHEMES_ON_SIDE;
 
     let s = s.into();
-    desired_graphemes = desired_graphemes.max(MIN_LEN);
+    desired_graphemes = desired_graphemes.max(min_len);
 
     debug_assert!(
         path_graphemes_count == s.graphemes(USE_EXTENDED).count(),
@@ -79,14 +83,78 @@ pub fn fit_string_graphemes_with_ellipsis(
     }
 
     let mut n = String::with_capacity(desired_graphemes);
-    let to_be_removed = gc - desired_graphemes + ELLIPSIS;
+    let to_be_removed = gc - desired_graphemes + ellipsis_len;
     let gmi = s.graphemes(USE_EXTENDED);
 
-    n.push('…');
+    n.push_str(ellipsis);
     n.extend(gmi.skip(to_be_removed));
     (n, desired_graphemes)
 }
 
+/// Like `fit_string_graphemes_with_ellipsis`, but removes graphemes from the middle of `s`, keeping
+/// its beginning and end visible. For paths, the directories leading to the last component are shortened first,
+/// keeping the last component whole unless it doesn't fit on its own.
+pub fn fit_string_graphemes_with_ellipsis_in_middle(
+    s: impl Into<String>,
+    path_graphemes_count: usize,
+    mut desired_graphemes: usize,
+    ellipsis: &str,
+) -> (String, usize) {
+    const MIN_GRAPHEMES_ON_SIDE: usize = 1;
+    const USE_EXTENDED: bool = true;
+    let ellipsis_len = ellipsis.graphemes(USE_EXTENDED).count();
+    let min_len = ellipsis_len + 2 * MIN_GRAPHEMES_ON_SIDE;
+
+    let s = s.into();
+    desired_graphemes = desired_graphemes.max(min_len);
+
+    debug_assert!(
+        path_graphemes_count == s.graphemes(USE_EXTENDED).count(),
+        "input grapheme count is actually correct"
+    );
+
+    let gc = path_graphemes_count;
+    if gc <= desired_graphemes {
+        return (s, gc);
+    }
+
+    if let Some(last_separator) = s.rfind(std::path::is_separator).filter(|pos| *pos > 0) {
+        let last_component_len = s[last_separator..].graphemes(USE_EXTENDED).count();
+        if let Some(head) = desired_graphemes
+            .checked_sub(ellipsis_len + last_component_len)
+            .filter(|head| *head >= MIN_GRAPHEMES_ON_SIDE)
+        {
+            let head_len = s
+                .grapheme_indices(USE_EXTENDED)
+                .nth(head)
+                .map_or(s.len(), |(pos, _)| pos);
+            return (
+                format!("{}{}{}", &s[..head_len], ellipsis, &s[last_separator..]),
+                desired_graphemes,
+            );
+        }
+    }
+
+    let graphemes_to_keep = desired_graphemes - ellipsis_len;
+    let head = graphemes_to_keep / 2;
+    let head_len = s
+        .grapheme_indices(USE_EXTENDED)
+        .nth(head)
+        .map(|(pos, _)| pos)
+        .unwrap_or_else(|| s.len());
+
+    let mut n = String::with_capacity(desired_graphemes);
+    n.push_str(&s[..head_len]);
+    let (tail, _) = fit_string_graphemes_with_ellipsis(
+        &s[head_len..],
+        gc - head,
+        desired_graphemes - head,
+        ellipsis,
+    );
+    n.push_str(&tail);
+    (n, desired_graphemes)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -95,22 +163,104 @@ mod tests {
     fn fit_string_inputs() {
         assert_eq!(
             ("aaa".into(), 3),
-            fit_string_graphemes_with_ellipsis("aaa", 3, 4)
+            fit_string_graphemes_with_ellipsis("aaa", 3, 4, ELLIPSIS)
         );
         assert_eq!(
             ("…a".to_string(), 2),
-            fit_string_graphemes_with_ellipsis("abbbba", 6, 1),
+            fit_string_graphemes_with_ellipsis("abbbba", 6, 1, ELLIPSIS),
             "even amount of chars, desired too small"
         );
         assert_eq!(
             ("…ca".to_string(), 3),
-            fit_string_graphemes_with_ellipsis("abbbbca", 7, 3),
+            fit_string_graphemes_with_ellipsis("abbbbca", 7, 3, ELLIPSIS),
             "uneven amount of chars, desired too small"
         );
         assert_eq!(
             ("… a".to_string(), 3),
-            fit_string_graphemes_with_ellipsis("a    a", 6, 3),
+            fit_string_graphemes_with_ellipsis("a    a", 6, 3, ELLIPSIS),
             "spaces are counted as graphemes, too"
         );
     }
+
+    #[test]
+    fn fit_string_inputs_in_middle() {
+        let fit = |s: &str, desired| {
+            fit_string_graphemes_with_ellipsis_in_middle(
+                s,
+                s.graphemes(true).count(),
+                desired,
+                ELLIPSIS,
+            )
+        };
+        assert_eq!(
+            ("aaa".into(), 3),
+            fit("aaa", 4),
+            "short strings are untouched"
+        );
+        assert_eq!(
+            ("a…a".to_string(), 3),
+            fit("abbbba", 1),
+            "desired too small, one grapheme is kept on each side"
+        );
+        assert_eq!(
+            ("/v…/file.txt".to_string(), 12),
+            fit("/very/long/path/to/file.txt", 12),
+            "directories are cut before the last component"
+        );
+        assert_eq!(
+            ("/very/l…/file.txt".to_string(), 17),
+            fit("/very/long/path/to/file.txt", 17),
+        );
+        assert_eq!(
+            ("/a/ver…me.txt".to_string(), 13),
+            fit("/a/very-long-file-name.txt", 13),
+            "a last component which doesn't fit on its own is cut in the middle like any string"
+        );
+        assert_eq!(
+            ("和製…研究所".to_string(), 6),
+            fit("和製漢語社會科學院語學研究所", 6),
+            "multi-byte characters are handled per grapheme"
+        );
+        assert_eq!(
+            ("👩🏽…🙏🏿".to_string(), 3),
+            fit("👩🏽👾🙇💁🙏🏿", 3),
+            "emoji with modifiers are never split"
+        );
+    }
+
+    #[test]
+    fn fit_string_inputs_with_multi_grapheme_ellipsis() {
+        assert_eq!(
+            ("...a".to_string(), 4),
+            fit_string_graphemes_with_ellipsis("abbbba", 6, 1, "..."),
+            "desired too small, the whole ellipsis is kept"
+        );
+        assert_eq!(
+            ("...ca".to_string(), 5),
+            fit_string_graphemes_with_ellipsis("abbbbca", 7, 5, "..."),
+        );
+        let fit = |s: &str, desired| {
+            fit_string_graphemes_with_ellipsis_in_middle(
+                s,
+                s.graphemes(true).count(),
+                desired,
+                "...",
+            )
+        };
+        assert_eq!(
+            ("abc".into(), 3),
+            fit("abc", 3),
+            "short strings are untouched"
+        );
+        assert_eq!(
+            ("a...a".to_string(), 5),
+            fit("abbbba", 1),
+            "desired too small, one grapheme is kept on each side"
+        );
+        assert_eq!(
+            ("/v.../file.txt".to_string(), 14),
+            fit("/very/long/path/to/file.txt", 14),
+            "the ellipsis takes three graphemes of the desired width"
+        );
+    }
 }
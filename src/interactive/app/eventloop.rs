@@ -1,31 +1,50 @@
 use crate::interactive::{
-    sorted_entries,
+    path_of, sorted_entries, translate,
     widgets::{MainWindow, MainWindowProps},
-    ByteVisualization, CursorDirection, DisplayOptions, EntryDataBundle, SortMode,
+    ByteVisualization, CursorDirection, DisplayOptions, EntryDataBundle, FilesystemSpace,
+    PendingSpace, SessionReport, SortMode, SHELL_KEY,
 };
 use dua::{
-    traverse::{Traversal, TreeIndex},
+    traverse::{walk, Traversal, TraversalBuilder, TreeIndex},
     WalkOptions, WalkResult,
 };
-use failure::Error;
-use std::{collections::BTreeMap, io, path::PathBuf};
+use failure::{err_msg, Error};
+use itertools::Itertools;
+use std::{
+    collections::BTreeMap,
+    io,
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::{Duration, Instant},
+};
 use termion::event::Key;
 use tui::backend::Backend;
 use tui_react::Terminal;
 
-#[derive(Copy, Clone)]
+/// How long to integrate traversal events before handling keys and redrawing
+const KEY_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long drawing waits for the free space of a new root, to show it right away if `df` is quick
+const FREE_SPACE_WAIT: Duration = Duration::from_millis(100);
+
+/// How long after typing a character of a name the next one extends it, instead of starting a new name
+pub const TYPE_AHEAD_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// True if `c` triggers an action in the main pane, in which case it never starts or extends a name typed ahead.
+fn has_main_pane_action(c: char) -> bool {
+    c.is_control() || c.is_ascii_digit() || "?eqObdhjklmosuvwgRSL ".contains(c)
+}
+
+#[derive(Copy, Clone, Default)]
 pub enum FocussedPane {
+    #[default]
     Main,
     Help,
+    Errors,
     Mark,
 }
 
-impl Default for FocussedPane {
-    fn default() -> Self {
-        FocussedPane::Main
-    }
-}
-
 #[derive(Default)]
 pub struct AppState {
     pub root: TreeIndex,
@@ -35,6 +54,31 @@ pub struct AppState {
     pub message: Option<String>,
     pub focussed: FocussedPane,
     pub bookmarks: BTreeMap<TreeIndex, TreeIndex>,
+    pub is_scanning: bool,
+    /// The amount of times to repeat the next move, as typed so far
+    pub pending_count: Option<usize>,
+    /// The beginning of the name of the entry to jump to as typed so far, and when its last character was typed
+    pub type_ahead: Option<(String, Instant)>,
+    /// The space of the filesystem containing the given root, if known, which is determined again once the root changes
+    pub free_space: Option<(TreeIndex, Option<FilesystemSpace>)>,
+    /// The free space of the root of `free_space` while it's being determined, which is picked up by later draws
+    pub pending_free_space: Option<PendingSpace>,
+    /// The paths deleted so far, along with the size they had
+    pub deleted: Vec<(PathBuf, u64)>,
+    /// If set, quitting was requested while entries were marked, and the next key confirms or cancels it
+    pub is_confirming_quit: bool,
+    /// If set, a second list of entries is shown next to the one the keys act on
+    pub split: Option<SplitPane>,
+}
+
+/// The list of entries next to the one the keys act on, to compare two directories side by side.
+/// Switching sides exchanges its directory, selection and entries with the ones of the [`AppState`].
+pub struct SplitPane {
+    pub root: TreeIndex,
+    pub selected: Option<TreeIndex>,
+    pub entries: Vec<EntryDataBundle>,
+    /// If set, this list is shown on the right, and the one the keys act on on the left
+    pub is_right: bool,
 }
 
 /// State and methods representing the interactive disk usage analyser for the terminal
@@ -43,6 +87,9 @@ pub struct TerminalApp {
     pub display: DisplayOptions,
     pub state: AppState,
     pub window: MainWindow,
+    /// Signalled once the key opening a shell was handled, as the thread reading keys waits for that to not compete
+    /// with the shell for input
+    pub resume_keys: Option<Sender<()>>,
 }
 
 impl TerminalApp {
@@ -63,6 +110,24 @@ impl TerminalApp {
     where
         B: Backend,
     {
+        let mut free_space_wait = Duration::from_secs(0);
+        if self.state.free_space.map(|(root, _)| root) != Some(self.state.root) {
+            let path = path_of(&self.traversal.tree, self.state.root);
+            let path = if path.as_os_str().is_empty() {
+                PathBuf::from(".")
+            } else {
+                path
+            };
+            self.state.free_space = Some((self.state.root, None));
+            self.state.pending_free_space = Some(PendingSpace::request(path));
+            free_space_wait = FREE_SPACE_WAIT;
+        }
+        if let Some(pending) = &self.state.pending_free_space {
+            if let Some(space) = pending.wait(free_space_wait) {
+                self.state.free_space = Some((self.state.root, space));
+                self.state.pending_free_space = None;
+            }
+        }
         let props = MainWindowProps {
             traversal: &self.traversal,
             display: self.display,
@@ -74,106 +139,260 @@ impl TerminalApp {
         &mut self,
         terminal: &mut Terminal<B>,
         keys: impl Iterator<Item = Result<Key, io::Error>>,
-    ) -> Result<WalkResult, Error>
+    ) -> Result<(WalkResult, SessionReport), Error>
+    where
+        B: Backend,
+    {
+        self.draw(terminal)?;
+        for key in keys.filter_map(Result::ok) {
+            if let ProcessingResult::Exit = self.process_key(key, terminal) {
+                break;
+            }
+            self.draw(terminal)?;
+        }
+        Ok((
+            WalkResult {
+                num_errors: self.traversal.io_errors,
+                total_bytes: self.traversal.total_bytes.unwrap_or(0),
+                ..Default::default()
+            },
+            self.report(),
+        ))
+    }
+
+    /// What was marked and deleted so far.
+    pub fn report(&self) -> SessionReport {
+        SessionReport {
+            marked: self
+                .window
+                .mark_pane
+                .as_ref()
+                .map(|pane| {
+                    pane.marked()
+                        .values()
+                        .sorted_by_key(|mark| mark.index)
+                        .map(|mark| (mark.path.clone(), mark.size))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            deleted: self.state.deleted.clone(),
+        }
+    }
+
+    /// The amount of entries which are currently marked.
+    fn num_marked(&self) -> usize {
+        self.window
+            .mark_pane
+            .as_ref()
+            .map_or(0, |pane| pane.marked().len())
+    }
+
+    fn process_key<B>(&mut self, key: Key, terminal: &mut Terminal<B>) -> ProcessingResult
+    where
+        B: Backend,
+    {
+        let key = translate(self.display.key_bindings, key);
+        let result = self.handle_key(key, terminal);
+        // reading keys pauses after the shell key and has to resume however it was handled
+        if key == SHELL_KEY {
+            if let Some(resume_keys) = &self.resume_keys {
+                resume_keys.send(()).ok();
+            }
+        }
+        result
+    }
+
+    fn handle_key<B>(&mut self, key: Key, terminal: &mut Terminal<B>) -> ProcessingResult
     where
         B: Backend,
     {
         use termion::event::Key::*;
         use FocussedPane::*;
 
-        self.draw(terminal)?;
-        for key in keys.filter_map(Result::ok) {
-            self.update_message();
-            match key {
-                Char('?') => self.toggle_help_pane(),
-                Char('\t') => {
-                    self.cycle_focus();
-                }
-                Ctrl('c') => break,
-                Char('q') | Esc => match self.state.focussed {
-                    Main => break,
-                    Mark => self.state.focussed = Main,
-                    Help => {
-                        self.state.focussed = Main;
-                        self.window.help_pane = None
-                    }
-                },
-                _ => {}
+        self.update_message();
+        if self.state.is_confirming_quit {
+            self.state.is_confirming_quit = false;
+            return match key {
+                Char('y') | Char('q') | Ctrl('c') => ProcessingResult::Exit,
+                _ => ProcessingResult::Continue,
+            };
+        }
+        let count = self.state.pending_count.take();
+        let times = count.unwrap_or(1);
+        let typed = self
+            .state
+            .type_ahead
+            .take()
+            .filter(|(_, typed_at)| typed_at.elapsed() < TYPE_AHEAD_TIMEOUT)
+            .map(|(name, _)| name);
+        let is_typing_ahead = typed.is_some()
+            && matches!(self.state.focussed, Main)
+            && matches!(key, Char(c) if !has_main_pane_action(c));
+        match key {
+            _ if is_typing_ahead => {}
+            Char('?') => self.toggle_help_pane(),
+            Char('e') => self.toggle_errors_pane(),
+            Char('\t') => {
+                self.cycle_focus();
             }
+            Ctrl('c') => return ProcessingResult::Exit,
+            Char('q') | Esc => match self.state.focussed {
+                Main if self.display.confirm_quit_with_marks && self.num_marked() > 0 => {
+                    let num_marked = self.num_marked();
+                    self.state.is_confirming_quit = true;
+                    self.state.message = Some(format!(
+                        "{} marked entr{} would be lost, press 'y' or 'q' to quit anyway, or any other key to continue",
+                        num_marked,
+                        if num_marked == 1 { "y" } else { "ies" }
+                    ));
+                    return ProcessingResult::Continue;
+                }
+                Main => return ProcessingResult::Exit,
+                Mark => self.state.focussed = Main,
+                Help => {
+                    self.state.focussed = Main;
+                    self.window.help_pane = None
+                }
+                Errors => {
+                    self.state.focussed = Main;
+                    self.window.errors_pane = None
+                }
+            },
+            _ => {}
+        }
 
-            match self.state.focussed {
-                FocussedPane::Mark => self.dispatch_to_mark_pane(key, terminal),
-                FocussedPane::Help => {
-                    self.window.help_pane.as_mut().expect("help pane").key(key);
+        match self.state.focussed {
+            FocussedPane::Mark => self.dispatch_to_mark_pane(key, terminal),
+            FocussedPane::Help => {
+                self.window.help_pane.as_mut().expect("help pane").key(key);
+            }
+            FocussedPane::Errors => {
+                self.window
+                    .errors_pane
+                    .as_mut()
+                    .expect("errors pane")
+                    .key(key);
+            }
+            FocussedPane::Main => match key {
+                Char(c) if is_typing_ahead => self.type_ahead(typed, c),
+                Char(c) if c.is_ascii_digit() && (c != '0' || count.is_some()) => {
+                    self.extend_count(count, c.to_digit(10).expect("ascii digit"))
                 }
-                FocussedPane::Main => match key {
-                    Char('O') => self.open_that(),
-                    Char(' ') => self.mark_entry(false),
-                    Char('d') => self.mark_entry(true),
-                    Char('u') | Char('h') | Backspace | Left => self.exit_node(),
-                    Char('o') | Char('l') | Char('\n') | Right => self.enter_node(),
-                    Ctrl('u') | PageUp => self.change_entry_selection(CursorDirection::PageUp),
-                    Char('k') | Up => self.change_entry_selection(CursorDirection::Up),
-                    Char('j') | Down => self.change_entry_selection(CursorDirection::Down),
-                    Ctrl('d') | PageDown => self.change_entry_selection(CursorDirection::PageDown),
-                    Char('s') => self.cycle_sorting(),
-                    Char('g') => self.display.byte_vis.cycle(),
-                    _ => {}
-                },
-            };
-            self.draw(terminal)?;
+                Char('O') => self.open_that(),
+                Char('b') => self.open_shell(terminal),
+                Char(' ') => self.mark_entry(false),
+                Char('d') => self.mark_entry(true),
+                Ctrl('a') => self.mark_all_entries(),
+                Char('u') | Char('h') | Backspace | Left => self.exit_node(),
+                Char('o') | Char('l') | Char('\n') | Right => self.enter_node(),
+                Ctrl('u') | PageUp => {
+                    self.change_entry_selection_times(CursorDirection::PageUp, times)
+                }
+                Char('k') | Up => self.change_entry_selection_times(CursorDirection::Up, times),
+                Char('j') | Down => self.change_entry_selection_times(CursorDirection::Down, times),
+                Ctrl('d') | PageDown => {
+                    self.change_entry_selection_times(CursorDirection::PageDown, times)
+                }
+                Char('s') => self.cycle_sorting(),
+                Char('g') => self.display.byte_vis.cycle(),
+                Char('m') => self.toggle_byte_units(),
+                Char('S') => self.display.show_own_size = !self.display.show_own_size,
+                Char('v') => self.toggle_split(),
+                Char('w') => self.switch_split_side(),
+                Char('R') => self.recompute_subtree_sizes(),
+                Char('L') => self.display.show_legend = !self.display.show_legend,
+                Char(c) if !has_main_pane_action(c) => self.type_ahead(None, c),
+                _ => {}
+            },
+        };
+        ProcessingResult::Continue
+    }
+
+    /// Re-read the entries of the current root from the tree, which changes while scanning.
+    fn refresh_entries(&mut self) {
+        self.state.entries =
+            sorted_entries(&self.traversal.tree, self.state.root, self.state.sorting);
+        if self.state.selected.is_none() {
+            self.state.selected = self.state.entries.first().map(|b| b.index);
         }
-        Ok(WalkResult {
-            num_errors: self.traversal.io_errors,
-        })
+        self.refresh_split_entries();
     }
 
+    /// Traverse `input` in the background and return the app once done, building its tree while the walk progresses.
+    /// While traversing, `keys` are handled as usual except for deletions, and quitting causes `None` to be returned.
+    /// `resume_keys` is signalled whenever the [`SHELL_KEY`] was handled.
     pub fn initialize<B>(
         terminal: &mut Terminal<B>,
         options: WalkOptions,
+        mut display_options: DisplayOptions,
         input: Vec<PathBuf>,
-    ) -> Result<TerminalApp, Error>
+        keys: &Receiver<Key>,
+        resume_keys: Option<Sender<()>>,
+    ) -> Result<Option<TerminalApp>, Error>
     where
         B: Backend,
     {
         terminal.hide_cursor()?;
         terminal.clear()?;
-        let mut display_options: DisplayOptions = options.clone().into();
         display_options.byte_vis = ByteVisualization::Bar;
-        let mut window = MainWindow::default();
 
-        let traversal = Traversal::from_walk(options, input, move |traversal| {
-            let state = AppState {
-                root: traversal.root_index,
-                sorting: Default::default(),
-                message: Some("-> scanning <-".into()),
-                entries: sorted_entries(&traversal.tree, traversal.root_index, Default::default()),
-                ..Default::default()
-            };
-            let props = MainWindowProps {
-                traversal,
-                display: display_options,
-                state: &state,
-            };
-            Self::draw_window(&mut window, props, terminal)
-        })?;
-
-        let sorting = Default::default();
-        let root = traversal.root_index;
-        let entries = sorted_entries(&traversal.tree, root, sorting);
-        let selected = entries.get(0).map(|b| b.index);
-        display_options.byte_vis = ByteVisualization::PercentageAndBar;
-        Ok(TerminalApp {
+        let (event_send, event_receive) = mpsc::channel();
+        thread::spawn(move || {
+            walk(options, input, |event| {
+                event_send
+                    .send(event)
+                    .map_err(|_| err_msg("receiver of traversal events hung up"))
+            })
+        });
+
+        let traversal = Traversal::empty();
+        let mut builder = TraversalBuilder::new(&traversal);
+        let mut app = TerminalApp {
             state: AppState {
-                root,
-                sorting,
-                selected,
-                entries,
+                root: traversal.root_index,
+                is_scanning: true,
                 ..Default::default()
             },
             display: display_options,
             traversal,
             window: Default::default(),
-        })
+            resume_keys,
+        };
+        while app.state.is_scanning {
+            let deadline = Instant::now() + KEY_POLL_INTERVAL;
+            while let Some(timeout) = deadline.checked_duration_since(Instant::now()) {
+                match event_receive.recv_timeout(timeout) {
+                    Ok(event) => builder.add(&mut app.traversal, event),
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => {
+                        app.state.is_scanning = false;
+                        break;
+                    }
+                }
+            }
+            for key in keys.try_iter() {
+                if let ProcessingResult::Exit = app.process_key(key, terminal) {
+                    return Ok(None);
+                }
+            }
+            if app.state.is_scanning {
+                app.refresh_entries();
+                if app.state.message.is_none() {
+                    app.state.message = Some("-> scanning <-".into());
+                }
+                app.draw(terminal)?;
+            }
+        }
+        builder.finish(&mut app.traversal);
+
+        app.refresh_entries();
+        app.state.message = None;
+        app.display.byte_vis = ByteVisualization::PercentageAndBar;
+        Ok(Some(app))
     }
 }
+
+enum ProcessingResult {
+    Continue,
+    Exit,
+}
@@ -0,0 +1,118 @@
+//! Learn how much space the filesystem containing a path has left.
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::Duration,
+};
+
+/// How long `df` may take before giving up on it, as it blocks on unresponsive network filesystems
+#[cfg_attr(not(all(unix, feature = "free-space")), allow(dead_code))]
+const DF_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The size of a filesystem and how it's used, in bytes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FilesystemSpace {
+    pub total: u64,
+    pub used: u64,
+    pub free: u64,
+}
+
+/// The space of a filesystem as it is determined in the background, to not block drawing on `df`.
+pub struct PendingSpace(Receiver<Option<FilesystemSpace>>);
+
+impl PendingSpace {
+    /// Start determining the space of the filesystem containing `path`.
+    pub fn request(path: PathBuf) -> Self {
+        let (send, receive) = mpsc::channel();
+        thread::spawn(move || send.send(filesystem_space(&path)));
+        PendingSpace(receive)
+    }
+
+    /// Wait up to `timeout` for the space, and return it once known, which is `Some(None)` if it couldn't be
+    /// determined.
+    pub fn wait(&self, timeout: Duration) -> Option<Option<FilesystemSpace>> {
+        match self.0.recv_timeout(timeout) {
+            Ok(space) => Some(space),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => Some(None),
+        }
+    }
+}
+
+/// The space of the filesystem containing `path` as reported by `df`, or `None` if it couldn't be determined
+/// within the [`DF_TIMEOUT`].
+#[cfg(all(unix, feature = "free-space"))]
+pub fn filesystem_space(path: &Path) -> Option<FilesystemSpace> {
+    use std::{
+        process::{Command, Stdio},
+        time::Instant,
+    };
+    let mut child = Command::new("df")
+        .arg("-Pk")
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let deadline = Instant::now() + DF_TIMEOUT;
+    // the output of a single filesystem fits into the pipe, so it's read once `df` is done
+    while child.try_wait().ok()?.is_none() {
+        if Instant::now() >= deadline {
+            child.kill().ok();
+            child.wait().ok();
+            return None;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    let output = child.wait_with_output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_df(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(not(all(unix, feature = "free-space")))]
+pub fn filesystem_space(_path: &Path) -> Option<FilesystemSpace> {
+    None
+}
+
+/// Parse the portable output of `df -Pk` for a single filesystem, whose second line is like
+/// `/dev/sda1 1024-blocks-total used available capacity% /mount/point`.
+#[cfg_attr(not(all(unix, feature = "free-space")), allow(dead_code))]
+fn parse_df(output: &str) -> Option<FilesystemSpace> {
+    let fields: Vec<_> = output.lines().nth(1)?.split_whitespace().collect();
+    // names of filesystems may contain spaces, so look for the numbers followed by the capacity
+    fields.windows(4).find_map(|window| match window {
+        [total, used, free, capacity] if capacity.ends_with('%') => {
+            let kilobytes = |field: &str| field.parse::<u64>().ok().map(|n| n * 1024);
+            Some(FilesystemSpace {
+                total: kilobytes(total)?,
+                used: kilobytes(used)?,
+                free: kilobytes(free)?,
+            })
+        }
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_space_is_read_from_the_portable_output_of_df() {
+        let output = "Filesystem     1024-blocks      Used Available Capacity Mounted on\n\
+                      /dev/disk one   487350000 400000000  87350000      83% /Volumes/With Space\n";
+        assert_eq!(
+            parse_df(output),
+            Some(FilesystemSpace {
+                total: 487_350_000 * 1024,
+                used: 400_000_000 * 1024,
+                free: 87_350_000 * 1024,
+            })
+        );
+        assert_eq!(parse_df("df: /nowhere: No such file or directory\n"), None);
+    }
+}
@@ -1,13 +1,16 @@
 use crate::interactive::widgets::MarkMode;
 use crate::interactive::{
-    app::{FocussedPane::*, TerminalApp},
-    path_of, sorted_entries,
-    widgets::{HelpPane, MarkPane},
+    app::{FocussedPane::*, SplitPane, TerminalApp},
+    path_of, run_shell_in, sorted_entries,
+    widgets::{ErrorsPane, HelpPane, MarkPane},
 };
-use dua::traverse::TreeIndex;
+use dua::{traverse::TreeIndex, ByteFormat};
 use itertools::Itertools;
-use petgraph::{visit::Bfs, Direction};
-use std::{fs, io, path::PathBuf};
+use petgraph::{
+    visit::{Bfs, DfsPostOrder},
+    Direction,
+};
+use std::{fs, io, path::PathBuf, time::Instant};
 use termion::event::Key;
 use tui::backend::Backend;
 use tui_react::Terminal;
@@ -21,12 +24,17 @@ pub enum CursorDirection {
 
 impl CursorDirection {
     pub fn move_cursor(&self, n: usize) -> usize {
+        self.move_cursor_times(n, 1)
+    }
+
+    /// Move the cursor at `n` as if moving `times` times in our direction.
+    pub fn move_cursor_times(&self, n: usize, times: usize) -> usize {
         use CursorDirection::*;
         match self {
-            Down => n.saturating_add(1),
-            Up => n.saturating_sub(1),
-            PageDown => n.saturating_add(10),
-            PageUp => n.saturating_sub(10),
+            Down => n.saturating_add(times),
+            Up => n.saturating_sub(times),
+            PageDown => n.saturating_add(times.saturating_mul(10)),
+            PageUp => n.saturating_sub(times.saturating_mul(10)),
         }
     }
 }
@@ -39,17 +47,19 @@ impl TerminalApp {
         self.state.focussed = match (
             self.state.focussed,
             &self.window.help_pane,
+            &self.window.errors_pane,
             &mut self.window.mark_pane,
         ) {
-            (Main, Some(_), _) => Help,
-            (Help, _, Some(ref mut pane)) => {
+            (Main, Some(_), _, _) => Help,
+            (Main, None, Some(_), _) => Errors,
+            (Help | Errors, _, _, Some(ref mut pane)) => {
                 pane.set_focus(true);
                 Mark
             }
-            (Help, _, None) => Main,
-            (Mark, _, _) => Main,
-            (Main, None, None) => Main,
-            (Main, None, Some(ref mut pane)) => {
+            (Help | Errors, _, _, None) => Main,
+            (Mark, _, _, _) => Main,
+            (Main, None, None, None) => Main,
+            (Main, None, None, Some(ref mut pane)) => {
                 pane.set_focus(true);
                 Mark
             }
@@ -58,7 +68,8 @@ impl TerminalApp {
 
     pub fn toggle_help_pane(&mut self) {
         self.state.focussed = match self.state.focussed {
-            Main | Mark => {
+            Main | Mark | Errors => {
+                self.window.errors_pane = None;
                 self.window.help_pane = Some(HelpPane::default());
                 Help
             }
@@ -69,16 +80,99 @@ impl TerminalApp {
         }
     }
 
+    /// Show or hide the paths of all entries which could not be read, in place of the help pane.
+    pub fn toggle_errors_pane(&mut self) {
+        self.state.focussed = match self.state.focussed {
+            Main | Mark | Help => {
+                let tree = &self.traversal.tree;
+                let mut paths: Vec<_> = tree
+                    .node_indices()
+                    .filter(|idx| tree[*idx].metadata_io_error)
+                    .map(|idx| path_of(tree, idx))
+                    .collect();
+                paths.sort();
+                self.window.help_pane = None;
+                self.window.errors_pane = Some(ErrorsPane {
+                    num_unlisted: self.traversal.io_errors.saturating_sub(paths.len() as u64),
+                    paths,
+                    ..Default::default()
+                });
+                Errors
+            }
+            Errors => {
+                self.window.errors_pane = None;
+                Main
+            }
+        }
+    }
+
     pub fn update_message(&mut self) {
         self.state.message = None;
     }
 
+    /// Add `digit` to the count which repeats the next move, like in vim.
+    pub fn extend_count(&mut self, count: Option<usize>, digit: u32) {
+        let count = count
+            .unwrap_or(0)
+            .saturating_mul(10)
+            .saturating_add(digit as usize);
+        self.state.pending_count = Some(count);
+        self.state.message = Some(format!("count: {}", count));
+    }
+
+    /// Add `c` to the beginning of a name `typed` so far and select the first entry whose name starts with it,
+    /// ignoring case. The next character extends the name if it's typed within `TYPE_AHEAD_TIMEOUT`.
+    pub fn type_ahead(&mut self, typed: Option<String>, c: char) {
+        let mut name = typed.unwrap_or_default();
+        name.push(c);
+        let prefix = name.to_lowercase();
+        match self.state.entries.iter().find(|e| {
+            e.data
+                .name
+                .to_string_lossy()
+                .to_lowercase()
+                .starts_with(&prefix)
+        }) {
+            Some(entry) => {
+                self.state.selected = Some(entry.index);
+                self.state.bookmarks.insert(self.state.root, entry.index);
+                self.state.message = Some(format!("jump to: {}", name));
+            }
+            None => self.state.message = Some(format!("no entry starts with '{}'", name)),
+        }
+        self.state.type_ahead = Some((name, Instant::now()));
+    }
+
     pub fn open_that(&mut self) {
         if let Some(ref idx) = self.state.selected {
             open::that(path_of(&self.traversal.tree, *idx)).ok();
         }
     }
 
+    /// Run a shell in the selected directory and redraw everything once it exits.
+    pub fn open_shell<B>(&mut self, terminal: &mut Terminal<B>)
+    where
+        B: Backend,
+    {
+        let dir = match self.state.selected {
+            Some(idx) => path_of(&self.traversal.tree, idx),
+            None => return,
+        };
+        if !dir.is_dir() {
+            self.state.message = Some("Select a directory to open a shell in".into());
+            return;
+        }
+        let res = run_shell_in(&dir);
+        terminal
+            .size()
+            .and_then(|area| terminal.resize(area))
+            .and_then(|_| terminal.hide_cursor())
+            .ok();
+        if let Err(err) = res {
+            self.state.message = Some(err.to_string());
+        }
+    }
+
     pub fn exit_node(&mut self) {
         match self
             .traversal
@@ -95,7 +189,7 @@ impl TerminalApp {
                     .bookmarks
                     .get(&parent_idx)
                     .copied()
-                    .or_else(|| self.state.entries.get(0).map(|b| b.index));
+                    .or_else(|| self.state.entries.first().map(|b| b.index));
             }
             None => self.state.message = Some("Top level reached".into()),
         }
@@ -134,12 +228,16 @@ impl TerminalApp {
     }
 
     pub fn change_entry_selection(&mut self, direction: CursorDirection) {
+        self.change_entry_selection_times(direction, 1)
+    }
+
+    pub fn change_entry_selection_times(&mut self, direction: CursorDirection, times: usize) {
         let entries = &self.state.entries;
         let next_selected_pos = match self.state.selected {
             Some(ref selected) => entries
                 .iter()
                 .find_position(|b| b.index == *selected)
-                .map(|(idx, _)| direction.move_cursor(idx))
+                .map(|(idx, _)| direction.move_cursor_times(idx, times))
                 .unwrap_or(0),
             None => 0,
         };
@@ -157,6 +255,60 @@ impl TerminalApp {
         self.state.sorting.toggle_size();
         self.state.entries =
             sorted_entries(&self.traversal.tree, self.state.root, self.state.sorting);
+        self.refresh_split_entries();
+    }
+
+    /// Show a second list of entries next to the current one, starting at the same directory, or hide it again.
+    pub fn toggle_split(&mut self) {
+        self.state.split = match self.state.split {
+            Some(_) => None,
+            None => Some(SplitPane {
+                root: self.state.root,
+                selected: self.state.selected,
+                entries: sorted_entries(&self.traversal.tree, self.state.root, self.state.sorting),
+                is_right: true,
+            }),
+        };
+    }
+
+    /// Let the keys act on the other list of entries if there are two of them.
+    pub fn switch_split_side(&mut self) {
+        if let Some(split) = self.state.split.as_mut() {
+            std::mem::swap(&mut split.root, &mut self.state.root);
+            std::mem::swap(&mut split.selected, &mut self.state.selected);
+            std::mem::swap(&mut split.entries, &mut self.state.entries);
+            split.is_right = !split.is_right;
+        }
+    }
+
+    /// Re-read the entries of the second list from the tree, moving it to the top level if its directory is gone.
+    pub fn refresh_split_entries(&mut self) {
+        let tree = &self.traversal.tree;
+        if let Some(split) = self.state.split.as_mut() {
+            if tree.node_weight(split.root).is_none() {
+                split.root = self.traversal.root_index;
+            }
+            split.entries = sorted_entries(tree, split.root, self.state.sorting);
+            if split
+                .selected
+                .is_none_or(|selected| split.entries.iter().all(|e| e.index != selected))
+            {
+                split.selected = split.entries.first().map(|e| e.index);
+            }
+        }
+    }
+
+    /// Switch between metric and binary units, keeping fixed units at their magnitude. Raw bytes switch to binary units.
+    pub fn toggle_byte_units(&mut self) {
+        use ByteFormat::*;
+        self.display.byte_format = match self.display.byte_format {
+            Metric | Bytes => Binary,
+            Binary => Metric,
+            GB => GiB,
+            GiB => GB,
+            MB => MiB,
+            MiB => MB,
+        };
     }
 
     pub fn mark_entry(&mut self, advance_cursor: bool) {
@@ -180,6 +332,26 @@ impl TerminalApp {
         }
     }
 
+    /// Mark all entries of the current directory, or unmark all of them if they are all marked already.
+    pub fn mark_all_entries(&mut self) {
+        let is_marked = |pane: &Option<MarkPane>, index| {
+            pane.as_ref()
+                .is_some_and(|p: &MarkPane| p.marked().contains_key(&index))
+        };
+        let mut pane = self.window.mark_pane.take();
+        let unmark = self.state.entries.iter().all(|e| is_marked(&pane, e.index));
+        for entry in &self.state.entries {
+            if is_marked(&pane, entry.index) == unmark {
+                pane = pane.take().unwrap_or_default().toggle_index(
+                    entry.index,
+                    &self.traversal.tree,
+                    entry.is_dir,
+                );
+            }
+        }
+        self.window.mark_pane = pane;
+    }
+
     fn set_root(&mut self, root: TreeIndex) {
         self.state.root = root;
         self.state.entries = sorted_entries(&self.traversal.tree, root, self.state.sorting);
@@ -187,9 +359,11 @@ impl TerminalApp {
 
     pub fn delete_entry(&mut self, index: TreeIndex) -> Result<usize, usize> {
         let mut entries_deleted = 0;
-        if let Some(_entry) = self.traversal.tree.node_weight(index) {
+        if let Some(entry) = self.traversal.tree.node_weight(index) {
+            let size = entry.size;
             let path_to_delete = path_of(&self.traversal.tree, index);
-            delete_directory_recursively(path_to_delete)?;
+            delete_directory_recursively(path_to_delete.clone())?;
+            self.state.deleted.push((path_to_delete, size));
             let parent_idx = self
                 .traversal
                 .tree
@@ -202,6 +376,7 @@ impl TerminalApp {
                 self.traversal.entries_traversed -= 1;
                 entries_deleted += 1;
             }
+            self.recompute_sizes_recursively(parent_idx);
             self.state.entries =
                 sorted_entries(&self.traversal.tree, self.state.root, self.state.sorting);
             if self.traversal.tree.node_weight(self.state.root).is_none() {
@@ -213,13 +388,42 @@ impl TerminalApp {
                 .and_then(|selected| self.state.entries.iter().find(|e| e.index == selected))
                 .is_none()
             {
-                self.state.selected = self.state.entries.get(0).map(|e| e.index);
+                self.state.selected = self.state.entries.first().map(|e| e.index);
             }
-            self.recompute_sizes_recursively(parent_idx);
+            self.refresh_split_entries();
         }
         Ok(entries_deleted)
     }
 
+    /// Sum up the sizes of all directories below the current one from the sizes of their entries, bottom-up, along
+    /// with the sizes of the directories above it. Nothing is read from the filesystem again.
+    pub fn recompute_subtree_sizes(&mut self) {
+        let tree = &mut self.traversal.tree;
+        let mut num_directories = 0;
+        let mut dfs = DfsPostOrder::new(&*tree, self.state.root);
+        while let Some(index) = dfs.next(&*tree) {
+            let mut children = tree
+                .neighbors_directed(index, Direction::Outgoing)
+                .filter_map(|idx| tree.node_weight(idx).map(|w| w.size))
+                .peekable();
+            if children.peek().is_none() {
+                continue;
+            }
+            let size = children.sum();
+            tree.node_weight_mut(index).expect("valid index").size = size;
+            num_directories += 1;
+        }
+        self.recompute_sizes_recursively(self.state.root);
+        self.state.entries =
+            sorted_entries(&self.traversal.tree, self.state.root, self.state.sorting);
+        self.refresh_split_entries();
+        self.state.message = Some(format!(
+            "Recomputed the sizes of {} director{}",
+            num_directories,
+            if num_directories == 1 { "y" } else { "ies" }
+        ));
+    }
+
     fn recompute_sizes_recursively(&mut self, mut index: TreeIndex) {
         loop {
             self.traversal
@@ -256,6 +460,11 @@ impl TerminalApp {
         let res = self.window.mark_pane.take().and_then(|p| p.key(key));
         self.window.mark_pane = match res {
             Some((pane, mode)) => match mode {
+                Some(MarkMode::Delete) if self.state.is_scanning => {
+                    self.state.message =
+                        Some("Entries can be deleted once scanning is done".into());
+                    Some(pane)
+                }
                 Some(MarkMode::Delete) => {
                     self.state.message = Some("Deleting entries...".to_string());
                     let mut entries_deleted = 0;
@@ -274,7 +483,18 @@ impl TerminalApp {
                         }
                     });
                     self.state.message = None;
-                    res
+                    res.and_then(|pane| {
+                        let (pane, num_dropped) =
+                            pane.reconcile(&self.traversal.tree, self.traversal.root_index);
+                        if num_dropped > 0 {
+                            self.state.message = Some(format!(
+                                "Unmarked {} entr{} which no longer exist",
+                                num_dropped,
+                                if num_dropped == 1 { "y" } else { "ies" }
+                            ));
+                        }
+                        pane
+                    })
                 }
                 None => Some(pane),
             },
@@ -326,7 +546,9 @@ fn delete_directory_recursively(path: PathBuf) -> Result<(), usize> {
                     }
                 }
             }
-            Err(ref e) if e.kind() == io::ErrorKind::Other => {
+            Err(ref e)
+                if e.kind() == io::ErrorKind::Other || e.kind() == io::ErrorKind::NotADirectory =>
+            {
                 // assume file, save IOps
                 num_errors += into_error_count(fs::remove_file(path));
                 continue;
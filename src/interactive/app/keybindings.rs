@@ -0,0 +1,101 @@
+use failure::{bail, Error};
+use termion::event::Key;
+
+/// The actions which can be bound to other keys, along with the key triggering them by default
+pub const ACTIONS: &[(&str, Key)] = &[
+    ("quit", Key::Char('q')),
+    ("help", Key::Char('?')),
+    ("errors", Key::Char('e')),
+    ("cycle-focus", Key::Char('\t')),
+    ("down", Key::Char('j')),
+    ("up", Key::Char('k')),
+    ("page-down", Key::Ctrl('d')),
+    ("page-up", Key::Ctrl('u')),
+    ("enter", Key::Char('o')),
+    ("exit", Key::Char('u')),
+    ("toggle-mark", Key::Char(' ')),
+    ("toggle-mark-and-move", Key::Char('d')),
+    ("toggle-mark-all", Key::Ctrl('a')),
+    ("sort", Key::Char('s')),
+    ("cycle-bars", Key::Char('g')),
+    ("toggle-units", Key::Char('m')),
+    ("toggle-own-size", Key::Char('S')),
+    ("open", Key::Char('O')),
+    ("shell", Key::Char('b')),
+    ("delete", Key::Ctrl('r')),
+];
+
+/// Parse the name of a key, which is either a single character like `x`, a character pressed along with control
+/// like `ctrl-x`, or one of `tab`, `enter`, `space`, `esc`, `backspace`, `up`, `down`, `left`, `right`, `pageup` and `pagedown`.
+pub fn parse_key(name: &str) -> Result<Key, Error> {
+    let mut chars = name.chars();
+    Ok(match (chars.next(), chars.next(), name) {
+        (Some(c), None, _) => Key::Char(c),
+        (_, _, "tab") => Key::Char('\t'),
+        (_, _, "enter") => Key::Char('\n'),
+        (_, _, "space") => Key::Char(' '),
+        (_, _, "esc") => Key::Esc,
+        (_, _, "backspace") => Key::Backspace,
+        (_, _, "up") => Key::Up,
+        (_, _, "down") => Key::Down,
+        (_, _, "left") => Key::Left,
+        (_, _, "right") => Key::Right,
+        (_, _, "pageup") => Key::PageUp,
+        (_, _, "pagedown") => Key::PageDown,
+        _ => match name
+            .strip_prefix("ctrl-")
+            .map(|c| (c.chars().next(), c.chars().count()))
+        {
+            Some((Some(c), 1)) => Key::Ctrl(c),
+            _ => bail!("Unknown key '{}'", name),
+        },
+    })
+}
+
+/// The key bound to `action` by default, or `None` if there is no such action.
+pub fn default_key_of(action: &str) -> Option<Key> {
+    ACTIONS
+        .iter()
+        .find(|(name, _)| *name == action)
+        .map(|(_, key)| *key)
+}
+
+/// Returns the default key of the action `key` is bound to in `bindings`, which are pairs of a custom key and
+/// the default key it stands for, or `key` itself if it is not bound.
+pub fn translate(bindings: &[(Key, Key)], key: Key) -> Key {
+    bindings
+        .iter()
+        .find(|(custom, _)| *custom == key)
+        .map_or(key, |(_, default)| *default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_parsed_by_name() -> Result<(), Error> {
+        assert_eq!(parse_key("x")?, Key::Char('x'));
+        assert_eq!(parse_key("ü")?, Key::Char('ü'));
+        assert_eq!(parse_key("ctrl-x")?, Key::Ctrl('x'));
+        assert_eq!(parse_key("pagedown")?, Key::PageDown);
+        assert_eq!(parse_key("space")?, Key::Char(' '));
+        assert!(parse_key("ctrl-xy").is_err());
+        assert!(parse_key("").is_err());
+        assert!(parse_key("hyper").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn bound_keys_are_translated_into_the_default_key_of_their_action() {
+        let bindings = [(Key::Char('x'), Key::Char('d'))];
+        assert_eq!(translate(&bindings, Key::Char('x')), Key::Char('d'));
+        assert_eq!(
+            translate(&bindings, Key::Char('d')),
+            Key::Char('d'),
+            "default keys keep working"
+        );
+        assert_eq!(default_key_of("toggle-mark-and-move"), Some(Key::Char('d')));
+        assert_eq!(default_key_of("fly"), None);
+    }
+}
@@ -1,9 +1,17 @@
 mod bytevis;
 mod common;
 mod eventloop;
+mod free_space;
 mod handlers;
+mod keybindings;
+mod report;
+mod shell;
 
 pub use bytevis::*;
 pub use common::*;
 pub use eventloop::*;
+pub use free_space::*;
 pub use handlers::*;
+pub use keybindings::*;
+pub use report::*;
+pub use shell::*;
@@ -0,0 +1,64 @@
+//! Summarize what happened in an interactive session, for scripts wrapping it.
+use dua::json_escaped;
+use std::path::PathBuf;
+
+/// The entries marked and deleted during an interactive session, each with its size as displayed
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SessionReport {
+    /// The entries which were still marked when the session ended, in the order they were marked
+    pub marked: Vec<(PathBuf, u64)>,
+    /// The entries which were deleted, in the order of their deletion
+    pub deleted: Vec<(PathBuf, u64)>,
+}
+
+impl SessionReport {
+    /// The amount of bytes the deleted entries occupied.
+    pub fn bytes_freed(&self) -> u64 {
+        self.deleted.iter().map(|(_, size)| size).sum()
+    }
+
+    /// The report as a single JSON object, like
+    /// `{"marked":[{"path":"a","bytes":1}],"deleted":[],"bytes_freed":0}`.
+    pub fn to_json(&self) -> String {
+        fn entries(entries: &[(PathBuf, u64)]) -> String {
+            entries
+                .iter()
+                .map(|(path, size)| {
+                    format!(
+                        r#"{{"path":"{}","bytes":{}}}"#,
+                        json_escaped(&path.to_string_lossy()),
+                        size
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+        }
+        format!(
+            r#"{{"marked":[{}],"deleted":[{}],"bytes_freed":{}}}"#,
+            entries(&self.marked),
+            entries(&self.deleted),
+            self.bytes_freed()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_are_written_as_a_single_json_object() {
+        let report = SessionReport {
+            marked: vec![("a \"b\"".into(), 1)],
+            deleted: vec![("c".into(), 2), ("d".into(), 3)],
+        };
+        assert_eq!(
+            report.to_json(),
+            r#"{"marked":[{"path":"a \"b\"","bytes":1}],"deleted":[{"path":"c","bytes":2},{"path":"d","bytes":3}],"bytes_freed":5}"#
+        );
+        assert_eq!(
+            SessionReport::default().to_json(),
+            r#"{"marked":[],"deleted":[],"bytes_freed":0}"#
+        );
+    }
+}
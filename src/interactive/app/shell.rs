@@ -0,0 +1,62 @@
+//! Temporarily hand the terminal over to an interactive shell.
+use failure::Error;
+use std::path::Path;
+use termion::event::Key;
+
+/// The key opening a shell by default. The thread reading keys pauses after reading it, until the shell exited.
+pub const SHELL_KEY: Key = Key::Char('b');
+
+/// Leave the alternate screen and raw mode, run `$SHELL` (or `/bin/sh`) in `dir` until it exits, and restore the
+/// terminal to what it was before. The caller has to redraw the whole screen afterwards.
+///
+/// Nothing else may read from the terminal while the shell runs, or it would steal the shell's input.
+#[cfg(all(unix, feature = "shell"))]
+pub fn run_shell_in(dir: &Path) -> Result<(), Error> {
+    use failure::{bail, ResultExt};
+    use std::{
+        env,
+        io::{self, Write},
+        process::{Command, Stdio},
+    };
+    use termion::{cursor, screen};
+
+    fn stty(args: &[&str]) -> Result<String, Error> {
+        let output = Command::new("stty")
+            .args(args)
+            .stdin(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .output()
+            .with_context(|_| "Could not run 'stty' to change the terminal mode")?;
+        if !output.status.success() {
+            bail!("'stty {}' failed with {}", args.join(" "), output.status);
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+    }
+
+    let raw_mode = stty(&["-g"])?;
+    let mut stdout = io::stdout();
+    write!(stdout, "{}{}", screen::ToMainScreen, cursor::Show)?;
+    stdout.flush()?;
+
+    let shell = env::var_os("SHELL")
+        .filter(|shell| !shell.is_empty())
+        .unwrap_or_else(|| "/bin/sh".into());
+    let status = stty(&["sane"]).and_then(|_| {
+        Command::new(&shell)
+            .current_dir(dir)
+            .status()
+            .with_context(|_| format!("Could not run the shell '{}'", shell.to_string_lossy()))
+            .map_err(Into::into)
+    });
+
+    let restored = stty(&[&raw_mode]);
+    write!(stdout, "{}{}", screen::ToAlternateScreen, cursor::Hide)?;
+    stdout.flush()?;
+    restored?;
+    status.map(|_| ())
+}
+
+#[cfg(not(all(unix, feature = "shell")))]
+pub fn run_shell_in(_dir: &Path) -> Result<(), Error> {
+    failure::bail!("Opening a shell is only supported on Unix, with the 'shell' feature enabled")
+}
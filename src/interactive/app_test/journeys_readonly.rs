@@ -3,7 +3,7 @@ use crate::interactive::app_test::utils::{
     node_by_name,
 };
 use crate::interactive::app_test::FIXTURE_PATH;
-use crate::interactive::SortMode;
+use crate::interactive::{SortMode, TerminalApp};
 use failure::Error;
 use pretty_assertions::assert_eq;
 use std::ffi::OsString;
@@ -159,9 +159,10 @@ fn simple_user_journey_read_only() -> Result<(), Error> {
                 "it marks only a single node",
             );
             assert!(
-                app.window.mark_pane.as_ref().map_or(false, |p| p
-                    .marked()
-                    .contains_key(&previously_selected_index)),
+                app.window
+                    .mark_pane
+                    .as_ref()
+                    .is_some_and(|p| p.marked().contains_key(&previously_selected_index)),
                 "it marks the selected node"
             );
             assert_eq!(
@@ -199,9 +200,10 @@ fn simple_user_journey_read_only() -> Result<(), Error> {
             );
 
             assert!(
-                app.window.mark_pane.as_ref().map_or(false, |p| p
-                    .marked()
-                    .contains_key(&previously_selected_index)),
+                app.window
+                    .mark_pane
+                    .as_ref()
+                    .is_some_and(|p| p.marked().contains_key(&previously_selected_index)),
                 "it leaves the first selected entry marked"
             );
         }
@@ -257,3 +259,46 @@ fn simple_user_journey_read_only() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[test]
+fn counts_typed_before_moves_repeat_them() -> Result<(), Error> {
+    let (mut terminal, mut app) = initialized_app_and_terminal_from_fixture(&["sample-01"])?;
+    let selected_name = |app: &TerminalApp| {
+        node_by_index(app, *app.state.selected.as_ref().unwrap())
+            .name
+            .to_owned()
+    };
+
+    // Entering the directory, which selects its largest entry
+    app.process_events(&mut terminal, b"o".keys())?;
+    assert_eq!(selected_name(&app), OsString::from("dir"));
+
+    app.process_events(&mut terminal, b"2j".keys())?;
+    assert_eq!(
+        selected_name(&app),
+        OsString::from("a"),
+        "it moves down twice"
+    );
+
+    app.process_events(&mut terminal, b"10".keys())?;
+    assert_eq!(
+        app.state.message.as_deref(),
+        Some("count: 10"),
+        "the pending count is shown"
+    );
+    app.process_events(&mut terminal, b"k".keys())?;
+    assert_eq!(
+        selected_name(&app),
+        OsString::from("dir"),
+        "it stops at the first entry"
+    );
+    assert_eq!(app.state.message, None, "the count is used up");
+
+    app.process_events(&mut terminal, b"3gj".keys())?;
+    assert_eq!(
+        selected_name(&app),
+        OsString::from(".hidden.666"),
+        "any other key resets the count"
+    );
+    Ok(())
+}
@@ -1,5 +1,8 @@
-use crate::interactive::app_test::utils::{
-    initialized_app_and_terminal_from_paths, WritableFixture,
+use crate::interactive::{
+    app_test::utils::{
+        index_by_name, initialized_app_and_terminal_from_paths, node_by_name, WritableFixture,
+    },
+    path_of, SessionReport, TerminalApp,
 };
 use failure::Error;
 use pretty_assertions::assert_eq;
@@ -9,7 +12,8 @@ use termion::input::TermRead;
 #[test]
 fn basic_user_journey_with_deletion() -> Result<(), Error> {
     let fixture = WritableFixture::from("sample-02");
-    let (mut terminal, mut app) = initialized_app_and_terminal_from_paths(&[fixture.root.clone()])?;
+    let (mut terminal, mut app) =
+        initialized_app_and_terminal_from_paths(std::slice::from_ref(&fixture.root))?;
 
     // With a selection of items
     app.process_events(&mut terminal, b"doddd".keys())?;
@@ -29,7 +33,7 @@ fn basic_user_journey_with_deletion() -> Result<(), Error> {
     // When selecting the marker window and pressing the combination to delete entries
     app.process_events(
         &mut terminal,
-        vec![Ok(Key::Char('\t')), Ok(Key::Ctrl('r'))].into_iter(),
+        vec![Ok(Key::Char('\t')), Ok(Key::Ctrl('r')), Ok(Key::Char('y'))].into_iter(),
     )?;
     assert_eq!(
         app.window.mark_pane.is_none(),
@@ -48,3 +52,96 @@ fn basic_user_journey_with_deletion() -> Result<(), Error> {
     );
     Ok(())
 }
+
+#[test]
+fn deletion_reduces_the_size_of_all_ancestors() -> Result<(), Error> {
+    let fixture = WritableFixture::from("sample-02");
+    let (mut terminal, mut app) =
+        initialized_app_and_terminal_from_paths(std::slice::from_ref(&fixture.root))?;
+    let size_of = |app: &TerminalApp, name: &str| node_by_name(app, name).size;
+    let root_size = node_by_name(&app, &fixture.root).size;
+    let (dir_size, sub_size, file_size) = (
+        size_of(&app, "dir"),
+        size_of(&app, "sub"),
+        size_of(&app, "e"),
+    );
+
+    // When marking the only file in 'dir/sub', and going back to 'dir'
+    app.process_events(&mut terminal, b"ooo u".keys())?;
+    assert_eq!(
+        app.window
+            .mark_pane
+            .as_ref()
+            .map(|p| p.marked().keys().copied().collect::<Vec<_>>()),
+        Some(vec![index_by_name(&app, "e")]),
+        "expecting only 'e' to be marked"
+    );
+
+    // And deleting it
+    app.process_events(
+        &mut terminal,
+        vec![Ok(Key::Char('\t')), Ok(Key::Ctrl('r')), Ok(Key::Char('y'))].into_iter(),
+    )?;
+    assert_eq!(
+        fixture.as_ref().join("dir").join("sub").join("e").exists(),
+        false,
+        "the file should have been deleted"
+    );
+    assert_eq!(
+        size_of(&app, "sub"),
+        sub_size - file_size,
+        "the parent shrinks by the size of the deleted file"
+    );
+    assert_eq!(
+        (size_of(&app, "dir"), node_by_name(&app, &fixture.root).size),
+        (dir_size - file_size, root_size - file_size),
+        "and so do all of its ancestors"
+    );
+    assert_eq!(
+        app.traversal.total_bytes,
+        Some(root_size - file_size),
+        "including the total"
+    );
+    assert_eq!(
+        app.state
+            .entries
+            .iter()
+            .find(|e| e.index == index_by_name(&app, "sub"))
+            .map(|e| e.data.size),
+        Some(sub_size - file_size),
+        "the entries on display are updated as well"
+    );
+    Ok(())
+}
+
+#[test]
+fn the_report_lists_deleted_and_still_marked_entries() -> Result<(), Error> {
+    let fixture = WritableFixture::from("sample-02");
+    let (mut terminal, mut app) =
+        initialized_app_and_terminal_from_paths(std::slice::from_ref(&fixture.root))?;
+    let file_size = node_by_name(&app, "e").size;
+    let file_path = path_of(&app.traversal.tree, index_by_name(&app, "e"));
+
+    // When deleting 'dir/sub/e', and marking an entry of 'dir' afterwards
+    app.process_events(&mut terminal, b"ooo u".keys())?;
+    app.process_events(
+        &mut terminal,
+        vec![Ok(Key::Char('\t')), Ok(Key::Ctrl('r')), Ok(Key::Char('y'))].into_iter(),
+    )?;
+    let (_, report) = app.process_events(&mut terminal, b" ".keys())?;
+
+    let marked = app.state.selected.expect("an entry to be selected");
+    let marked = (
+        path_of(&app.traversal.tree, marked),
+        app.traversal.tree[marked].size,
+    );
+    assert_eq!(
+        report,
+        SessionReport {
+            marked: vec![marked],
+            deleted: vec![(file_path, file_size)],
+        }
+    );
+    assert_eq!(report.bytes_freed(), file_size);
+    Ok(())
+}
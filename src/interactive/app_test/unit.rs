@@ -1,8 +1,23 @@
-use crate::interactive::app_test::utils::{
-    debug, initialized_app_and_terminal_from_fixture, sample_01_tree, sample_02_tree,
+use crate::interactive::{
+    app_test::utils::{
+        debug, fixture_str, index_by_name, initialized_app_and_terminal_from_fixture,
+        make_add_node, node_by_name, sample_01_tree, sample_02_tree, without_own_sizes,
+    },
+    widgets::{
+        Entries, EntriesProps, Legend, LegendProps, MarkMode, MarkPane, MarkPaneProps, COLOR_LARGE,
+        INCOMPLETE_MARKER,
+    },
+    FocussedPane, ELLIPSIS, NUM_PATHS_TO_CONFIRM, TYPE_AHEAD_TIMEOUT,
 };
+use dua::{traverse::Tree, ByteFormat};
 use failure::Error;
 use pretty_assertions::assert_eq;
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+use termion::{event::Key, input::TermRead};
+use tui::{buffer::Buffer, layout::Rect, style::Style};
 
 #[test]
 fn it_can_handle_ending_traversal_reaching_top_but_skipping_levels() -> Result<(), Error> {
@@ -10,7 +25,7 @@ fn it_can_handle_ending_traversal_reaching_top_but_skipping_levels() -> Result<(
     let expected_tree = sample_01_tree();
 
     assert_eq!(
-        debug(app.traversal.tree),
+        debug(without_own_sizes(app.traversal.tree)),
         debug(expected_tree),
         "filesystem graph is stable and matches the directory structure"
     );
@@ -23,9 +38,762 @@ fn it_can_handle_ending_traversal_without_reaching_the_top() -> Result<(), Error
     let expected_tree = sample_02_tree();
 
     assert_eq!(
-        debug(app.traversal.tree),
+        debug(without_own_sizes(app.traversal.tree)),
         debug(expected_tree),
         "filesystem graph is stable and matches the directory structure"
     );
     Ok(())
 }
+
+#[test]
+fn mark_pane_drops_sizes_in_narrow_areas_instead_of_overlapping() -> Result<(), Error> {
+    let (_, app) = initialized_app_and_terminal_from_fixture(&["sample-01"])?;
+    let pane = MarkPane::default()
+        .toggle_index(index_by_name(&app, "dir"), &app.traversal.tree, true)
+        .expect("one marked entry");
+    let mut pane = pane
+        .toggle_index(index_by_name(&app, "a"), &app.traversal.tree, false)
+        .expect("two marked entries");
+
+    let props = |ellipsis| MarkPaneProps {
+        border_style: Style::default(),
+        format: ByteFormat::Metric,
+        ellipsis,
+        num_paths_to_confirm: NUM_PATHS_TO_CONFIRM,
+    };
+    for width in 0..=40 {
+        let area = Rect::new(0, 0, width, 5);
+        pane.render(props(ELLIPSIS), area, &mut Buffer::empty(area));
+        pane.render(props("..."), area, &mut Buffer::empty(area));
+    }
+
+    let mut rows_with_ellipsis = |ellipsis| -> Vec<String> {
+        let area = Rect::new(0, 0, 10, 5);
+        let mut buf = Buffer::empty(area);
+        pane.render(props(ellipsis), area, &mut buf);
+        (1..3)
+            .map(|y| (1..9).map(|x| buf.get(x, y).symbol.as_str()).collect())
+            .collect()
+    };
+    assert_eq!(
+        rows_with_ellipsis(ELLIPSIS),
+        vec![" …/dir  ", " te…/a  "],
+        "paths use all available space, without sizes, and keep their last component"
+    );
+    assert_eq!(
+        rows_with_ellipsis("..."),
+        vec![" t...r  ", " .../a  "],
+        "ellipses spanning multiple columns are accounted for"
+    );
+    Ok(())
+}
+
+#[test]
+fn mark_pane_shows_a_sample_of_paths_before_deleting_them() -> Result<(), Error> {
+    let (_, app) = initialized_app_and_terminal_from_fixture(&["sample-01"])?;
+    let mut pane = Some(MarkPane::default());
+    for name in &["dir", "a", "b.empty"] {
+        pane = pane
+            .and_then(|p| p.toggle_index(index_by_name(&app, name), &app.traversal.tree, false));
+    }
+    let mut pane = pane.expect("three marked entries");
+    pane.set_focus(true);
+    let (mut pane, mode) = pane.key(Key::Ctrl('r')).expect("pane to stay");
+    assert!(mode.is_none(), "nothing is deleted before confirmation");
+
+    let area = Rect::new(0, 0, 60, 10);
+    let mut buf = Buffer::empty(area);
+    let props = MarkPaneProps {
+        border_style: Style::default(),
+        format: ByteFormat::Metric,
+        ellipsis: ELLIPSIS,
+        num_paths_to_confirm: 2,
+    };
+    pane.render(props, area, &mut buf);
+    let rows: Vec<String> = (1..5)
+        .map(|y| {
+            (1..59)
+                .map(|x| buf.get(x, y).symbol.as_str())
+                .collect::<String>()
+                .trim_end()
+                .to_owned()
+        })
+        .collect();
+    let fixture = fixture_str("sample-01");
+    assert_eq!(
+        rows[1..].to_vec(),
+        vec![
+            format!("   {}/dir", fixture),
+            format!("   {}/a", fixture),
+            "   ...and 1 more".to_string(),
+        ],
+        "the first marked paths are listed, and the amount of the remaining ones"
+    );
+    assert!(rows[0].starts_with(" Delete 3 items"), "{}", rows[0]);
+
+    let (pane, mode) = pane.key(Key::Char('n')).expect("pane to stay");
+    assert!(mode.is_none(), "any other key cancels the deletion");
+    let (_, mode) = pane
+        .key(Key::Ctrl('r'))
+        .and_then(|(pane, _)| pane.key(Key::Char('y')))
+        .expect("pane to stay");
+    assert!(
+        matches!(mode, Some(MarkMode::Delete)),
+        "'y' confirms the deletion"
+    );
+    Ok(())
+}
+
+#[test]
+fn mark_pane_sorts_by_size_or_path_and_keeps_the_selected_entry() -> Result<(), Error> {
+    let (_, app) = initialized_app_and_terminal_from_fixture(&["sample-01"])?;
+    let mut pane = Some(MarkPane::default());
+    for name in &["b.empty", "dir", "a"] {
+        pane = pane
+            .and_then(|p| p.toggle_index(index_by_name(&app, name), &app.traversal.tree, false));
+    }
+    let mut pane = pane.expect("three marked entries");
+    pane.set_focus(true);
+    let fixture = fixture_str("sample-01");
+    let paths = |names: &[&str]| -> Vec<PathBuf> {
+        names
+            .iter()
+            .map(|name| PathBuf::from(format!("{}/{}", fixture, name)))
+            .collect()
+    };
+    let press = |pane: MarkPane, key: char| pane.key(Key::Char(key)).expect("pane to stay").0;
+
+    let pane = press(pane, 's');
+    assert_eq!(pane.listed_paths(), paths(&["dir", "a", "b.empty"]));
+    assert_eq!(
+        pane.selected(),
+        Some(1),
+        "the selection moves with its entry"
+    );
+
+    let pane = press(pane, 'n');
+    assert_eq!(pane.listed_paths(), paths(&["a", "b.empty", "dir"]));
+    assert_eq!(pane.selected(), Some(0));
+
+    let pane = press(pane, 'n');
+    assert_eq!(
+        pane.listed_paths(),
+        paths(&["b.empty", "dir", "a"]),
+        "toggling again lists entries in the order they were marked"
+    );
+    assert_eq!(pane.selected(), Some(2));
+    Ok(())
+}
+
+#[test]
+fn marks_are_found_by_path_in_a_rebuilt_tree() -> Result<(), Error> {
+    let (_, app) = initialized_app_and_terminal_from_fixture(&["sample-01"])?;
+    let tree = &app.traversal.tree;
+    let sub = tree
+        .neighbors(index_by_name(&app, "dir"))
+        .find(|idx| tree[*idx].name == "sub")
+        .expect("sub within dir");
+    let mut pane = Some(MarkPane::default());
+    for idx in &[index_by_name(&app, "a"), sub, index_by_name(&app, "z123.b")] {
+        pane = pane.and_then(|p| p.toggle_index(*idx, tree, false));
+    }
+    let mut pane = pane.expect("three marked entries");
+    pane.set_focus(true);
+
+    let mut rebuilt = Tree::new();
+    let (root, a, sub) = {
+        let mut add_node = make_add_node(&mut rebuilt);
+        let root = add_node("", 0, None);
+        let s = add_node(&fixture_str("sample-01"), 0, Some(root));
+        let d = add_node("dir", 0, Some(s));
+        let sub = add_node("sub", 10, Some(d));
+        let a = add_node("a", 20, Some(s));
+        (root, a, sub)
+    };
+    let (pane, num_dropped) = pane.reconcile(&rebuilt, root);
+    let pane = pane.expect("entries which still exist stay marked");
+    assert_eq!(num_dropped, 1, "'z123.b' vanished");
+    assert_eq!(
+        pane.marked()
+            .iter()
+            .map(|(idx, mark)| (*idx, mark.size))
+            .collect::<Vec<_>>(),
+        vec![(sub, 10), (a, 20)],
+        "marks use the new indices and sizes"
+    );
+    let fixture = fixture_str("sample-01");
+    assert_eq!(
+        pane.listed_paths(),
+        vec![
+            PathBuf::from(format!("{}/a", fixture)),
+            PathBuf::from(format!("{}/dir/sub", fixture))
+        ],
+        "entries are still listed in the order they were marked"
+    );
+    assert_eq!(
+        pane.selected(),
+        Some(1),
+        "the selection moves to the last remaining entry"
+    );
+
+    let (pane, num_dropped) = pane.reconcile(&Tree::new(), root);
+    assert!(pane.is_none(), "nothing is left to mark");
+    assert_eq!(num_dropped, 2);
+    Ok(())
+}
+
+#[test]
+fn mark_pane_scrolls_to_keep_the_selection_in_view() -> Result<(), Error> {
+    let (_, app) = initialized_app_and_terminal_from_fixture(&["sample-01"])?;
+    let tree = &app.traversal.tree;
+    let mut pane = tree
+        .node_indices()
+        .filter(|idx| *idx != app.traversal.root_index)
+        .try_fold(MarkPane::default(), |pane, idx| {
+            pane.toggle_index(idx, tree, false)
+        })
+        .expect("marked entries");
+    let num_marked = pane.marked().len();
+    let list_height = 4;
+    assert!(num_marked > list_height, "more entries than fit the pane");
+
+    // a border on each side, and a line of help as the pane has focus
+    let area = Rect::new(0, 0, 40, list_height as u16 + 3);
+    let props = || MarkPaneProps {
+        border_style: Style::default(),
+        format: ByteFormat::Metric,
+        ellipsis: ELLIPSIS,
+        num_paths_to_confirm: NUM_PATHS_TO_CONFIRM,
+    };
+    let render = |pane: &mut MarkPane| pane.render(props(), area, &mut Buffer::empty(area));
+    let press = |pane: MarkPane, key: Key, times: usize| {
+        (0..times).fold(pane, |pane, _| pane.key(key).expect("pane to stay").0)
+    };
+
+    pane.set_focus(true);
+    render(&mut pane);
+    assert_eq!(
+        pane.selected(),
+        Some(num_marked - 1),
+        "focus selects the last entry"
+    );
+    assert_eq!(
+        pane.list_offset(),
+        num_marked - list_height,
+        "which is at the bottom"
+    );
+
+    let mut pane = press(pane, Key::Char('k'), num_marked);
+    render(&mut pane);
+    assert_eq!(
+        (pane.selected(), pane.list_offset()),
+        (Some(0), 0),
+        "moving past the top scrolls up"
+    );
+
+    let mut pane = press(pane, Key::Char('j'), list_height);
+    render(&mut pane);
+    assert_eq!(
+        (pane.selected(), pane.list_offset()),
+        (Some(list_height), 1),
+        "moving past the bottom scrolls down by as much as needed"
+    );
+
+    let mut pane = press(pane, Key::Char('k'), 1);
+    render(&mut pane);
+    assert_eq!(
+        pane.list_offset(),
+        1,
+        "moving within the visible entries doesn't scroll"
+    );
+    Ok(())
+}
+
+#[test]
+fn errors_pane_lists_entries_which_could_not_be_read() -> Result<(), Error> {
+    let (mut terminal, mut app) = initialized_app_and_terminal_from_fixture(&["sample-01"])?;
+    let dir = index_by_name(&app, "dir");
+    app.traversal.tree[dir].metadata_io_error = true;
+    app.traversal.io_errors = 3;
+
+    app.process_events(&mut terminal, b"e".keys())?;
+    assert!(matches!(app.state.focussed, FocussedPane::Errors));
+    let pane = app.window.errors_pane.as_ref().expect("errors pane");
+    assert_eq!(
+        pane.paths,
+        vec![PathBuf::from(format!("{}/dir", fixture_str("sample-01")))],
+        "entries flagged in the tree are listed by path"
+    );
+    assert_eq!(
+        pane.num_unlisted, 2,
+        "errors we can't attribute to a path are counted"
+    );
+
+    app.process_events(&mut terminal, b"?".keys())?;
+    assert!(
+        app.window.errors_pane.is_none() && app.window.help_pane.is_some(),
+        "help and errors share the same space"
+    );
+    app.process_events(&mut terminal, b"eq".keys())?;
+    assert!(app.window.errors_pane.is_none());
+    assert!(matches!(app.state.focussed, FocussedPane::Main));
+    Ok(())
+}
+
+#[test]
+fn sizes_of_directories_which_could_not_be_read_completely_are_marked() -> Result<(), Error> {
+    let (mut terminal, mut app) = initialized_app_and_terminal_from_fixture(&["sample-01"])?;
+    let dir = index_by_name(&app, "dir");
+    app.traversal.tree[dir].incomplete = true;
+    app.process_events(&mut terminal, b"o".keys())?;
+
+    let area = Rect::new(0, 0, 60, 10);
+    let mut buf = Buffer::empty(area);
+    let props = EntriesProps {
+        tree: &app.traversal.tree,
+        root: app.state.root,
+        display: app.display,
+        selected: None,
+        entries: &app.state.entries,
+        marked: None,
+        border_style: Style::default(),
+        is_focussed: false,
+    };
+    Entries::default().render(props, area, &mut buf);
+
+    let rows: Vec<String> = (1..area.height - 1)
+        .map(|y| {
+            (1..area.width - 1)
+                .map(|x| buf.get(x, y).symbol.as_str())
+                .collect()
+        })
+        .collect();
+    let row_of = |name: &str| {
+        rows.iter()
+            .find(|row| row.contains(name))
+            .expect("entry to be visible")
+    };
+    assert!(
+        row_of("/dir").contains(&format!("{}1.26 MB", INCOMPLETE_MARKER)),
+        "{}",
+        row_of("/dir")
+    );
+    assert!(
+        !row_of(" a ").contains(INCOMPLETE_MARKER),
+        "complete entries are not marked"
+    );
+    Ok(())
+}
+
+#[test]
+fn bound_keys_trigger_the_action_of_their_default_key() -> Result<(), Error> {
+    let (mut terminal, mut app) = initialized_app_and_terminal_from_fixture(&["sample-01"])?;
+    app.display.key_bindings = &[(Key::Char('n'), Key::Char('j'))];
+    app.process_events(&mut terminal, b"o".keys())?;
+    let first = app.state.selected;
+
+    app.process_events(&mut terminal, b"n".keys())?;
+    assert_eq!(
+        app.state.selected,
+        app.state.entries.get(1).map(|e| e.index),
+        "'n' moves down like 'j'"
+    );
+    app.process_events(&mut terminal, b"k".keys())?;
+    assert_eq!(app.state.selected, first, "unbound keys work as usual");
+    Ok(())
+}
+
+#[test]
+fn sizes_of_entries_larger_than_the_threshold_are_highlighted() -> Result<(), Error> {
+    let (mut terminal, mut app) = initialized_app_and_terminal_from_fixture(&["sample-01"])?;
+    app.process_events(&mut terminal, b"o".keys())?;
+    app.display.large_entry_size = Some(1000);
+
+    let area = Rect::new(0, 0, 60, 10);
+    let mut buf = Buffer::empty(area);
+    let props = EntriesProps {
+        tree: &app.traversal.tree,
+        root: app.state.root,
+        display: app.display,
+        selected: None,
+        entries: &app.state.entries,
+        marked: None,
+        border_style: Style::default(),
+        is_focussed: false,
+    };
+    Entries::default().render(props, area, &mut buf);
+
+    let row_of = |name: &str| {
+        (1..area.height - 1)
+            .find(|&y| {
+                (1..area.width - 1)
+                    .map(|x| buf.get(x, y).symbol.as_str())
+                    .collect::<String>()
+                    .contains(name)
+            })
+            .expect("entry to be visible")
+    };
+    let size_color = |name| buf.get(area.width / 8, row_of(name)).style.fg;
+    assert_eq!(
+        size_color("dir"),
+        COLOR_LARGE,
+        "1.26 MB is larger than 1 KB"
+    );
+    assert_ne!(size_color(" a"), COLOR_LARGE, "256 B is not");
+    Ok(())
+}
+
+#[test]
+fn a_shell_is_only_opened_in_directories_and_key_reading_resumes_afterwards() -> Result<(), Error> {
+    let (mut terminal, mut app) = initialized_app_and_terminal_from_fixture(&["sample-01"])?;
+    let (resume_send, resume_receive) = std::sync::mpsc::channel();
+    app.resume_keys = Some(resume_send);
+    app.process_events(&mut terminal, b"oj".keys())?;
+    assert!(
+        resume_receive.try_recv().is_err(),
+        "only the shell key pauses reading"
+    );
+
+    app.process_events(&mut terminal, b"b".keys())?;
+    assert_eq!(
+        app.state.message.as_deref(),
+        Some("Select a directory to open a shell in"),
+        "the second entry is a file"
+    );
+    assert!(resume_receive.try_recv().is_ok());
+
+    app.process_events(&mut terminal, b"k q".keys())?;
+    assert!(app.state.is_confirming_quit);
+    app.process_events(&mut terminal, b"b".keys())?;
+    assert!(
+        !app.state.is_confirming_quit,
+        "the shell key cancels quitting"
+    );
+    assert!(
+        resume_receive.try_recv().is_ok(),
+        "key reading resumes even if the shell key answered the prompt"
+    );
+    Ok(())
+}
+
+#[test]
+fn typing_the_beginning_of_a_name_selects_the_first_entry_starting_with_it() -> Result<(), Error> {
+    let (mut terminal, mut app) = initialized_app_and_terminal_from_fixture(&["sample-01"])?;
+    app.process_events(&mut terminal, b"o".keys())?;
+    let name_of_selected = |app: &crate::interactive::TerminalApp| {
+        app.state
+            .entries
+            .iter()
+            .find(|e| Some(e.index) == app.state.selected)
+            .map(|e| e.data.name.to_string_lossy().into_owned())
+    };
+
+    let pause_typing = |app: &mut crate::interactive::TerminalApp| {
+        let (name, _) = app.state.type_ahead.take().expect("typing ahead");
+        app.state.type_ahead = Some((name, Instant::now() - TYPE_AHEAD_TIMEOUT));
+    };
+
+    app.process_events(&mut terminal, b"c.".keys())?;
+    assert_eq!(name_of_selected(&app).as_deref(), Some("c.lnk"));
+    assert_eq!(app.state.message.as_deref(), Some("jump to: c."));
+
+    app.process_events(&mut terminal, b"x".keys())?;
+    assert_eq!(name_of_selected(&app).as_deref(), Some("c.lnk"));
+    assert_eq!(
+        app.state.message.as_deref(),
+        Some("no entry starts with 'c.x'")
+    );
+
+    app.process_events(&mut terminal, b"j".keys())?;
+    assert_ne!(
+        name_of_selected(&app).as_deref(),
+        Some("c.lnk"),
+        "keys with actions trigger them while typing, like 'j'"
+    );
+    assert!(app.state.type_ahead.is_none(), "and end the name");
+
+    app.process_events(&mut terminal, b"Z".keys())?;
+    assert_eq!(
+        name_of_selected(&app).as_deref(),
+        Some("z123.b"),
+        "a new name is started and case is ignored"
+    );
+
+    pause_typing(&mut app);
+    app.process_events(&mut terminal, b"k".keys())?;
+    assert_ne!(
+        name_of_selected(&app).as_deref(),
+        Some("z123.b"),
+        "after a pause, keys trigger their action again"
+    );
+    Ok(())
+}
+
+#[test]
+fn units_toggle_between_metric_and_binary_without_walking_again() -> Result<(), Error> {
+    let (mut terminal, mut app) = initialized_app_and_terminal_from_fixture(&["sample-01"])?;
+    let entries_traversed = app.traversal.entries_traversed;
+    assert_eq!(app.display.byte_format, ByteFormat::Metric);
+
+    app.process_events(&mut terminal, b"m".keys())?;
+    assert_eq!(app.display.byte_format, ByteFormat::Binary);
+    app.process_events(&mut terminal, b"m".keys())?;
+    assert_eq!(app.display.byte_format, ByteFormat::Metric);
+    assert_eq!(app.traversal.entries_traversed, entries_traversed);
+
+    app.display.byte_format = ByteFormat::MB;
+    app.process_events(&mut terminal, b"m".keys())?;
+    assert_eq!(
+        app.display.byte_format,
+        ByteFormat::MiB,
+        "fixed units keep their magnitude"
+    );
+    Ok(())
+}
+
+#[test]
+fn all_entries_are_marked_at_once_unless_all_of_them_are_marked_already() -> Result<(), Error> {
+    let (mut terminal, mut app) = initialized_app_and_terminal_from_fixture(&["sample-01"])?;
+    let num_marked = |app: &crate::interactive::TerminalApp| {
+        app.window.mark_pane.as_ref().map(|p| p.marked().len())
+    };
+    app.process_events(&mut terminal, b"o ".keys())?;
+    assert_eq!(num_marked(&app), Some(1));
+
+    app.process_events(&mut terminal, b"\x01".keys())?;
+    assert_eq!(
+        num_marked(&app),
+        Some(app.state.entries.len()),
+        "the already marked entry stays marked"
+    );
+
+    app.process_events(&mut terminal, b"\x01".keys())?;
+    assert_eq!(num_marked(&app), None, "all entries are unmarked");
+    Ok(())
+}
+
+#[test]
+fn the_free_space_is_determined_again_once_the_root_changes() -> Result<(), Error> {
+    let (mut terminal, mut app) = initialized_app_and_terminal_from_fixture(&["sample-01"])?;
+    let top_root = app.state.root;
+    app.process_events(&mut terminal, b"".keys())?;
+    assert_eq!(
+        app.state.free_space.map(|(root, _)| root),
+        Some(top_root),
+        "it's determined when drawing"
+    );
+
+    app.process_events(&mut terminal, b"o".keys())?;
+    assert_ne!(app.state.root, top_root);
+    // drawing doesn't wait for 'df' for long, so later draws pick up the space once it's known
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while app.state.pending_free_space.is_some() && Instant::now() < deadline {
+        app.draw(&mut terminal)?;
+    }
+    let (root, space) = app.state.free_space.expect("free space of the new root");
+    assert_eq!(root, app.state.root);
+    if cfg!(all(unix, feature = "free-space")) {
+        let space = space.expect("'df' to know the filesystem of the fixture");
+        assert!(space.free <= space.total);
+    }
+    Ok(())
+}
+
+#[test]
+fn the_own_size_of_directories_is_shown_next_to_their_size_on_request() -> Result<(), Error> {
+    let (mut terminal, mut app) = initialized_app_and_terminal_from_fixture(&["sample-01"])?;
+    app.process_events(&mut terminal, b"oS".keys())?;
+    assert!(app.display.show_own_size);
+    assert_eq!(
+        node_by_name(&app, "z123.b").own_size,
+        0,
+        "files have no own size"
+    );
+
+    let area = Rect::new(0, 0, 80, 10);
+    let mut buf = Buffer::empty(area);
+    let props = EntriesProps {
+        tree: &app.traversal.tree,
+        root: app.state.root,
+        display: app.display,
+        selected: None,
+        entries: &app.state.entries,
+        marked: None,
+        border_style: Style::default(),
+        is_focussed: false,
+    };
+    Entries::default().render(props, area, &mut buf);
+    let line_of = |name: &str| {
+        (1..area.height - 1)
+            .map(|y| {
+                (1..area.width - 1)
+                    .map(|x| buf.get(x, y).symbol.as_str())
+                    .collect::<String>()
+            })
+            .find(|line| line.contains(name))
+            .expect("entry to be visible")
+    };
+    assert!(line_of("/dir").contains(" self "));
+    assert!(!line_of("z123.b").contains(" self "));
+
+    app.process_events(&mut terminal, b"S".keys())?;
+    assert!(!app.display.show_own_size);
+    Ok(())
+}
+
+#[test]
+fn quitting_with_marked_entries_asks_for_confirmation() -> Result<(), Error> {
+    let (mut terminal, mut app) = initialized_app_and_terminal_from_fixture(&["sample-01"])?;
+    app.process_events(&mut terminal, b"o q".keys())?;
+    assert!(app.state.is_confirming_quit);
+    assert_eq!(
+        app.state.message.as_deref(),
+        Some("1 marked entry would be lost, press 'y' or 'q' to quit anyway, or any other key to continue")
+    );
+
+    let selected = app.state.selected;
+    app.process_events(&mut terminal, b"nj".keys())?;
+    assert!(!app.state.is_confirming_quit && app.state.message.is_none());
+    assert_ne!(
+        app.state.selected, selected,
+        "keys after the one cancelling are handled as usual"
+    );
+
+    let selected = app.state.selected;
+    app.process_events(&mut terminal, b"qyj".keys())?;
+    assert_eq!(app.state.selected, selected, "'y' quits right away");
+
+    app.display.confirm_quit_with_marks = false;
+    app.process_events(&mut terminal, b"qj".keys())?;
+    assert_eq!(
+        app.state.selected, selected,
+        "without confirmation, 'q' quits right away"
+    );
+    Ok(())
+}
+
+#[test]
+fn split_entries_are_navigated_independently_and_marked_together() -> Result<(), Error> {
+    let (mut terminal, mut app) = initialized_app_and_terminal_from_fixture(&["sample-01"])?;
+    app.process_events(&mut terminal, b"o".keys())?;
+    let top = app.state.root;
+    app.process_events(&mut terminal, b"v".keys())?;
+    let split = app.state.split.as_ref().expect("split entries");
+    assert_eq!(
+        (split.root, split.is_right),
+        (top, true),
+        "the second list starts at the same directory, on the right"
+    );
+
+    let dir = index_by_name(&app, "dir");
+    app.state.selected = Some(dir);
+    app.process_events(&mut terminal, b"o ".keys())?;
+    assert_eq!(app.state.root, dir);
+    assert_eq!(
+        app.state.split.as_ref().map(|split| split.root),
+        Some(top),
+        "the second list stays where it was"
+    );
+
+    app.process_events(&mut terminal, b"w".keys())?;
+    assert_eq!(app.state.root, top, "keys act on the list on the right now");
+    let split = app.state.split.as_ref().expect("split entries");
+    assert_eq!((split.root, split.is_right), (dir, false));
+    app.state.selected = Some(index_by_name(&app, "a"));
+    app.process_events(&mut terminal, b" ".keys())?;
+    assert_eq!(
+        app.window
+            .mark_pane
+            .as_ref()
+            .map(|pane| pane.marked().len()),
+        Some(2),
+        "entries are marked in both lists"
+    );
+
+    app.process_events(&mut terminal, b"v".keys())?;
+    assert!(app.state.split.is_none());
+    assert_eq!(app.state.root, top, "the list the keys act on remains");
+    Ok(())
+}
+
+#[test]
+fn sizes_below_the_current_directory_are_recomputed_in_memory() -> Result<(), Error> {
+    let (mut terminal, mut app) = initialized_app_and_terminal_from_fixture(&["sample-01"])?;
+    app.process_events(&mut terminal, b"o".keys())?;
+    let (top, dir) = (app.state.root, index_by_name(&app, "dir"));
+    let size_of = |app: &crate::interactive::TerminalApp, index| {
+        app.traversal.tree.node_weight(index).map(|w| w.size)
+    };
+    let expected = (
+        size_of(&app, top),
+        size_of(&app, dir),
+        app.traversal.total_bytes,
+    );
+
+    for index in &[top, dir, app.traversal.root_index] {
+        app.traversal
+            .tree
+            .node_weight_mut(*index)
+            .expect("valid index")
+            .size += 1000;
+    }
+    app.traversal.total_bytes = Some(0);
+    app.process_events(&mut terminal, b"R".keys())?;
+    assert_eq!(
+        (
+            size_of(&app, top),
+            size_of(&app, dir),
+            app.traversal.total_bytes
+        ),
+        expected,
+        "directories below and above are summed up again"
+    );
+    assert_eq!(
+        app.state.message.as_deref(),
+        Some("Recomputed the sizes of 4 directories")
+    );
+    Ok(())
+}
+
+#[test]
+fn the_legend_explains_only_what_is_in_use() -> Result<(), Error> {
+    let (mut terminal, mut app) = initialized_app_and_terminal_from_fixture(&["sample-01"])?;
+    app.process_events(&mut terminal, b"L".keys())?;
+    assert!(app.display.show_legend);
+
+    let meanings = |props: &LegendProps| -> Vec<String> {
+        Legend::items(props)
+            .into_iter()
+            .map(|(_, _, meaning)| meaning)
+            .collect()
+    };
+    let props = LegendProps {
+        format: ByteFormat::Bytes,
+        large_entry_size: None,
+        show_own_size: false,
+        has_marked_entries: false,
+        has_incomplete_entries: false,
+        has_missing_entries: false,
+        is_split: false,
+    };
+    assert_eq!(meanings(&props), vec!["directory", "file"]);
+    assert_eq!(
+        meanings(&LegendProps {
+            large_entry_size: Some(1000),
+            has_marked_entries: true,
+            has_incomplete_entries: true,
+            ..props
+        }),
+        vec![
+            "directory",
+            "file",
+            "marked",
+            "larger than 1000 b",
+            "not all entries could be read"
+        ]
+    );
+
+    app.process_events(&mut terminal, b"L".keys())?;
+    assert!(!app.display.show_legend, "it's toggled by the same key");
+    Ok(())
+}
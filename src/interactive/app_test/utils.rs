@@ -2,12 +2,13 @@ use crate::interactive::app_test::FIXTURE_PATH;
 use crate::interactive::TerminalApp;
 use dua::{
     traverse::{EntryData, Tree, TreeIndex},
-    ByteFormat, Color, TraversalSorting, WalkOptions,
+    ByteFormat, Color, ColumnOrder, ErrorAnnotation, OutputFormat, OutputOrder, Symlinks,
+    TimeStyle, WalkOptions,
 };
 use failure::{Error, ResultExt};
 use itertools::Itertools;
 use jwalk::{DirEntry, WalkDir};
-use petgraph::prelude::NodeIndex;
+use petgraph::{prelude::NodeIndex, Direction};
 use std::{
     env::temp_dir,
     ffi::OsStr,
@@ -26,7 +27,7 @@ pub fn node_by_index(app: &TerminalApp, id: TreeIndex) -> &EntryData {
 }
 
 pub fn node_by_name(app: &TerminalApp, name: impl AsRef<OsStr>) -> &EntryData {
-    node_by_index(app, index_by_name(&app, name))
+    node_by_index(app, index_by_name(app, name))
 }
 
 pub fn index_by_name_and_size(
@@ -125,7 +126,7 @@ fn copy_recursive(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> Result<(), Er
 
 impl From<&'static str> for WritableFixture {
     fn from(fixture_name: &str) -> Self {
-        const TEMP_TLD_DIRNAME: &'static str = "dua-unit";
+        const TEMP_TLD_DIRNAME: &str = "dua-unit";
 
         let src = fixture(fixture_name);
         let dst = temp_dir().join(TEMP_TLD_DIRNAME);
@@ -159,18 +160,45 @@ pub fn initialized_app_and_terminal_with_closure<P: AsRef<Path>>(
     std::env::set_current_dir(Path::new(env!("CARGO_MANIFEST_DIR")))?;
 
     let input = fixture_paths.iter().map(|c| convert(c.as_ref())).collect();
+    let (_, keys) = std::sync::mpsc::channel();
+    let options = WalkOptions {
+        threads: 1,
+        byte_format: ByteFormat::Metric,
+        apparent_size: true,
+        both_sizes: false,
+        shared_unit: false,
+        show_entry_count: false,
+        estimate_compression: false,
+        verbose_size: false,
+        thousands_separator: None,
+        column_order: ColumnOrder::SizePath,
+        error_annotation: ErrorAnnotation::Human,
+        output_format: OutputFormat::Text,
+        time_style: TimeStyle::Iso,
+        count_hard_links: false,
+        color: Color::None,
+        output_order: OutputOrder::Unsorted,
+        exclude: Vec::new(),
+        exclude_caches: false,
+        exclude_larger_than: None,
+        exclude_smaller_than: None,
+        labels: Vec::new(),
+        relative_to: None,
+        heatmap: None,
+        symlinks: Symlinks::default(),
+        ls_colors: None,
+        max_time: None,
+        fatal_errors: Vec::new(),
+    };
     let app = TerminalApp::initialize(
         &mut terminal,
-        WalkOptions {
-            threads: 1,
-            byte_format: ByteFormat::Metric,
-            apparent_size: true,
-            count_hard_links: false,
-            color: Color::None,
-            sorting: TraversalSorting::AlphabeticalByFileName,
-        },
+        options.clone(),
+        options.into(),
         input,
-    )?;
+        &keys,
+        None,
+    )?
+    .expect("the traversal not to be aborted without any keys");
     Ok((terminal, app))
 }
 
@@ -264,15 +292,38 @@ pub fn make_add_node<'a>(
         let n = t.add_node(EntryData {
             name: OsString::from(name),
             size,
+            own_size: 0,
+            apparent_size: if maybe_from_idx.is_some() { size } else { 0 },
+            is_dir: false,
             metadata_io_error: false,
+            incomplete: false,
         });
         if let Some(from) = maybe_from_idx {
             t.add_edge(from, n, ());
+            // nodes with entries are directories, except for the root all input paths are attached to
+            if t.neighbors_directed(from, Direction::Incoming)
+                .next()
+                .is_some()
+            {
+                t[from].is_dir = true;
+                t[from].apparent_size = 0;
+            }
         }
         n
     }
 }
 
+/// `tree` with the own size of all directories set to 0, as it depends on the filesystem.
+pub fn without_own_sizes(mut tree: Tree) -> Tree {
+    for index in tree.node_indices().collect::<Vec<_>>() {
+        tree[index].own_size = 0;
+        if tree[index].is_dir {
+            tree[index].apparent_size = 0;
+        }
+    }
+    tree
+}
+
 pub fn debug(item: impl fmt::Debug) -> String {
     format!("{:?}", item)
 }
@@ -8,7 +8,7 @@ mod utils {
         get_entry_or_panic,
         traverse::{Tree, TreeIndex},
     };
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
 
     pub fn path_of(tree: &Tree, mut node_idx: TreeIndex) -> PathBuf {
         const THE_ROOT: usize = 1;
@@ -28,6 +28,21 @@ mod utils {
                 acc
             })
     }
+
+    /// The index of the entry below `root` whose path is `path` as returned by `path_of()`, if there is one.
+    pub fn index_of_path(tree: &Tree, root: TreeIndex, path: &Path) -> Option<TreeIndex> {
+        let mut node_idx = root;
+        let mut node_path = PathBuf::new();
+        while node_path != path {
+            let (child_idx, child_path) = tree
+                .neighbors_directed(node_idx, petgraph::Outgoing)
+                .map(|idx| (idx, node_path.join(&get_entry_or_panic(tree, idx).name)))
+                .find(|(_, child_path)| path.starts_with(child_path))?;
+            node_idx = child_idx;
+            node_path = child_path;
+        }
+        Some(node_idx)
+    }
 }
 pub use utils::*;
 
@@ -1,6 +1,6 @@
 use crate::interactive::{
     path_of,
-    widgets::{entry_color, EntryMarkMap},
+    widgets::{entry_color, EntryMarkMap, COLOR_LARGE},
     DisplayOptions, EntryDataBundle,
 };
 use dua::traverse::{Tree, TreeIndex};
@@ -14,6 +14,9 @@ use tui::{
 };
 use tui_react::{fill_background_to_right, List, ListProps};
 
+/// Written in front of the size of directories whose entries couldn't all be read, as their size is only a lower bound
+pub const INCOMPLETE_MARKER: &str = "≥";
+
 pub struct EntriesProps<'a> {
     pub tree: &'a Tree,
     pub root: TreeIndex,
@@ -111,12 +114,41 @@ impl Entries {
                 let bytes = Text::Styled(
                     format!(
                         "{:>byte_column_width$}",
-                        display.byte_format.display(w.size).to_string(), // we would have to impl alignment/padding ourselves otherwise...
+                        format!(
+                            "{}{}",
+                            // entries below couldn't all be read, so there may be more
+                            if w.incomplete { INCOMPLETE_MARKER } else { "" },
+                            display.byte_format.display(w.size)
+                        ),
                         byte_column_width = display.byte_format.width()
                     )
                     .into(),
+                    if display.large_entry_size.is_some_and(|size| w.size > size) {
+                        Style {
+                            fg: COLOR_LARGE,
+                            modifier: style.modifier | Modifier::BOLD,
+                            ..style
+                        }
+                    } else {
+                        Style {
+                            fg: Color::Green,
+                            ..style
+                        }
+                    },
+                );
+                let own_size = Text::Styled(
+                    match (display.show_own_size, is_dir) {
+                        (false, _) => String::new(),
+                        (true, true) => format!(
+                            " self {:>byte_column_width$}",
+                            display.byte_format.display(w.own_size).to_string(),
+                            byte_column_width = display.byte_format.width()
+                        ),
+                        (true, false) => " ".repeat(" self ".len() + display.byte_format.width()),
+                    }
+                    .into(),
                     Style {
-                        fg: Color::Green,
+                        fg: Color::DarkGray,
                         ..style
                     },
                 );
@@ -150,7 +182,7 @@ impl Entries {
                         Style { fg, ..style }
                     },
                 );
-                vec![bytes, percentage, name]
+                vec![bytes, own_size, percentage, name]
             },
         );
 
@@ -0,0 +1,82 @@
+use super::help::margin;
+use crate::interactive::CursorDirection;
+use std::{borrow::Borrow, path::PathBuf};
+use termion::{event::Key, event::Key::*};
+use tui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    widgets::{Block, Borders, Paragraph, Text, Widget},
+};
+
+/// A scrollable list of the entries which could not be read while scanning.
+#[derive(Default, Clone)]
+pub struct ErrorsPane {
+    pub scroll: u16,
+    /// The paths of all entries whose metadata could not be obtained
+    pub paths: Vec<PathBuf>,
+    /// The amount of errors we can't attribute to a path, as the walk only tells us that reading a directory failed
+    pub num_unlisted: u64,
+}
+
+pub struct ErrorsPaneProps {
+    pub border_style: Style,
+}
+
+impl ErrorsPane {
+    pub fn key(&mut self, key: Key) {
+        match key {
+            Ctrl('u') | PageUp => self.scroll_errors(CursorDirection::PageUp),
+            Char('k') | Up => self.scroll_errors(CursorDirection::Up),
+            Char('j') | Down => self.scroll_errors(CursorDirection::Down),
+            Ctrl('d') | PageDown => self.scroll_errors(CursorDirection::PageDown),
+            _ => {}
+        };
+    }
+    fn scroll_errors(&mut self, direction: CursorDirection) {
+        self.scroll = direction.move_cursor(self.scroll as usize) as u16;
+    }
+
+    pub fn render(&mut self, props: impl Borrow<ErrorsPaneProps>, area: Rect, buf: &mut Buffer) {
+        let mut lines: Vec<_> = self
+            .paths
+            .iter()
+            .map(|p| Text::Raw(format!("{}\n", p.display()).into()))
+            .collect();
+        if self.num_unlisted > 0 {
+            lines.push(Text::Styled(
+                format!(
+                    "{} more {} could not be read\n",
+                    self.num_unlisted,
+                    if self.num_unlisted == 1 {
+                        "entry"
+                    } else {
+                        "entries"
+                    }
+                )
+                .into(),
+                Style {
+                    fg: Color::DarkGray,
+                    ..Default::default()
+                },
+            ));
+        }
+        if lines.is_empty() {
+            lines.push(Text::Raw("No errors were encountered\n".into()));
+        }
+        let num_lines = lines.len() as u16;
+
+        let ErrorsPaneProps { border_style } = props.borrow();
+        let mut block = Block::default()
+            .title("Errors")
+            .border_style(*border_style)
+            .borders(Borders::ALL);
+        block.draw(area, buf);
+
+        let area = margin(block.inner(area), 1);
+        self.scroll = self.scroll.min(num_lines.saturating_sub(area.height));
+        Paragraph::new(lines.iter())
+            .scroll(self.scroll)
+            .draw(area, buf);
+    }
+}
@@ -13,6 +13,7 @@ pub struct Footer;
 pub struct FooterProps {
     pub total_bytes: Option<u64>,
     pub entries_traversed: u64,
+    pub io_errors: u64,
     pub format: ByteFormat,
     pub message: Option<String>,
 }
@@ -22,6 +23,7 @@ impl Footer {
         let FooterProps {
             total_bytes,
             entries_traversed,
+            io_errors,
             format,
             message,
         } = props.borrow();
@@ -29,12 +31,16 @@ impl Footer {
         let lines = [
             Text::Raw(
                 format!(
-                    " Total disk usage: {}  Entries: {}   ",
+                    " Total disk usage: {}  Entries: {}{}   ",
                     match total_bytes {
                         Some(b) => format!("{}", format.display(*b)).to_owned(),
                         None => "-".to_owned(),
                     },
                     entries_traversed,
+                    match io_errors {
+                        0 => String::new(),
+                        n => format!("  Errors: {} (e to list)", n),
+                    },
                 )
                 .into(),
             )
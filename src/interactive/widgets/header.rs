@@ -1,3 +1,5 @@
+use crate::interactive::FilesystemSpace;
+use dua::ByteFormat;
 use tui::{
     buffer::Buffer,
     layout::Rect,
@@ -8,7 +10,14 @@ use tui::{
 pub struct Header;
 
 impl Header {
-    pub fn render(&self, bg_color: Color, area: Rect, buf: &mut Buffer) {
+    pub fn render(
+        &self,
+        bg_color: Color,
+        byte_format: ByteFormat,
+        free_space: Option<FilesystemSpace>,
+        area: Rect,
+        buf: &mut Buffer,
+    ) {
         let standard = Style {
             fg: Color::Black,
             bg: bg_color,
@@ -28,7 +37,7 @@ impl Header {
         let italic = |text: &'static str| modified(text, Modifier::UNDERLINED);
         let text = |text: &'static str| Text::Styled(text.into(), standard);
 
-        let lines = [
+        let mut lines = vec![
             bold(" D"),
             text("isk "),
             bold("U"),
@@ -40,7 +49,22 @@ impl Header {
             italic("(press "),
             modified("?", Modifier::BOLD | Modifier::UNDERLINED),
             italic(" for help)"),
+            text("    units: "),
+            bold(unit_system(byte_format)),
         ];
+        if let Some(space) = free_space {
+            lines.push(text("    "));
+            lines.push(Text::Styled(
+                format!(
+                    "{} free, {} used of {}",
+                    byte_format.display(space.free),
+                    byte_format.display(space.used),
+                    byte_format.display(space.total)
+                )
+                .into(),
+                standard,
+            ));
+        }
         Paragraph::new(lines.iter())
             .style(Style {
                 bg: bg_color,
@@ -49,3 +73,13 @@ impl Header {
             .draw(area, buf);
     }
 }
+
+/// The name of the unit system `format` belongs to
+fn unit_system(format: ByteFormat) -> &'static str {
+    use ByteFormat::*;
+    match format {
+        Metric | GB | MB => "metric",
+        Binary | GiB | MiB => "binary",
+        Bytes => "bytes",
+    }
+}
@@ -21,7 +21,7 @@ pub struct HelpPaneProps {
     pub border_style: Style,
 }
 
-fn margin(r: Rect, margin: u16) -> Rect {
+pub(super) fn margin(r: Rect, margin: u16) -> Rect {
     Rect {
         x: r.x + margin,
         y: r.y + margin,
@@ -103,10 +103,15 @@ impl HelpPane {
                 hotkey(
                     "q/<ESC>",
                     "Close the current pane. Closes the program if no",
-                    Some("pane is open"),
+                    Some("pane is open, asking first if entries are marked"),
                 );
                 hotkey("<tab>", "Cycle between all open panes", None);
                 hotkey("?", "Show or hide the help pane", None);
+                hotkey(
+                    "e",
+                    "Show or hide the entries which could not be read",
+                    Some("in place of the help pane"),
+                );
                 spacer();
             }
             title("Keys for Navigation");
@@ -125,6 +130,16 @@ impl HelpPane {
                 hotkey("<Page Down>", "^", None);
                 hotkey("Ctrl + u", "move up 10 entries at once", None);
                 hotkey("<Page Up>", "^", None);
+                hotkey(
+                    "<count>",
+                    "digits typed before a move repeat it, e.g. 5j moves down 5 entries",
+                    None,
+                );
+                hotkey(
+                    "<name>",
+                    "typing the beginning of a name jumps to the first entry starting with it",
+                    Some("Keys without an action start a name and extend it within a second, others end it"),
+                );
                 spacer();
             }
             title("Keys for display");
@@ -135,6 +150,32 @@ impl HelpPane {
                     "cycle through percentage display and bar options",
                     None,
                 );
+                hotkey(
+                    "m",
+                    "toggle between metric and binary units",
+                    Some("The units in use are shown at the top"),
+                );
+                hotkey(
+                    "Shift + s",
+                    "show or hide the own size of directories",
+                    Some("It's the space their list of entries takes, which isn't part of their size"),
+                );
+                hotkey(
+                    "v",
+                    "show or hide a second list of entries next to the first",
+                    Some("Compares two directories side by side, each with its own selection"),
+                );
+                hotkey("w", "let keys act on the other list of entries", None);
+                hotkey(
+                    "Shift + l",
+                    "show or hide a legend of the colors and symbols in use",
+                    None,
+                );
+                hotkey(
+                    "Shift + r",
+                    "recompute the sizes of directories below the current one",
+                    Some("Sums up the sizes in memory without reading the filesystem again"),
+                );
                 spacer();
             }
             title("Keys for entry operations");
@@ -144,20 +185,40 @@ impl HelpPane {
                     "Open the entry with the associated program",
                     None,
                 );
+                hotkey(
+                    "b",
+                    "Open a shell in the selected directory",
+                    Some("Exit the shell to continue"),
+                );
                 hotkey(
                     "d",
                     "Toggle the currently selected entry and move down",
                     None,
                 );
                 hotkey("<space bar>", "Toggle the currently selected entry", None);
+                hotkey(
+                    "Ctrl + a",
+                    "Mark all entries of the current directory",
+                    Some("Unmarks them all if all of them are marked already"),
+                );
                 spacer();
             }
             title("Keys in the Mark pane");
             {
                 hotkey("d/<space>", "remove the selected entry from the list", None);
+                hotkey(
+                    "s",
+                    "toggle sort by size descending",
+                    Some("Lists entries in the order they were marked when pressed again"),
+                );
+                hotkey(
+                    "n",
+                    "toggle sort by path",
+                    Some("Lists entries in the order they were marked when pressed again"),
+                );
                 hotkey(
                     "Ctrl + r",
-                    "Permanently delete all marked entries without prompt!",
+                    "Permanently delete all marked entries after confirming with y",
                     Some("This operation cannot be undone!"),
                 );
                 spacer();
@@ -0,0 +1,98 @@
+use crate::interactive::widgets::{COLOR_LARGE, COLOR_MARKED, INCOMPLETE_MARKER};
+use crate::ByteFormat;
+use std::borrow::Borrow;
+use tui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Paragraph, Text, Widget},
+};
+
+/// A line explaining the colors and glyphs which can currently be seen
+pub struct Legend;
+
+pub struct LegendProps {
+    pub format: ByteFormat,
+    pub large_entry_size: Option<u64>,
+    pub show_own_size: bool,
+    pub has_marked_entries: bool,
+    pub has_incomplete_entries: bool,
+    pub has_missing_entries: bool,
+    pub is_split: bool,
+}
+
+impl Legend {
+    /// The pairs of a styled sample and its meaning, only for what is in use right now.
+    pub fn items(props: &LegendProps) -> Vec<(String, Style, String)> {
+        let LegendProps {
+            format,
+            large_entry_size,
+            show_own_size,
+            has_marked_entries,
+            has_incomplete_entries,
+            has_missing_entries,
+            is_split,
+        } = props;
+        let fg = |fg| Style {
+            fg,
+            ..Default::default()
+        };
+        let mut items = vec![
+            ("/name".to_owned(), Style::default(), "directory".to_owned()),
+            ("name".to_owned(), fg(Color::DarkGray), "file".to_owned()),
+        ];
+        if *has_marked_entries {
+            items.push(("name".into(), fg(COLOR_MARKED), "marked".into()));
+        }
+        if *has_missing_entries {
+            items.push(("name".into(), fg(Color::Red), "deleted".into()));
+        }
+        if let Some(size) = large_entry_size {
+            items.push((
+                "size".into(),
+                Style {
+                    fg: COLOR_LARGE,
+                    modifier: Modifier::BOLD,
+                    ..Default::default()
+                },
+                format!("larger than {}", format.display(*size)),
+            ));
+        }
+        if *has_incomplete_entries {
+            items.push((
+                INCOMPLETE_MARKER.into(),
+                fg(Color::Green),
+                "not all entries could be read".into(),
+            ));
+        }
+        if *show_own_size {
+            items.push((
+                "self".into(),
+                fg(Color::DarkGray),
+                "size of the directory itself".into(),
+            ));
+        }
+        if *is_split {
+            items.push((
+                "border".into(),
+                fg(Color::DarkGray),
+                "list keys don't act on".into(),
+            ));
+        }
+        items
+    }
+
+    pub fn render(&self, props: impl Borrow<LegendProps>, area: Rect, buf: &mut Buffer) {
+        let texts: Vec<_> = Self::items(props.borrow())
+            .into_iter()
+            .flat_map(|(sample, style, meaning)| {
+                vec![
+                    Text::Raw(" ".into()),
+                    Text::Styled(sample.into(), style),
+                    Text::Raw(format!(" {} ", meaning).into()),
+                ]
+            })
+            .collect();
+        Paragraph::new(texts.iter()).draw(area, buf);
+    }
+}
@@ -1,7 +1,7 @@
 use crate::interactive::{
     widgets::{
-        Entries, EntriesProps, Footer, FooterProps, Header, HelpPane, HelpPaneProps, MarkPane,
-        MarkPaneProps, COLOR_MARKED,
+        Entries, EntriesProps, ErrorsPane, ErrorsPaneProps, Footer, FooterProps, Header, HelpPane,
+        HelpPaneProps, Legend, LegendProps, MarkPane, MarkPaneProps, COLOR_MARKED,
     },
     AppState, DisplayOptions, FocussedPane,
 };
@@ -22,10 +22,20 @@ pub struct MainWindowProps<'a> {
     pub state: &'a AppState,
 }
 
+/// The pane shown at the top right, next to the entries
+enum InfoPane<'a> {
+    Help(&'a mut HelpPane),
+    Errors(&'a mut ErrorsPane),
+}
+
 #[derive(Default)]
 pub struct MainWindow {
     pub help_pane: Option<HelpPane>,
+    /// Shown in place of the help pane, hence only one of them is set at a time
+    pub errors_pane: Option<ErrorsPane>,
     pub entries_pane: Entries,
+    /// Shows the second list of entries on the right if the entries are split
+    pub split_entries_pane: Entries,
     pub mark_pane: Option<MarkPane>,
 }
 
@@ -42,13 +52,14 @@ impl MainWindow {
                     tree,
                     entries_traversed,
                     total_bytes,
+                    io_errors,
                     ..
                 },
             display,
             state,
         } = props.borrow();
 
-        let (entries_style, help_style, mark_style) = {
+        let (entries_style, help_style, mark_style, unfocussed_style) = {
             let grey = Style {
                 fg: Color::DarkGray,
                 bg: Color::Reset,
@@ -59,46 +70,63 @@ impl MainWindow {
                 modifier: Modifier::BOLD,
                 ..grey
             };
+            // entries in the mark pane can be deleted, which deserves the same color as the header uses then
+            let marked_bold = Style {
+                fg: Color::LightRed,
+                ..bold
+            };
             match state.focussed {
-                Main => (bold, grey, grey),
-                Help => (grey, bold, grey),
-                Mark => (grey, grey, bold),
+                Main => (bold, grey, grey, grey),
+                Help | Errors => (grey, bold, grey, grey),
+                Mark => (grey, grey, marked_bold, grey),
             }
         };
 
-        let (header_area, entries_area, footer_area) = {
+        let (header_area, entries_area, legend_area, footer_area) = {
+            let legend_height = if display.show_legend { 1 } else { 0 };
             let regions = Layout::default()
                 .direction(Direction::Vertical)
-                .constraints([Length(1), Max(256), Length(1)].as_ref())
+                .constraints([Length(1), Max(256), Length(legend_height), Length(1)].as_ref())
                 .split(area);
-            (regions[0], regions[1], regions[2])
+            (regions[0], regions[1], regions[2], regions[3])
         };
         {
             let marked = self.mark_pane.as_ref().map(|p| p.marked());
-            let bg_color = match (marked.map_or(true, |m| m.is_empty()), state.focussed) {
+            let bg_color = match (marked.is_none_or(|m| m.is_empty()), state.focussed) {
                 (false, FocussedPane::Mark) => Color::LightRed,
                 (false, _) => COLOR_MARKED,
                 (_, _) => Color::White,
             };
-            Header.render(bg_color, header_area, buf);
+            Header.render(
+                bg_color,
+                display.byte_format,
+                state.free_space.and_then(|(_, space)| space),
+                header_area,
+                buf,
+            );
         }
-        let (entries_area, help_pane, mark_pane) = {
+        let info_pane = match (&mut self.help_pane, &mut self.errors_pane) {
+            (Some(ref mut pane), _) => Some(InfoPane::Help(pane)),
+            (None, Some(ref mut pane)) => Some(InfoPane::Errors(pane)),
+            (None, None) => None,
+        };
+        let (entries_area, info_pane, mark_pane) = {
             let regions = Layout::default()
                 .direction(Direction::Horizontal)
                 .constraints([Percentage(50), Percentage(50)].as_ref())
                 .split(entries_area);
             let (left_pane, right_pane) = (regions[0], regions[1]);
-            match (&mut self.help_pane, &mut self.mark_pane) {
-                (Some(ref mut pane), None) => (left_pane, Some((right_pane, pane)), None),
+            match (info_pane, &mut self.mark_pane) {
+                (Some(pane), None) => (left_pane, Some((right_pane, pane)), None),
                 (None, Some(ref mut pane)) => (left_pane, None, Some((right_pane, pane))),
-                (Some(ref mut help), Some(ref mut mark)) => {
+                (Some(info), Some(ref mut mark)) => {
                     let regions = Layout::default()
                         .direction(Direction::Vertical)
                         .constraints([Percentage(50), Percentage(50)].as_ref())
                         .split(right_pane);
                     (
                         left_pane,
-                        Some((regions[0], help)),
+                        Some((regions[0], info)),
                         Some((regions[1], mark)),
                     )
                 }
@@ -110,39 +138,100 @@ impl MainWindow {
             let props = MarkPaneProps {
                 border_style: mark_style,
                 format: display.byte_format,
+                ellipsis: display.ellipsis,
+                num_paths_to_confirm: display.num_paths_to_confirm,
             };
             pane.render(props, mark_area, buf);
         }
 
-        if let Some((help_area, pane)) = help_pane {
-            let props = HelpPaneProps {
-                border_style: help_style,
-            };
-            pane.render(props, help_area, buf);
+        match info_pane {
+            Some((help_area, InfoPane::Help(pane))) => {
+                let props = HelpPaneProps {
+                    border_style: help_style,
+                };
+                pane.render(props, help_area, buf);
+            }
+            Some((errors_area, InfoPane::Errors(pane))) => {
+                let props = ErrorsPaneProps {
+                    border_style: help_style,
+                };
+                pane.render(props, errors_area, buf);
+            }
+            None => {}
         }
 
         let marked = self.mark_pane.as_ref().map(|p| p.marked());
         let props = EntriesProps {
-            tree: &tree,
+            tree,
             root: state.root,
             display: *display,
             entries: &state.entries,
             marked,
             selected: state.selected,
             border_style: entries_style,
-            is_focussed: if let Main = state.focussed {
-                true
-            } else {
-                false
-            },
+            is_focussed: matches!(state.focussed, Main),
         };
-        self.entries_pane.render(props, entries_area, buf);
+        match state.split {
+            Some(ref split) => {
+                let regions = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Percentage(50), Percentage(50)].as_ref())
+                    .split(entries_area);
+                let split_props = EntriesProps {
+                    root: split.root,
+                    entries: &split.entries,
+                    selected: split.selected,
+                    border_style: unfocussed_style,
+                    is_focussed: false,
+                    ..props
+                };
+                let (left, right) = if split.is_right {
+                    (props, split_props)
+                } else {
+                    (split_props, props)
+                };
+                self.entries_pane.render(left, regions[0], buf);
+                self.split_entries_pane.render(right, regions[1], buf);
+            }
+            None => self.entries_pane.render(props, entries_area, buf),
+        }
+
+        if display.show_legend {
+            let visible_entries = state
+                .entries
+                .iter()
+                .chain(state.split.iter().flat_map(|split| &split.entries));
+            let (has_incomplete_entries, has_missing_entries) =
+                visible_entries.fold((false, false), |(incomplete, missing), entry| {
+                    (
+                        incomplete || entry.data.incomplete,
+                        missing || !entry.exists,
+                    )
+                });
+            Legend.render(
+                LegendProps {
+                    format: display.byte_format,
+                    large_entry_size: display.large_entry_size,
+                    show_own_size: display.show_own_size,
+                    has_marked_entries: self
+                        .mark_pane
+                        .as_ref()
+                        .is_some_and(|pane| !pane.marked().is_empty()),
+                    has_incomplete_entries,
+                    has_missing_entries,
+                    is_split: state.split.is_some(),
+                },
+                legend_area,
+                buf,
+            );
+        }
 
         Footer.render(
             FooterProps {
                 total_bytes: *total_bytes,
                 format: display.byte_format,
                 entries_traversed: *entries_traversed,
+                io_errors: *io_errors,
                 message: state.message.clone(),
             },
             footer_area,
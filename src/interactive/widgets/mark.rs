@@ -1,5 +1,6 @@
 use crate::interactive::{
-    fit_string_graphemes_with_ellipsis, path_of, widgets::entry_color, CursorDirection,
+    fit_string_graphemes_with_ellipsis_in_middle, index_of_path, path_of, widgets::entry_color,
+    CursorDirection,
 };
 use dua::{
     traverse::{Tree, TreeIndex},
@@ -21,10 +22,36 @@ use tui::{
 use tui_react::{List, ListProps};
 use unicode_segmentation::UnicodeSegmentation;
 
+/// Below this width of the path column, sizes are not shown to leave more room for the path
+const MIN_PATH_WIDTH: usize = 8;
+
 pub enum MarkMode {
     Delete,
 }
 
+/// The order in which marked entries are listed
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum MarkSorting {
+    /// In the order they were marked
+    #[default]
+    Marked,
+    /// The largest ones first, and by path if their size is equal
+    SizeDescending,
+    /// Alphabetically by path
+    Path,
+}
+
+impl MarkSorting {
+    /// Sort by `sorting`, or in the order entries were marked if we sort like that already.
+    fn toggle(self, sorting: MarkSorting) -> MarkSorting {
+        if self == sorting {
+            MarkSorting::Marked
+        } else {
+            sorting
+        }
+    }
+}
+
 pub type EntryMarkMap = BTreeMap<TreeIndex, EntryMark>;
 pub struct EntryMark {
     pub size: u64,
@@ -41,11 +68,16 @@ pub struct MarkPane {
     list: List,
     has_focus: bool,
     last_sorting_index: usize,
+    sorting: MarkSorting,
+    is_confirming_deletion: bool,
 }
 
 pub struct MarkPaneProps {
     pub border_style: Style,
     pub format: ByteFormat,
+    pub ellipsis: &'static str,
+    /// The amount of marked paths to show when asking for confirmation of their deletion
+    pub num_paths_to_confirm: usize,
 }
 
 impl MarkPane {
@@ -53,8 +85,24 @@ impl MarkPane {
     pub fn has_focus(&self) -> bool {
         self.has_focus
     }
+    #[cfg(test)]
+    pub fn list_offset(&self) -> usize {
+        self.list.offset
+    }
+    #[cfg(test)]
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+    #[cfg(test)]
+    pub fn listed_paths(&self) -> Vec<PathBuf> {
+        self.marked_sorted()
+            .into_iter()
+            .map(|(_, v)| v.path.clone())
+            .collect()
+    }
     pub fn set_focus(&mut self, has_focus: bool) {
         self.has_focus = has_focus;
+        self.is_confirming_deletion = false;
         if has_focus {
             self.selected = Some(self.marked.len().saturating_sub(1));
         } else {
@@ -89,15 +137,57 @@ impl MarkPane {
     pub fn marked(&self) -> &EntryMarkMap {
         &self.marked
     }
+
+    /// Find the marked entries in `tree` by their path, as their indices change whenever the tree is built anew,
+    /// and update their sizes. The marks of paths which are not below `root` anymore are dropped, and their
+    /// amount is returned along with the pane, if anything is still marked.
+    pub fn reconcile(mut self, tree: &Tree, root: TreeIndex) -> (Option<Self>, usize) {
+        let selected_path = self
+            .selected
+            .and_then(|position| self.tree_index_by_list_position(position))
+            .and_then(|idx| self.marked.get(&idx))
+            .map(|mark| mark.path.clone());
+        let num_marks = self.marked.len();
+        self.marked = std::mem::take(&mut self.marked)
+            .into_values()
+            .filter_map(|mut mark| {
+                let idx = index_of_path(tree, root, &mark.path)?;
+                mark.size = tree.node_weight(idx)?.size;
+                Some((idx, mark))
+            })
+            .collect();
+        let num_dropped = num_marks - self.marked.len();
+        if self.marked.is_empty() {
+            return (None, num_dropped);
+        }
+        if let Some(selected) = self.selected {
+            let marked = self.marked_sorted();
+            self.selected = Some(
+                selected_path
+                    .and_then(|path| marked.iter().position(|(_, mark)| mark.path == path))
+                    .unwrap_or_else(|| selected.min(marked.len() - 1)),
+            );
+        }
+        (Some(self), num_dropped)
+    }
     pub fn key(mut self, key: Key) -> Option<(Self, Option<MarkMode>)> {
         let action = None;
+        if self.is_confirming_deletion {
+            self.is_confirming_deletion = false;
+            return match key {
+                Char('y') => self.prepare_deletion(),
+                _ => Some((self, action)),
+            };
+        }
         match key {
-            Ctrl('r') => return self.prepare_deletion(),
+            Ctrl('r') => self.is_confirming_deletion = true,
             Char('d') | Char(' ') => return self.remove_selected().map(|s| (s, action)),
             Ctrl('u') | PageUp => self.change_selection(CursorDirection::PageUp),
             Char('k') | Up => self.change_selection(CursorDirection::Up),
             Char('j') | Down => self.change_selection(CursorDirection::Down),
             Ctrl('d') | PageDown => self.change_selection(CursorDirection::PageDown),
+            Char('s') => self.change_sorting(self.sorting.toggle(MarkSorting::SizeDescending)),
+            Char('n') => self.change_sorting(self.sorting.toggle(MarkSorting::Path)),
             _ => {}
         };
         Some((self, action))
@@ -186,16 +276,27 @@ impl MarkPane {
     }
 
     fn tree_index_by_list_position(&mut self, selected: usize) -> Option<TreeIndex> {
-        self.marked_sorted_by_index()
+        self.marked_sorted()
             .get(selected)
             .map(|(k, _)| *k.to_owned())
     }
 
-    fn marked_sorted_by_index(&self) -> Vec<(&TreeIndex, &EntryMark)> {
-        self.marked
-            .iter()
-            .sorted_by_key(|(_, v)| &v.index)
-            .collect()
+    fn marked_sorted(&self) -> Vec<(&TreeIndex, &EntryMark)> {
+        sorted_marks(&self.marked, self.sorting)
+    }
+
+    /// List entries in the order of `sorting`, keeping the selected entry selected wherever it ends up.
+    fn change_sorting(&mut self, sorting: MarkSorting) {
+        let selected = self
+            .selected
+            .and_then(|position| self.tree_index_by_list_position(position));
+        self.sorting = sorting;
+        if let Some(selected) = selected {
+            self.selected = self
+                .marked_sorted()
+                .iter()
+                .position(|(idx, _)| **idx == selected);
+        }
     }
 
     fn change_selection(&mut self, direction: CursorDirection) {
@@ -210,18 +311,34 @@ impl MarkPane {
         let MarkPaneProps {
             border_style,
             format,
+            ellipsis,
+            num_paths_to_confirm,
         } = props.borrow();
 
         let marked: &_ = &self.marked;
+        let total_size = format.display(marked.values().map(|v| v.size).sum::<u64>());
         let title = format!(
-            "Marked {} items ({}) ",
+            "Marked {} items ({}){} ",
             marked.len(),
-            format.display(marked.iter().map(|(_k, v)| v.size).sum::<u64>())
+            total_size,
+            match self.sorting {
+                MarkSorting::Marked => "",
+                MarkSorting::SizeDescending => " by size",
+                MarkSorting::Path => " by path",
+            }
         );
         let selected = self.selected;
         let has_focus = self.has_focus;
-        let entries = marked.values().sorted_by_key(|v| &v.index).enumerate().map(
-            |(idx, v): (usize, &EntryMark)| {
+        // The size column accounts for the pane's borders as well, which need to be subtracted if it is dropped
+        let (path_column_width, show_sizes) =
+            match (area.width as usize).checked_sub(format.total_width()) {
+                Some(width) if width >= MIN_PATH_WIDTH => (width, true),
+                _ => (area.width.saturating_sub(2) as usize, false),
+            };
+        let entries = sorted_marks(marked, self.sorting)
+            .into_iter()
+            .enumerate()
+            .map(|(idx, (_, v)): (usize, (&TreeIndex, &EntryMark))| {
                 let default_style = match selected {
                     Some(selected) if idx == selected => {
                         let mut modifier = Modifier::REVERSED;
@@ -246,15 +363,13 @@ impl MarkPane {
                         }
                     );
                     let num_path_graphemes = path.graphemes(true).count();
-                    match num_path_graphemes + format.total_width() {
-                        n if n > area.width as usize => {
-                            let desired_size = num_path_graphemes - (n - area.width as usize);
-                            fit_string_graphemes_with_ellipsis(
-                                path,
-                                num_path_graphemes,
-                                desired_size,
-                            )
-                        }
+                    match num_path_graphemes {
+                        n if n > path_column_width => fit_string_graphemes_with_ellipsis_in_middle(
+                            path,
+                            num_path_graphemes,
+                            path_column_width,
+                            ellipsis,
+                        ),
                         _ => (path, num_path_graphemes),
                     }
                 };
@@ -266,35 +381,35 @@ impl MarkPane {
                         ..default_style
                     },
                 );
-                let bytes = Text::Styled(
+                let spacer = Text::Styled(
                     format!(
-                        "{:>byte_column_width$} ",
-                        format.display(v.size).to_string(), // we would have to impl alignment/padding ourselves otherwise...
-                        byte_column_width = format.width()
+                        "{:-space$}",
+                        "",
+                        space = path_column_width.saturating_sub(path_len)
                     )
                     .into(),
                     Style {
-                        fg: Color::Green,
+                        fg: fg_path,
                         ..default_style
                     },
                 );
-                let spacer = Text::Styled(
+                if !show_sizes {
+                    return vec![path, spacer];
+                }
+                let bytes = Text::Styled(
                     format!(
-                        "{:-space$}",
-                        "",
-                        space = (area.width as usize)
-                            .saturating_sub(path_len)
-                            .saturating_sub(format.total_width())
+                        "{:>byte_column_width$} ",
+                        format.display(v.size).to_string(), // we would have to impl alignment/padding ourselves otherwise...
+                        byte_column_width = format.width()
                     )
                     .into(),
                     Style {
-                        fg: fg_path,
+                        fg: Color::Green,
                         ..default_style
                     },
                 );
                 vec![path, spacer, bytes]
-            },
-        );
+            });
 
         let entry_in_view = match self.selected {
             Some(s) => Some(s),
@@ -312,11 +427,51 @@ impl MarkPane {
         block.draw(area, buf);
 
         let list_area = if self.has_focus {
+            let default_style = Style {
+                fg: Color::Black,
+                bg: Color::Yellow,
+                modifier: Modifier::BOLD,
+            };
+            let key_style = Style {
+                fg: Color::LightRed,
+                modifier: default_style.modifier | Modifier::RAPID_BLINK,
+                ..default_style
+            };
+            let (help_lines, num_help_lines) = if self.is_confirming_deletion {
+                let paths = self.paths_to_confirm(*num_paths_to_confirm);
+                let num_lines = 1 + paths.len();
+                let mut lines = vec![
+                    Text::Styled(
+                        format!(" Delete {} items ({})?", marked.len(), total_size).into(),
+                        default_style,
+                    ),
+                    Text::Styled(" y".into(), key_style),
+                    Text::Styled(" to confirm, any other key cancels\n".into(), default_style),
+                ];
+                lines.extend(
+                    paths
+                        .into_iter()
+                        .map(|p| Text::Styled(format!("{}\n", p).into(), default_style)),
+                );
+                (lines, num_lines)
+            } else {
+                let lines = vec![
+                    Text::Styled(" Ctrl + r".into(), key_style),
+                    Text::Styled(
+                        " deletes listed entries from disk after confirmation".into(),
+                        default_style,
+                    ),
+                ];
+                (lines, 1)
+            };
             let (help_line_area, list_area) = {
                 let help_at_bottom =
                     selected.unwrap_or(0) >= inner_area.height.saturating_sub(1) as usize / 2;
                 let constraints = {
-                    let mut c = vec![Constraint::Length(1), Constraint::Max(256)];
+                    let mut c = vec![
+                        Constraint::Length(num_help_lines as u16),
+                        Constraint::Max(256),
+                    ];
                     if help_at_bottom {
                         c.reverse();
                     }
@@ -333,30 +488,9 @@ impl MarkPane {
                 }
             };
 
-            let default_style = Style {
-                fg: Color::Black,
-                bg: Color::Yellow,
-                modifier: Modifier::BOLD,
-            };
-            Paragraph::new(
-                [
-                    Text::Styled(
-                        " Ctrl + r".into(),
-                        Style {
-                            fg: Color::LightRed,
-                            modifier: default_style.modifier | Modifier::RAPID_BLINK,
-                            ..default_style
-                        },
-                    ),
-                    Text::Styled(
-                        " deletes listed entries from disk without prompt".into(),
-                        default_style,
-                    ),
-                ]
-                .iter(),
-            )
-            .style(default_style)
-            .draw(help_line_area, buf);
+            Paragraph::new(help_lines.iter())
+                .style(default_style)
+                .draw(help_line_area, buf);
             list_area
         } else {
             inner_area
@@ -368,4 +502,31 @@ impl MarkPane {
         };
         self.list.render(props, entries, list_area, buf)
     }
+
+    /// The first `num_paths` marked paths in the order they are listed, followed by a note about the amount
+    /// of paths which were left out, if any.
+    fn paths_to_confirm(&self, num_paths: usize) -> Vec<String> {
+        let marked = self.marked_sorted();
+        let mut lines: Vec<_> = marked
+            .iter()
+            .take(num_paths)
+            .map(|(_, v)| format!("   {}", v.path.display()))
+            .collect();
+        if marked.len() > num_paths {
+            lines.push(format!("   ...and {} more", marked.len() - num_paths));
+        }
+        lines
+    }
+}
+
+/// The `marked` entries in the order they are listed with `sorting`.
+fn sorted_marks(marked: &EntryMarkMap, sorting: MarkSorting) -> Vec<(&TreeIndex, &EntryMark)> {
+    marked
+        .iter()
+        .sorted_by(|(_, l), (_, r)| match sorting {
+            MarkSorting::Marked => l.index.cmp(&r.index),
+            MarkSorting::SizeDescending => r.size.cmp(&l.size).then_with(|| l.path.cmp(&r.path)),
+            MarkSorting::Path => l.path.cmp(&r.path),
+        })
+        .collect()
 }
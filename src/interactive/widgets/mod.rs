@@ -1,14 +1,18 @@
 mod entries;
+mod errors;
 mod footer;
 mod header;
 mod help;
+mod legend;
 mod main;
 mod mark;
 
 pub use entries::*;
+pub use errors::*;
 pub use footer::*;
 pub use header::*;
 pub use help::*;
+pub use legend::*;
 pub use main::*;
 pub use mark::*;
 
@@ -16,6 +20,8 @@ use tui::style::Color;
 
 pub const COLOR_MARKED: Color = Color::Yellow;
 pub const COLOR_MARKED_DARK: Color = Color::Rgb(176, 126, 0);
+/// The color of the size of entries larger than the configured threshold
+pub const COLOR_LARGE: Color = Color::LightMagenta;
 
 fn entry_color(fg: Color, is_file: bool, is_marked: bool) -> Color {
     match (is_file, is_marked) {
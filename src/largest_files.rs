@@ -0,0 +1,190 @@
+//! Find the largest files below the given paths.
+use crate::{
+    aggregate::{
+        has_header, path_color, path_column_width, write_header, write_path, PathAggregate,
+        Subject, SubjectKind,
+    },
+    compression,
+    ls_colors::PathKind,
+    walk::{Filesystem, Walk},
+    InodeFilter, WalkOptions, WalkResult,
+};
+use failure::Error;
+use std::{cmp::Reverse, collections::BinaryHeap, io, path::Path};
+
+/// Walk the given `paths` and write the `n` largest files within them to `out` along with their size, ascending by
+/// size and then by path, in the format [`aggregate()`](crate::aggregate()) uses for paths.
+///
+/// Only the `n` largest files seen so far are kept while walking. Unless `options.count_hard_links` is set, a file
+/// with multiple hard links is only considered by the path which is walked first.
+pub fn largest_files(
+    out: impl io::Write,
+    options: WalkOptions,
+    n: usize,
+    paths: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> Result<WalkResult, Error> {
+    largest_files_with(&Filesystem, out, options, n, paths)
+}
+
+fn largest_files_with(
+    walk: &impl Walk,
+    mut out: impl io::Write,
+    mut options: WalkOptions,
+    n: usize,
+    paths: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> Result<WalkResult, Error> {
+    let mut res = WalkResult::default();
+    let mut inodes = InodeFilter::default();
+    let mut largest = BinaryHeap::with_capacity(n + 1);
+    for path in paths {
+        for entry in walk.entries(path.as_ref(), &options) {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(_) => {
+                    res.num_errors += 1;
+                    continue;
+                }
+            };
+            let m = match entry.metadata {
+                Ok(m) if m.is_file => m,
+                Ok(_) => continue,
+                Err(_) => {
+                    res.num_errors += 1;
+                    continue;
+                }
+            };
+            if !options.count_hard_links
+                && !m
+                    .file_id_and_links
+                    .is_none_or(|(id, nlinks)| inodes.add_inode(id, nlinks))
+            {
+                continue;
+            }
+            let size = match (options.apparent_size, m.disk_size) {
+                (true, _) => m.apparent_size,
+                (false, Ok(disk_size)) => disk_size,
+                (false, Err(_)) => {
+                    res.num_errors += 1;
+                    continue;
+                }
+            };
+            res.total_bytes += size;
+            largest.push(Reverse((
+                size,
+                m.apparent_size,
+                entry.path,
+                PathKind::of(&m),
+                entry.depth,
+            )));
+            if largest.len() > n {
+                largest.pop();
+            }
+        }
+    }
+
+    let files: Vec<_> = largest
+        .into_sorted_vec()
+        .into_iter()
+        .rev()
+        .map(|Reverse(file)| file)
+        .collect();
+    let path_column_width = path_column_width(
+        files
+            .iter()
+            .map(|(_, _, path, _, _)| options.displayed_path(path).to_string_lossy()),
+        None,
+    );
+    if let Some(heatmap) = options.heatmap.as_mut() {
+        heatmap.largest = files
+            .iter()
+            .map(|(size, _, _, _, _)| *size)
+            .max()
+            .unwrap_or(0);
+    }
+    if has_header(&options) {
+        write_header(&mut out, &options, path_column_width, SubjectKind::Path)?;
+    }
+    for (size, apparent_size, path, kind, depth) in files {
+        let estimated_savings = if options.estimate_compression {
+            compression::estimated_savings(&path, size).unwrap_or_else(|_| {
+                res.num_errors += 1;
+                0
+            })
+        } else {
+            0
+        };
+        write_path(
+            &mut out,
+            &options,
+            path_column_width,
+            Subject::Path {
+                path: options.displayed_path(&path),
+                depth,
+                collapsed_levels: 0,
+            },
+            &PathAggregate::of_file(size, apparent_size, estimated_savings),
+            path_color(&options, kind, &path),
+        )?;
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::test_options,
+        walk::{EntryMetadata, FakeFilesystem},
+        ErrorClass, FileId,
+    };
+
+    fn options() -> WalkOptions {
+        WalkOptions {
+            apparent_size: false,
+            ..test_options()
+        }
+    }
+
+    #[test]
+    fn only_the_largest_files_are_written_with_each_hard_linked_file_once() -> Result<(), Error> {
+        let link = Some((
+            FileId {
+                device: 1,
+                inode: 1,
+            },
+            2,
+        ));
+        let file = |disk_size: Option<u64>, file_id_and_links| EntryMetadata {
+            disk_size: disk_size.ok_or(ErrorClass::Other),
+            file_id_and_links,
+            ..Default::default()
+        };
+        let fs = FakeFilesystem::default()
+            .dir("root")
+            .file("root/small", file(Some(1), None))
+            .file("root/link", file(Some(8), link))
+            .dir("root/dir")
+            .file("root/dir/medium", file(Some(4), None))
+            .file("root/dir/link", file(Some(8), link))
+            .file("root/dir/unsized", file(None, None))
+            .unreadable("root/dir/unreadable")
+            .file("other", file(Some(2), None));
+
+        let mut out = Vec::new();
+        let res = largest_files_with(&fs, &mut out, options(), 2, ["root", "other"])?;
+        assert_eq!(res.num_errors, 2);
+        assert_eq!(
+            res.total_bytes, 15,
+            "all files count, not only the largest ones"
+        );
+        assert_eq!(
+            String::from_utf8(out)?
+                .lines()
+                .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+                .collect::<Vec<_>>(),
+            vec!["4 b root/dir/medium", "8 b root/link"],
+            "the largest file is last, and its second hard link doesn't count"
+        );
+        Ok(())
+    }
+}
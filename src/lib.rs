@@ -5,10 +5,44 @@ extern crate jwalk;
 
 mod aggregate;
 mod common;
+mod compression;
+mod dot;
+mod duplicates;
+mod find_inode;
+mod glob;
+mod heatmap;
 mod inodefilter;
+mod largest_files;
+mod locale;
+mod ls_colors;
+mod max_depth;
+mod ncdu;
+mod ndjson;
+mod owner;
+mod remote;
+mod sample;
+#[cfg(test)]
+mod testing;
+mod time;
+mod walk;
 
 pub mod traverse;
 
-pub use aggregate::aggregate;
+pub use aggregate::{aggregate, aggregate_with, AggregateOptions, TotalLine, TotalPosition};
 pub use common::*;
+pub use dot::export_dot;
+pub use duplicates::find_duplicates;
+pub use find_inode::find_inode;
+pub use glob::{expand_glob, Glob};
+pub use heatmap::{parse_palette, Heatmap};
+pub use inodefilter::FileId;
 pub(crate) use inodefilter::InodeFilter;
+pub use largest_files::largest_files;
+pub use locale::thousands_separator;
+pub use ls_colors::LsColors;
+pub use max_depth::max_depth_total;
+pub use ncdu::export_ncdu;
+pub use ndjson::{json_escaped, ndjson};
+pub use sample::{sample, SampleStatistics};
+pub use time::TimeStyle;
+pub use walk::{Entry, EntryMetadata, Filesystem, Walk};
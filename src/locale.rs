@@ -0,0 +1,117 @@
+//! Minimal knowledge about locales, as far as needed to format numbers and dates.
+use std::env;
+
+/// The character separating groups of thousands in numbers, as configured by the locale in the environment.
+/// `None` if the locale is unset or unknown, in which case digits should not be grouped.
+pub fn thousands_separator() -> Option<char> {
+    locale_of(&["LC_ALL", "LC_NUMERIC", "LANG"]).and_then(|locale| separator_for_locale(&locale))
+}
+
+/// The first locale set in the environment variables with the given `names`.
+fn locale_of(names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+/// The order of day, month and year in dates, along with the character separating them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateOrder {
+    YearMonthDay(char),
+    DayMonthYear(char),
+    MonthDayYear(char),
+}
+
+/// The way dates are written according to the locale configured for times in the environment.
+/// `None` if the locale is unset or unknown.
+pub fn date_order() -> Option<DateOrder> {
+    locale_of(&["LC_ALL", "LC_TIME", "LANG"]).and_then(|locale| date_order_for_locale(&locale))
+}
+
+fn date_order_for_locale(locale: &str) -> Option<DateOrder> {
+    use DateOrder::*;
+    let name = locale.split(['.', '@']).next()?;
+    if name == "en_US" {
+        return Some(MonthDayYear('/'));
+    }
+    Some(match name.split('_').next()? {
+        "en" | "fr" | "es" | "it" | "pt" | "el" | "he" | "id" | "th" => DayMonthYear('/'),
+        "de" | "ru" | "pl" | "cs" | "sk" | "fi" | "nb" | "no" | "da" | "tr" | "uk" => {
+            DayMonthYear('.')
+        }
+        "nl" => DayMonthYear('-'),
+        "ja" | "zh" | "ko" => YearMonthDay('/'),
+        "sv" | "lt" => YearMonthDay('-'),
+        "hu" => YearMonthDay('.'),
+        _ => return None,
+    })
+}
+
+fn separator_for_locale(locale: &str) -> Option<char> {
+    let name = locale.split(['.', '@']).next()?;
+    if name == "de_CH" || name == "it_CH" {
+        return Some('\'');
+    }
+    Some(match name.split('_').next()? {
+        "en" | "ja" | "ko" | "zh" | "he" | "th" => ',',
+        "de" | "es" | "it" | "nl" | "pt" | "da" | "id" | "tr" | "el" => '.',
+        "fr" | "ru" | "sv" | "pl" | "cs" | "sk" | "fi" | "nb" | "no" | "uk" | "hu" => ' ',
+        _ => return None,
+    })
+}
+
+/// Write `n` with its digits in groups of three, separated by `separator`.
+pub(crate) fn group_digits(n: u64, separator: char) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (idx, digit) in digits.chars().enumerate() {
+        if idx != 0 && (digits.len() - idx).is_multiple_of(3) {
+            grouped.push(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_knows_the_separators_of_common_locales_only() {
+        assert_eq!(separator_for_locale("en_US.UTF-8"), Some(','));
+        assert_eq!(separator_for_locale("de_DE.UTF-8@euro"), Some('.'));
+        assert_eq!(separator_for_locale("de_CH"), Some('\''));
+        assert_eq!(separator_for_locale("fr_FR"), Some(' '));
+        assert_eq!(separator_for_locale("C"), None);
+        assert_eq!(separator_for_locale("POSIX"), None);
+        assert_eq!(separator_for_locale(""), None);
+    }
+
+    #[test]
+    fn it_knows_the_date_order_of_common_locales_only() {
+        use DateOrder::*;
+        assert_eq!(
+            date_order_for_locale("en_US.UTF-8"),
+            Some(MonthDayYear('/'))
+        );
+        assert_eq!(
+            date_order_for_locale("en_GB.UTF-8"),
+            Some(DayMonthYear('/'))
+        );
+        assert_eq!(date_order_for_locale("de_DE@euro"), Some(DayMonthYear('.')));
+        assert_eq!(date_order_for_locale("ja_JP"), Some(YearMonthDay('/')));
+        assert_eq!(date_order_for_locale("C"), None);
+        assert_eq!(date_order_for_locale(""), None);
+    }
+
+    #[test]
+    fn it_groups_digits_by_thousands() {
+        assert_eq!(group_digits(0, ','), "0");
+        assert_eq!(group_digits(999, ','), "999");
+        assert_eq!(group_digits(1000, ','), "1,000");
+        assert_eq!(group_digits(1_258_024, '.'), "1.258.024");
+        assert_eq!(group_digits(u64::MAX, ' '), "18 446 744 073 709 551 615");
+    }
+}
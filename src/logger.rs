@@ -0,0 +1,77 @@
+//! Log to stderr with as much detail as asked for with `-v`.
+use failure::{format_err, Error};
+use log::{LevelFilter, Log, Metadata, Record};
+use std::{
+    io::{self, Write},
+    sync::OnceLock,
+    time::Instant,
+};
+
+/// Writes records of our own crates along with the time since it was installed, like `[  0.012s INFO ] message`.
+struct StderrLogger;
+
+static LOGGER: StderrLogger = StderrLogger;
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level() && metadata.target().starts_with("dua")
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let elapsed = STARTED
+            .get()
+            .map_or(0.0, |started| started.elapsed().as_secs_f64());
+        writeln!(
+            io::stderr(),
+            "[{:>8.3}s {:<5}] {}",
+            elapsed,
+            record.level(),
+            record.args()
+        )
+        .ok();
+    }
+
+    fn flush(&self) {
+        io::stderr().flush().ok();
+    }
+}
+
+/// The most detailed level to log given the amount of times `-v` was passed, or the level named by `env` like
+/// `debug` if it wasn't passed at all, which is the value of `RUST_LOG`. Nothing is logged by default.
+pub fn level_for(verbosity: u64, env: Option<&str>) -> LevelFilter {
+    match verbosity {
+        0 => env
+            .and_then(|level| level.parse().ok())
+            .unwrap_or(LevelFilter::Off),
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+/// Log everything up to `level` to stderr from now on.
+pub fn init(level: LevelFilter) -> Result<(), Error> {
+    STARTED.get_or_init(Instant::now);
+    log::set_logger(&LOGGER).map_err(|err| format_err!("Could not set up logging: {}", err))?;
+    log::set_max_level(level);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_v_adds_detail_and_the_environment_is_only_used_without_it() {
+        assert_eq!(level_for(0, None), LevelFilter::Off);
+        assert_eq!(level_for(0, Some("debug")), LevelFilter::Debug);
+        assert_eq!(level_for(0, Some("dua=debug")), LevelFilter::Off);
+        assert_eq!(level_for(1, Some("trace")), LevelFilter::Info);
+        assert_eq!(level_for(2, None), LevelFilter::Debug);
+        assert_eq!(level_for(3, None), LevelFilter::Trace);
+    }
+}
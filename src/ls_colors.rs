@@ -0,0 +1,133 @@
+//! Color paths like `ls` does, as configured by the `LS_COLORS` environment variable.
+use crate::walk::EntryMetadata;
+use std::{fmt, path::Path};
+
+/// What kind of entry a written path is, as far as its color is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub(crate) enum PathKind {
+    /// Anything else, like paths which couldn't be read or names of owners
+    #[default]
+    Other,
+    Directory,
+    File,
+    /// A file which can be executed by anyone
+    Executable,
+    Symlink,
+}
+
+impl PathKind {
+    /// The kind of the entry with `metadata`, as obtained by the walk.
+    pub(crate) fn of(metadata: &EntryMetadata) -> Self {
+        if metadata.is_dir {
+            PathKind::Directory
+        } else if metadata.is_symlink {
+            PathKind::Symlink
+        } else if metadata.is_executable {
+            PathKind::Executable
+        } else if metadata.is_file {
+            PathKind::File
+        } else {
+            PathKind::Other
+        }
+    }
+}
+
+/// The styles of paths by kind and by extension, parsed from a value like `di=01;34:ex=01;32:*.tar=01;31`.
+///
+/// Executables, directories and symbolic links use the style of their kind, and files the one of the last pattern
+/// matching the end of their name, or `fi` if there is none. Styles which aren't made of digits and `;` are ignored,
+/// and so are `ln=target` and any other entry we don't know.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LsColors {
+    directory: Option<String>,
+    file: Option<String>,
+    executable: Option<String>,
+    symlink: Option<String>,
+    /// Endings of names like `.tar` and their style, in the order they were given
+    suffixes: Vec<(String, String)>,
+}
+
+impl LsColors {
+    /// Parse the value of `LS_COLORS`.
+    pub fn parse(value: &str) -> Self {
+        let mut colors = LsColors::default();
+        for (key, style) in value.split(':').filter_map(|entry| entry.split_once('=')) {
+            if style.is_empty() || !style.chars().all(|c| c.is_ascii_digit() || c == ';') {
+                continue;
+            }
+            let style = style.to_owned();
+            match key {
+                "di" => colors.directory = Some(style),
+                "fi" => colors.file = Some(style),
+                "ex" => colors.executable = Some(style),
+                "ln" => colors.symlink = Some(style),
+                _ => {
+                    if let Some(suffix) = key.strip_prefix('*') {
+                        colors.suffixes.push((suffix.to_owned(), style));
+                    }
+                }
+            }
+        }
+        colors
+    }
+
+    /// The style of `path` of the given `kind`, if one is configured.
+    pub(crate) fn style_of(&self, kind: PathKind, path: &Path) -> Option<&str> {
+        match kind {
+            PathKind::Directory => self.directory.as_deref(),
+            PathKind::Executable => self.executable.as_deref(),
+            PathKind::Symlink => self.symlink.as_deref(),
+            PathKind::File => {
+                let name = path.file_name()?.to_string_lossy();
+                self.suffixes
+                    .iter()
+                    .rev()
+                    .find(|(suffix, _)| name.ends_with(suffix.as_str()))
+                    .map(|(_, style)| style.as_str())
+                    .or(self.file.as_deref())
+            }
+            PathKind::Other => None,
+        }
+    }
+}
+
+/// The escape sequence selecting a style of [`LsColors`].
+pub(crate) struct LsStyle(pub String);
+
+impl fmt::Display for LsStyle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "\x1b[{}m", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn styles_are_chosen_by_kind_and_then_by_the_end_of_the_name() {
+        let colors = LsColors::parse(
+            "rs=0:di=01;34:ln=target:ex=01;32:fi=00:*.tar=01;31:*.gz=31:*.tar.gz=35:bad=;x",
+        );
+        let style = |kind, path: &str| colors.style_of(kind, Path::new(path));
+        assert_eq!(style(PathKind::Directory, "dir.tar"), Some("01;34"));
+        assert_eq!(style(PathKind::Executable, "run.tar"), Some("01;32"));
+        assert_eq!(
+            style(PathKind::Symlink, "link"),
+            None,
+            "'target' isn't a style"
+        );
+        assert_eq!(style(PathKind::File, "dir/a.tar"), Some("01;31"));
+        assert_eq!(
+            style(PathKind::File, "a.tar.gz"),
+            Some("35"),
+            "the last matching pattern wins"
+        );
+        assert_eq!(style(PathKind::File, "README"), Some("00"));
+        assert_eq!(style(PathKind::Other, "a.tar"), None);
+        assert_eq!(
+            LsColors::parse("").style_of(PathKind::File, Path::new("a")),
+            None
+        );
+    }
+}
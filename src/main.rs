@@ -4,37 +4,189 @@ extern crate failure;
 extern crate failure_tools;
 extern crate structopt;
 
-use crate::interactive::TerminalApp;
-use dua::{ByteFormat, Color, TraversalSorting};
-use failure::{Error, ResultExt};
+use crate::interactive::{translate, DisplayOptions, TerminalApp, SHELL_KEY};
+use dua::{AggregateOptions, ByteFormat, Color, Glob, Heatmap, TotalLine, TotalPosition};
+use failure::{bail, Error, ResultExt};
 use failure_tools::ok_or_exit;
-use std::{fs, io, io::Write, path::PathBuf, process};
+use std::{
+    fs, io,
+    io::Write,
+    path::{Path, PathBuf},
+    process,
+    sync::mpsc,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 use structopt::StructOpt;
 use termion::{input::TermRead, raw::IntoRawMode, screen::AlternateScreen};
 use tui::backend::TermionBackend;
 use tui_react::Terminal;
 
+mod config;
 mod interactive;
+mod logger;
 mod options;
 
 fn run() -> Result<(), Error> {
     use options::Command::*;
 
     let opt: options::Args = options::Args::from_args();
+    let mut log_level = logger::level_for(opt.verbosity, std::env::var("RUST_LOG").ok().as_deref());
+    if log_level != log::LevelFilter::Off
+        && matches!(opt.command, Some(Interactive { .. }))
+        && atty::is(atty::Stream::Stderr)
+    {
+        if opt.verbosity > 0 {
+            bail!("Logs would garble the screen of the interactive mode, redirect stderr to a file like '2>dua.log'");
+        }
+        log_level = log::LevelFilter::Off;
+    }
+    logger::init(log_level)?;
+    if opt.print_capabilities {
+        println!("{}", capabilities());
+        return Ok(());
+    }
+    if opt.fail_over.is_some() && opt.command.is_some() {
+        bail!("--fail-over can only be used without a subcommand");
+    }
+    if opt.oneline && opt.command.is_some() {
+        bail!("--oneline can only be used without a subcommand");
+    }
+    if opt.watch.is_some() && opt.command.is_some() {
+        bail!("--watch can only be used without a subcommand");
+    }
+    if (opt.json || opt.csv)
+        && !matches!(
+            opt.command,
+            None | Some(Aggregate {
+                ndjson: false,
+                find_inode: None,
+                sample: None,
+                export_dot: None,
+                find_duplicates: false,
+                export_ncdu: None,
+                ..
+            })
+        )
+    {
+        bail!(
+            "{} only applies to the sizes of paths, not to interactive mode or the other modes of the aggregate subcommand",
+            if opt.json { "--json" } else { "--csv" }
+        );
+    }
+    if opt.max_time.is_some() && !aggregates_sizes_of_paths(&opt.command) {
+        bail!("--max-time only limits the sizes of paths, not interactive mode or the other modes of the aggregate subcommand");
+    }
+    if !opt.fatal_errors.is_empty() && !aggregates_sizes_of_paths(&opt.command) {
+        bail!("--fatal-error only applies to the sizes of paths, not to interactive mode or the other modes of the aggregate subcommand");
+    }
+    if opt.summarize
+        && matches!(
+            opt.command,
+            Some(Aggregate { top: Some(_), .. }) | Some(Aggregate { biggest: true, .. })
+        )
+    {
+        bail!("--summarize writes paths in the order given, which can't be limited by --top or --biggest");
+    }
+    if let (Some(smaller), Some(larger)) = (opt.exclude_smaller_than, opt.exclude_larger_than) {
+        if smaller > larger {
+            bail!(
+                "--exclude-smaller-than {} exceeds --exclude-larger-than {}, which would exclude all files",
+                smaller,
+                larger
+            );
+        }
+    }
+    let config = config::Config::from_env().or(config::Config::load(opt.config.clone())?);
+    let output_order = opt.output_order();
+    let expand_globs = opt.expand_globs || cfg!(windows);
+    let exclude = exclude_patterns(opt.exclude, opt.exclude_from)?;
     let walk_options = dua::WalkOptions {
-        threads: opt.threads.unwrap_or(0),
-        byte_format: opt.format.map(Into::into).unwrap_or(ByteFormat::Metric),
-        color: if atty::is(atty::Stream::Stdout) {
+        threads: opt.threads.or(config.threads).unwrap_or(0),
+        byte_format: if opt.bytes {
+            ByteFormat::Bytes
+        } else {
+            opt.format
+                .or(config.format)
+                .map(Into::into)
+                .unwrap_or(ByteFormat::Metric)
+        },
+        color: if config.color != Some(false)
+            && opt.output.is_none()
+            && atty::is(atty::Stream::Stdout)
+        {
             Color::Terminal
         } else {
             Color::None
         },
-        apparent_size: opt.apparent_size,
+        apparent_size: opt.apparent_size || opt.bytes,
+        both_sizes: opt.both_sizes,
+        shared_unit: opt.shared_unit,
+        show_entry_count: opt.entry_count,
+        estimate_compression: opt.estimate_compression,
+        verbose_size: opt.verbose_size,
+        thousands_separator: if opt.group_digits {
+            dua::thousands_separator()
+        } else {
+            None
+        },
+        column_order: opt.columns.into(),
+        error_annotation: opt.error_annotation.into(),
+        output_format: if opt.json {
+            dua::OutputFormat::Json
+        } else if opt.csv {
+            dua::OutputFormat::Csv {
+                delimiter: opt.delimiter.unwrap_or(','),
+            }
+        } else {
+            dua::OutputFormat::Text
+        },
+        time_style: opt.time_style.into(),
         count_hard_links: opt.count_hard_links,
-        sorting: TraversalSorting::None,
+        output_order,
+        exclude: exclude.clone(),
+        exclude_caches: opt.exclude_caches,
+        exclude_larger_than: opt.exclude_larger_than,
+        exclude_smaller_than: opt.exclude_smaller_than,
+        labels: opt.labels,
+        relative_to: opt.relative_to,
+        heatmap: match (opt.heatmap, opt.heatmap_palette) {
+            (_, Some(heatmap)) => Some(heatmap),
+            (true, None) => Some(Heatmap::default()),
+            (false, None) => None,
+        },
+        ls_colors: match std::env::var("LS_COLORS") {
+            Ok(value) if opt.ls_colors => Some(dua::LsColors::parse(&value)),
+            _ => None,
+        },
+        max_time: opt.max_time,
+        fatal_errors: opt.fatal_errors.into_iter().map(Into::into).collect(),
+        symlinks: dua::Symlinks {
+            follow_root: opt.follow_root_symlink_only || !opt.no_follow_root_symlinks,
+            follow_dirs: opt.follow_dir_symlinks,
+            follow_files: opt.follow_file_symlinks,
+        },
     };
+    let byte_format = walk_options.byte_format;
     let res = match opt.command {
-        Some(Interactive { input }) => {
+        Some(Interactive {
+            ellipsis,
+            paths_to_confirm,
+            large_entry_size,
+            report_on_exit,
+            no_confirm_quit,
+            input,
+        }) => {
+            if opt.output.is_some() {
+                bail!("Interactive mode can't write its results to a file, run without --output");
+            }
+            let input = paths_from(
+                input,
+                !opt.no_implicit_cwd,
+                &exclude,
+                opt.exclude_caches,
+                expand_globs,
+            )?;
             let mut terminal = {
                 let stdout = io::stdout()
                     .into_raw_mode()
@@ -43,57 +195,574 @@ fn run() -> Result<(), Error> {
                 let backend = TermionBackend::new(stdout);
                 Terminal::new(backend)?
             };
-            let mut app = TerminalApp::initialize(&mut terminal, walk_options, paths_from(input)?)?;
-            let res = app.process_events(&mut terminal, io::stdin().keys())?;
+            let (keys, resume_keys) = {
+                let (key_send, key_receive) = mpsc::channel();
+                let (resume_send, resume_receive) = mpsc::channel();
+                let key_bindings = config.key_bindings.clone();
+                thread::spawn(move || {
+                    for key in io::stdin().keys().filter_map(Result::ok) {
+                        if key_send.send(key).is_err() {
+                            break;
+                        }
+                        // the shell started by this key reads from the terminal as well, so don't read until it's done
+                        if translate(&key_bindings, key) == SHELL_KEY
+                            && resume_receive.recv().is_err()
+                        {
+                            break;
+                        }
+                    }
+                });
+                (key_receive, resume_send)
+            };
+            let display_options = DisplayOptions {
+                ellipsis: Box::leak(ellipsis.into_boxed_str()),
+                num_paths_to_confirm: paths_to_confirm,
+                key_bindings: Box::leak(config.key_bindings.into_boxed_slice()),
+                large_entry_size: large_entry_size.or(config.large_entry_size),
+                confirm_quit_with_marks: !(no_confirm_quit || report_on_exit),
+                ..walk_options.clone().into()
+            };
+            let (res, report) = match TerminalApp::initialize(
+                &mut terminal,
+                walk_options,
+                display_options,
+                input,
+                &keys,
+                Some(resume_keys),
+            )? {
+                Some(mut app) => app.process_events(&mut terminal, keys.into_iter().map(Ok))?,
+                None => Default::default(),
+            };
+            // leave the alternate screen first, or the report would vanish along with it
+            drop(terminal);
             io::stdout().flush().ok();
+            if report_on_exit {
+                println!("{}", report.to_json());
+            }
+            res
+        }
+        Some(Aggregate {
+            input,
+            find_inode: Some(id),
+            ..
+        }) => {
+            let mut out = output_to(opt.output.as_deref())?;
+            let res = dua::find_inode(
+                &mut out,
+                walk_options,
+                id,
+                aggregate_paths_from(
+                    input,
+                    opt.summarize,
+                    !opt.no_implicit_cwd,
+                    &exclude,
+                    opt.exclude_caches,
+                    expand_globs,
+                )?,
+            )?;
+            out.flush()?;
+            res
+        }
+        Some(Aggregate {
+            input,
+            files_only: true,
+            top,
+            ..
+        }) => {
+            let mut out = output_to(opt.output.as_deref())?;
+            let res = dua::largest_files(
+                &mut out,
+                walk_options,
+                top.expect("--files-only requires --top"),
+                aggregate_paths_from(
+                    input,
+                    opt.summarize,
+                    !opt.no_implicit_cwd,
+                    &exclude,
+                    opt.exclude_caches,
+                    expand_globs,
+                )?,
+            )?;
+            out.flush()?;
+            res
+        }
+        Some(Aggregate {
+            input,
+            sample: Some(fraction),
+            sample_seed,
+            ..
+        }) => {
+            let mut out = output_to(opt.output.as_deref())?;
+            let (res, stats) = dua::sample(
+                &mut out,
+                walk_options,
+                fraction,
+                sample_seed.unwrap_or_else(|| {
+                    SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0, |d| d.as_nanos() as u64)
+                }),
+                aggregate_paths_from(
+                    input,
+                    opt.summarize,
+                    !opt.no_implicit_cwd,
+                    &exclude,
+                    opt.exclude_caches,
+                    expand_globs,
+                )?,
+            )?;
+            out.flush()?;
+            writeln!(
+                io::stderr(),
+                "Sizes are estimated by walking {} directories and skipping {}",
+                stats.num_directories_walked,
+                stats.num_directories_skipped
+            )
+            .ok();
+            res
+        }
+        Some(Aggregate {
+            input,
+            max_depth_total: Some(max_depth),
+            ..
+        }) => {
+            let mut out = output_to(opt.output.as_deref())?;
+            let res = dua::max_depth_total(
+                &mut out,
+                walk_options,
+                max_depth,
+                aggregate_paths_from(
+                    input,
+                    opt.summarize,
+                    !opt.no_implicit_cwd,
+                    &exclude,
+                    opt.exclude_caches,
+                    expand_globs,
+                )?,
+            )?;
+            out.flush()?;
+            res
+        }
+        Some(Aggregate {
+            input,
+            export_dot: Some(path),
+            dot_max_depth,
+            dot_min_size,
+            ..
+        }) => {
+            if opt.output.is_some() {
+                bail!("--export-dot writes to the given file and can't be used with --output");
+            }
+            let mut out = output_to(Some(&path))?;
+            let res = dua::export_dot(
+                &mut out,
+                walk_options,
+                dot_max_depth,
+                dot_min_size.unwrap_or(0),
+                aggregate_paths_from(
+                    input,
+                    opt.summarize,
+                    !opt.no_implicit_cwd,
+                    &exclude,
+                    opt.exclude_caches,
+                    expand_globs,
+                )?,
+            )?;
+            out.flush()?;
+            res
+        }
+        Some(Aggregate {
+            input,
+            find_duplicates: true,
+            ..
+        }) => {
+            let mut out = output_to(opt.output.as_deref())?;
+            let res = dua::find_duplicates(
+                &mut out,
+                walk_options,
+                aggregate_paths_from(
+                    input,
+                    opt.summarize,
+                    !opt.no_implicit_cwd,
+                    &exclude,
+                    opt.exclude_caches,
+                    expand_globs,
+                )?,
+            )?;
+            out.flush()?;
+            res
+        }
+        Some(Aggregate {
+            input,
+            export_ncdu: Some(path),
+            ..
+        }) => {
+            if opt.output.is_some() {
+                bail!("--export-ncdu writes to the given file and can't be used with --output");
+            }
+            // a single tree is walked, which is the current directory as a whole unless given otherwise
+            let paths = aggregate_paths_from(
+                input,
+                true,
+                !opt.no_implicit_cwd,
+                &exclude,
+                opt.exclude_caches,
+                expand_globs,
+            )?;
+            let root = match paths.as_slice() {
+                [root] => root,
+                _ => bail!(
+                    "ncdu imports a single tree, but {} paths were given. Pass a single directory, like '.'",
+                    paths.len()
+                ),
+            };
+            let mut out = output_to(Some(&path))?;
+            let res = dua::export_ncdu(&mut out, walk_options, root)?;
+            out.flush()?;
+            res
+        }
+        Some(Aggregate {
+            input,
+            ndjson: true,
+            ..
+        }) => {
+            let mut out = output_to(opt.output.as_deref())?;
+            let res = dua::ndjson(
+                &mut out,
+                walk_options,
+                aggregate_paths_from(
+                    input,
+                    opt.summarize,
+                    !opt.no_implicit_cwd,
+                    &exclude,
+                    opt.exclude_caches,
+                    expand_globs,
+                )?,
+            )?;
+            out.flush()?;
             res
         }
         Some(Aggregate {
             input,
             no_total,
-            no_sort,
+            no_sort: _,
+            sort_by: _,
+            biggest,
+            top,
+            files_only: false,
+            by_owner,
+            rollup,
+            skip_empty,
             statistics,
+            time_per_root,
+            total_label,
+            bold_total,
+            total_shares,
+            total,
+            total_only,
+            ndjson: false,
+            find_inode: None,
+            sample: None,
+            sample_seed: _,
+            max_depth_total: None,
+            export_dot: None,
+            dot_max_depth: _,
+            dot_min_size: _,
+            find_duplicates: false,
+            export_ncdu: None,
         }) => {
-            let stdout = io::stdout();
-            let stdout_locked = stdout.lock();
-            let (res, stats) = dua::aggregate(
-                stdout_locked,
+            let mut out = output_to(opt.output.as_deref())?;
+            let (res, mut stats) = dua::aggregate(
+                &mut out,
                 walk_options,
-                !no_total,
-                !no_sort,
-                paths_from(input)?,
+                if !total_only && (no_total || biggest || opt.summarize) {
+                    None
+                } else {
+                    Some(TotalLine {
+                        label: total_label.unwrap_or_else(|| TotalLine::default().label),
+                        bold: bold_total,
+                        position: total.map_or(TotalPosition::Last, Into::into),
+                        only: total_only,
+                        shares: total_shares,
+                    })
+                },
+                if biggest { Some(1) } else { top },
+                AggregateOptions {
+                    by_owner,
+                    rollup,
+                    skip_empty,
+                    statistics,
+                },
+                aggregate_paths_from(
+                    input,
+                    opt.summarize,
+                    !opt.no_implicit_cwd,
+                    &exclude,
+                    opt.exclude_caches,
+                    expand_globs,
+                )?,
             )?;
+            out.flush()?;
             if statistics {
+                if !time_per_root {
+                    stats.time_per_root.clear();
+                }
                 writeln!(io::stderr(), "{:?}", stats).ok();
             }
             res
         }
+        None if opt.watch.is_some() => {
+            let (input, summarize, implicit_cwd, exclude_caches) = (
+                opt.input,
+                opt.summarize,
+                !opt.no_implicit_cwd,
+                opt.exclude_caches,
+            );
+            let paths = || {
+                aggregate_paths_from(
+                    input.clone(),
+                    summarize,
+                    implicit_cwd,
+                    &exclude,
+                    exclude_caches,
+                    expand_globs,
+                )
+            };
+            return watch(
+                walk_options,
+                opt.watch.expect("checked by the guard"),
+                summarize,
+                paths,
+            );
+        }
         None => {
-            let stdout = io::stdout();
-            let stdout_locked = stdout.lock();
-            dua::aggregate(
-                stdout_locked,
+            let mut out: Box<dyn Write> = match opt.fail_over {
+                Some(_) => Box::new(io::sink()),
+                None => output_to(opt.output.as_deref())?,
+            };
+            let thousands_separator = walk_options.thousands_separator;
+            let mut sink = io::sink();
+            let (res, _) = dua::aggregate(
+                if opt.oneline {
+                    &mut sink
+                } else {
+                    &mut out as &mut dyn Write
+                },
                 walk_options,
-                true,
-                true,
-                paths_from(opt.input)?,
-            )?
-            .0
+                if opt.summarize {
+                    None
+                } else {
+                    Some(TotalLine::default())
+                },
+                None,
+                AggregateOptions::default(),
+                aggregate_paths_from(
+                    opt.input,
+                    opt.summarize,
+                    !opt.no_implicit_cwd,
+                    &exclude,
+                    opt.exclude_caches,
+                    expand_globs,
+                )?,
+            )?;
+            if opt.oneline {
+                write!(
+                    out,
+                    "{}",
+                    byte_format
+                        .display(res.total_bytes)
+                        .grouped(thousands_separator)
+                )?;
+            }
+            out.flush()?;
+            res
         }
     };
 
-    if res.num_errors > 0 {
+    for warning in &res.warnings {
+        eprintln!("warning: {}", warning);
+    }
+    if let Some(threshold) = opt.fail_over.filter(|&t| res.total_bytes > t) {
+        eprintln!(
+            "the total of {} exceeds {}",
+            byte_format.display(res.total_bytes),
+            byte_format.display(threshold)
+        );
+        process::exit(2);
+    }
+    if res.is_truncated {
+        process::exit(3);
+    }
+    if res.num_errors > 0 && !opt.ignore_errors {
         process::exit(1);
     }
     Ok(())
 }
 
-fn paths_from(paths: Vec<PathBuf>) -> Result<Vec<PathBuf>, io::Error> {
-    if paths.is_empty() {
-        cwd_dirlist()
+/// Clear the screen and write the aggregate of the paths returned by `paths` every `interval`, until interrupted.
+/// Each refresh is written in one go, so Ctrl-C never leaves a partial one behind, and as the terminal stays in its
+/// normal mode there is nothing to restore when the process ends.
+fn watch(
+    walk_options: dua::WalkOptions,
+    interval: Duration,
+    summarize: bool,
+    paths: impl Fn() -> Result<Vec<PathBuf>, Error>,
+) -> Result<(), Error> {
+    let time_style = match walk_options.time_style {
+        dua::TimeStyle::Relative => dua::TimeStyle::Iso,
+        style => style,
+    };
+    loop {
+        let started = SystemTime::now();
+        let mut frame = Vec::new();
+        write!(
+            frame,
+            "{}{}Every {:?}: {}\n\n",
+            termion::clear::All,
+            termion::cursor::Goto(1, 1),
+            interval,
+            time_style.format(started, started)
+        )?;
+        let (res, _) = dua::aggregate(
+            &mut frame,
+            walk_options.clone(),
+            if summarize {
+                None
+            } else {
+                Some(TotalLine::default())
+            },
+            None,
+            AggregateOptions::default(),
+            paths()?,
+        )?;
+        let mut out = io::stdout().lock();
+        out.write_all(&frame)?;
+        out.flush()?;
+        drop(out);
+        for warning in &res.warnings {
+            eprintln!("warning: {}", warning);
+        }
+        thread::sleep(interval);
+    }
+}
+
+/// The file at `path` to write results to, created or truncated, or stdout if unset.
+fn output_to(path: Option<&Path>) -> Result<Box<dyn Write>, Error> {
+    Ok(match path {
+        Some(path) => Box::new(io::BufWriter::new(fs::File::create(path).with_context(
+            |_| format!("Could not create output file '{}'", path.display()),
+        )?)),
+        None => Box::new(io::stdout().lock()),
+    })
+}
+
+/// The capabilities of this build which depend on the platform or on cargo features, as JSON object.
+fn capabilities() -> String {
+    let capabilities = [
+        ("interactive", true),
+        ("by-owner", cfg!(all(unix, feature = "by-owner"))),
+        ("ssh", cfg!(feature = "ssh")),
+        ("shell", cfg!(all(unix, feature = "shell"))),
+        ("free-space", cfg!(all(unix, feature = "free-space"))),
+    ];
+    format!(
+        r#"{{"version":"{}","capabilities":{{{}}}}}"#,
+        env!("CARGO_PKG_VERSION"),
+        capabilities
+            .iter()
+            .map(|(name, is_available)| format!(r#""{}":{}"#, name, is_available))
+            .collect::<Vec<_>>()
+            .join(",")
+    )
+}
+
+/// Return `paths`, or all entries of the current working directory which aren't matched by `exclude` if there are none
+/// and `implicit_cwd` is set. Cache directories are left out as well if `exclude_caches` is set.
+fn paths_from(
+    paths: Vec<PathBuf>,
+    implicit_cwd: bool,
+    exclude: &[Glob],
+    exclude_caches: bool,
+    expand_globs: bool,
+) -> Result<Vec<PathBuf>, Error> {
+    let paths = if expand_globs && !paths.is_empty() {
+        let mut expanded = Vec::new();
+        for pattern in paths {
+            let matches = dua::expand_glob(&pattern)?;
+            if matches.is_empty() {
+                eprintln!(
+                    "warning: the pattern '{}' matched nothing and is ignored",
+                    pattern.display()
+                );
+            }
+            expanded.extend(matches);
+        }
+        if expanded.is_empty() {
+            bail!("None of the input patterns matched a path");
+        }
+        expanded
     } else {
-        Ok(paths)
+        paths
+    };
+    match (paths.is_empty(), implicit_cwd) {
+        (false, _) => Ok(paths),
+        (true, true) => Ok(cwd_dirlist()?
+            .into_iter()
+            .filter(|path| !exclude.iter().any(|glob| glob.is_match(path)))
+            .filter(|path| !(exclude_caches && dua::is_cache_dir(path)))
+            .collect()),
+        (true, false) => bail!("No input paths given, and the current working directory is not used due to --no-implicit-cwd"),
+    }
+}
+
+/// Like `paths_from()`, but when summarizing, the current working directory itself is used like `du -s` does.
+fn aggregate_paths_from(
+    paths: Vec<PathBuf>,
+    summarize: bool,
+    implicit_cwd: bool,
+    exclude: &[Glob],
+    exclude_caches: bool,
+    expand_globs: bool,
+) -> Result<Vec<PathBuf>, Error> {
+    if paths.is_empty() && summarize && implicit_cwd {
+        Ok(vec![PathBuf::from(".")])
+    } else {
+        paths_from(paths, implicit_cwd, exclude, exclude_caches, expand_globs)
+    }
+}
+
+/// True if `command` writes the sizes of paths with [`dua::aggregate()`], which is the only walk that can stop early
+/// or end with an error on the IO errors chosen to be fatal.
+fn aggregates_sizes_of_paths(command: &Option<options::Command>) -> bool {
+    matches!(
+        command,
+        None | Some(options::Command::Aggregate {
+            ndjson: false,
+            find_inode: None,
+            files_only: false,
+            sample: None,
+            max_depth_total: None,
+            export_dot: None,
+            find_duplicates: false,
+            export_ncdu: None,
+            ..
+        })
+    )
+}
+
+/// All `patterns` followed by the ones read from each of the `files`.
+fn exclude_patterns(patterns: Vec<String>, files: Vec<PathBuf>) -> Result<Vec<Glob>, Error> {
+    let mut globs = patterns
+        .iter()
+        .map(|pattern| pattern.parse())
+        .collect::<Result<Vec<Glob>, _>>()?;
+    for file in files {
+        let content = fs::read_to_string(&file).with_context(|_| {
+            format!("Could not read exclude patterns from '{}'", file.display())
+        })?;
+        globs.extend(Glob::parse_lines(&content)?);
     }
+    Ok(globs)
 }
 
 fn cwd_dirlist() -> Result<Vec<PathBuf>, io::Error> {
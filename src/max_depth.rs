@@ -0,0 +1,303 @@
+//! Write the size of each directory down to a given depth below the given paths, like `du --max-depth` does.
+use crate::{
+    aggregate::{
+        has_header, path_color, path_column_width, write_header, write_path, PathAggregate,
+        Subject, SubjectKind,
+    },
+    compression,
+    ls_colors::PathKind,
+    walk::{Filesystem, Walk},
+    InodeFilter, WalkOptions, WalkResult,
+};
+use failure::Error;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// A directory whose entries are still being walked
+struct OpenDirectory {
+    path: PathBuf,
+    depth: usize,
+    aggregate: PathAggregate,
+    /// The depth of the deepest entry below the directory, or its own depth if it is empty
+    deepest: usize,
+    kind: PathKind,
+}
+
+/// A directory whose size is known
+struct ClosedDirectory {
+    path: PathBuf,
+    depth: usize,
+    aggregate: PathAggregate,
+    /// The amount of levels of directories below it whose sizes are part of it, but which are not written
+    num_collapsed_levels: usize,
+    kind: PathKind,
+}
+
+/// Walk the given `paths` and write the size of each of them and of each directory up to `max_depth` levels below
+/// them to `out`, in the format [`aggregate()`](crate::aggregate()) uses for paths. Directories come before the
+/// directory containing them, hence each input path is written after everything below it.
+///
+/// Sizes include everything below a directory, also what is deeper than `max_depth`. Directories at `max_depth`
+/// which contain more directories note the amount of levels which aren't shown after their path, like
+/// `dir (+3 levels)`. With a `max_depth` of 0, only the input paths are written.
+pub fn max_depth_total(
+    out: impl io::Write,
+    options: WalkOptions,
+    max_depth: usize,
+    paths: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> Result<WalkResult, Error> {
+    max_depth_total_with(&Filesystem, out, options, max_depth, paths)
+}
+
+fn max_depth_total_with(
+    walk: &impl Walk,
+    mut out: impl io::Write,
+    mut options: WalkOptions,
+    max_depth: usize,
+    paths: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> Result<WalkResult, Error> {
+    let mut res = WalkResult::default();
+    let mut inodes = InodeFilter::default();
+    let mut closed = Vec::new();
+    for path in paths {
+        let path = path.as_ref();
+        let mut open = Vec::<OpenDirectory>::new();
+        for entry in walk.entries(path, &options) {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(_) => {
+                    res.num_errors += 1;
+                    match open.last_mut() {
+                        Some(dir) => dir.aggregate.record_walk_error(false),
+                        None => {
+                            let mut aggregate = PathAggregate::default();
+                            aggregate.record_walk_error(true);
+                            closed.push(ClosedDirectory {
+                                path: path.to_owned(),
+                                depth: 0,
+                                aggregate,
+                                num_collapsed_levels: 0,
+                                kind: PathKind::Other,
+                            });
+                        }
+                    }
+                    continue;
+                }
+            };
+            close_directories_at_or_below(&mut open, &mut closed, entry.depth, max_depth);
+
+            let mut aggregate = PathAggregate::of_file(0, 0, 0);
+            match entry.metadata {
+                Ok(ref m)
+                    if !m.is_dir
+                        && (options.count_hard_links
+                            || m.file_id_and_links
+                                .is_none_or(|(id, nlinks)| inodes.add_inode(id, nlinks))) =>
+                {
+                    let size = if options.apparent_size {
+                        m.apparent_size
+                    } else {
+                        m.disk_size.unwrap_or_else(|_| {
+                            aggregate.num_errors += 1;
+                            0
+                        })
+                    };
+                    let estimated_savings = if options.estimate_compression {
+                        compression::estimated_savings(&entry.path, size).unwrap_or_else(|_| {
+                            aggregate.num_errors += 1;
+                            0
+                        })
+                    } else {
+                        0
+                    };
+                    aggregate.add_sizes(size, m.apparent_size, estimated_savings);
+                }
+                Ok(_) => {}
+                Err(_) => aggregate.num_errors += 1,
+            }
+            res.num_errors += aggregate.num_errors;
+            let is_dir = matches!(entry.metadata, Ok(ref m) if m.is_dir);
+            if entry.depth == 0 || (is_dir && entry.depth <= max_depth) {
+                open.push(OpenDirectory {
+                    path: entry.path,
+                    depth: entry.depth,
+                    aggregate,
+                    deepest: entry.depth,
+                    kind: entry.metadata.as_ref().map_or(PathKind::File, PathKind::of),
+                });
+            } else if let Some(dir) = open.last_mut() {
+                dir.aggregate.add(&aggregate);
+                dir.deepest = dir.deepest.max(entry.depth - usize::from(!is_dir));
+            }
+        }
+        close_directories_at_or_below(&mut open, &mut closed, 0, max_depth);
+    }
+
+    let path_column_width = path_column_width(
+        closed.iter().map(|dir| {
+            dir.subject(&options)
+                .text()
+                .to_string_lossy()
+                .into_owned()
+                .into()
+        }),
+        None,
+    );
+    if let Some(heatmap) = options.heatmap.as_mut() {
+        heatmap.largest = closed
+            .iter()
+            .map(|dir| dir.aggregate.num_bytes)
+            .max()
+            .unwrap_or(0);
+    }
+    if has_header(&options) {
+        write_header(&mut out, &options, path_column_width, SubjectKind::Path)?;
+    }
+    for dir in closed {
+        if dir.depth == 0 {
+            res.total_bytes += dir.aggregate.num_bytes;
+        }
+        write_path(
+            &mut out,
+            &options,
+            path_column_width,
+            dir.subject(&options),
+            &dir.aggregate,
+            path_color(&options, dir.kind, &dir.path),
+        )?;
+    }
+    Ok(res)
+}
+
+impl ClosedDirectory {
+    /// The line written for the directory, with its path as configured by `options`.
+    fn subject<'a>(&'a self, options: &'a WalkOptions) -> Subject<'a> {
+        Subject::Path {
+            path: options.displayed_path(&self.path),
+            depth: self.depth,
+            collapsed_levels: self.num_collapsed_levels,
+        }
+    }
+}
+
+/// Remove all directories in `open` at `depth` or deeper and add them to `closed`, adding their totals to their
+/// parents.
+fn close_directories_at_or_below(
+    open: &mut Vec<OpenDirectory>,
+    closed: &mut Vec<ClosedDirectory>,
+    depth: usize,
+    max_depth: usize,
+) {
+    while open.last().is_some_and(|dir| dir.depth >= depth) {
+        let dir = open.pop().expect("a directory as we just checked");
+        if let Some(parent) = open.last_mut() {
+            parent.aggregate.add(&dir.aggregate);
+            parent.deepest = parent.deepest.max(dir.deepest);
+        }
+        closed.push(ClosedDirectory {
+            path: dir.path,
+            depth: dir.depth,
+            num_collapsed_levels: if dir.depth == max_depth {
+                dir.deepest - dir.depth
+            } else {
+                0
+            },
+            aggregate: dir.aggregate,
+            kind: dir.kind,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::test_options,
+        walk::{EntryMetadata, FakeFilesystem},
+        OutputFormat,
+    };
+
+    fn options() -> WalkOptions {
+        WalkOptions {
+            apparent_size: false,
+            ..test_options()
+        }
+    }
+
+    #[test]
+    fn directories_at_the_maximum_depth_note_the_levels_below_them() -> Result<(), Error> {
+        let file = |disk_size| EntryMetadata {
+            disk_size: Ok(disk_size),
+            ..Default::default()
+        };
+        let fs = FakeFilesystem::default()
+            .dir("root")
+            .file("root/a", file(1))
+            .dir("root/d1")
+            .file("root/d1/f", file(2))
+            .dir("root/d1/d2")
+            .dir("root/d1/d2/d3")
+            .file("root/d1/d2/d3/f", file(4))
+            .unreadable("root/d1/d2/d3/unreadable")
+            .dir("root/empty")
+            .file("other", file(8));
+        let lines = |max_depth| -> Result<(WalkResult, Vec<String>), Error> {
+            let mut out = Vec::new();
+            let res = max_depth_total_with(&fs, &mut out, options(), max_depth, ["root", "other"])?;
+            Ok((
+                res,
+                String::from_utf8(out)?
+                    .lines()
+                    .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+                    .collect(),
+            ))
+        };
+
+        let (res, only_inputs) = lines(0)?;
+        assert_eq!(
+            only_inputs,
+            vec!["7 b root (+3 levels) <1 IO Error>", "8 b other"]
+        );
+        assert_eq!((res.num_errors, res.total_bytes), (1, 15));
+
+        assert_eq!(
+            lines(1)?.1,
+            vec![
+                "6 b root/d1 (+2 levels) <1 IO Error>",
+                "0 b root/empty",
+                "7 b root <1 IO Error>",
+                "8 b other"
+            ],
+            "directories come before their parents, and empty ones have nothing below them"
+        );
+        assert_eq!(
+            lines(3)?.1,
+            vec![
+                "4 b root/d1/d2/d3 <1 IO Error>",
+                "4 b root/d1/d2 <1 IO Error>",
+                "6 b root/d1 <1 IO Error>",
+                "0 b root/empty",
+                "7 b root <1 IO Error>",
+                "8 b other"
+            ],
+            "files below the deepest directories are no level of their own"
+        );
+
+        let mut out = Vec::new();
+        let options = WalkOptions {
+            output_format: OutputFormat::Json,
+            ..options()
+        };
+        max_depth_total_with(&fs, &mut out, options, 1, ["root"])?;
+        assert_eq!(
+            String::from_utf8(out)?,
+            "{\"path\":\"root/d1\",\"depth\":1,\"collapsed_levels\":2,\"bytes\":6,\"entries\":6,\"errors\":1}\n\
+             {\"path\":\"root/empty\",\"depth\":1,\"collapsed_levels\":0,\"bytes\":0,\"entries\":1,\"errors\":0}\n\
+             {\"path\":\"root\",\"depth\":0,\"collapsed_levels\":0,\"bytes\":7,\"entries\":9,\"errors\":1}\n",
+            "the collapsed levels are a field of their own"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,158 @@
+//! Export the tree of a walk in the JSON format of ncdu, to browse it with `ncdu -f FILE`.
+use crate::{
+    json_escaped,
+    traverse::{EntryData, Traversal, Tree, TreeIndex},
+    WalkOptions, WalkResult,
+};
+use failure::Error;
+use itertools::Itertools;
+use petgraph::Direction;
+use std::{
+    io,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The version of the format understood by ncdu 1.9 and later
+const MAJOR_VERSION: u32 = 1;
+const MINOR_VERSION: u32 = 2;
+
+/// Walk the directory at `path` and write its tree to `out` in the JSON format of ncdu. Each directory is an array
+/// starting with the object describing it, followed by its entries, and each file is an object.
+///
+/// Each entry comes with its size on disk as `dsize` and its apparent size as `asize`, so ncdu can show either.
+/// Hard links are only counted once unless `options.count_hard_links` is set, with all further links having no size.
+/// Directories come with the size of the directory itself, as ncdu adds up the sizes of their entries on its own.
+/// Entries whose metadata couldn't be read are marked with `read_error`.
+pub fn export_ncdu(
+    mut out: impl io::Write,
+    options: WalkOptions,
+    path: impl AsRef<Path>,
+) -> Result<WalkResult, Error> {
+    let options = WalkOptions {
+        // the size on disk is kept in the tree, and the apparent size next to it
+        apparent_size: false,
+        ..options
+    };
+    let traversal = Traversal::from_walk(options, vec![path.as_ref().to_owned()], |_| Ok(()))?;
+    let tree = &traversal.tree;
+
+    write!(
+        out,
+        r#"[{},{},{{"progname":"dua","progver":"{}","timestamp":{}}}"#,
+        MAJOR_VERSION,
+        MINOR_VERSION,
+        env!("CARGO_PKG_VERSION"),
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs())
+    )?;
+    for index in children_of(tree, traversal.root_index) {
+        writeln!(out, ",")?;
+        write_entry(&mut out, tree, index)?;
+    }
+    writeln!(out, "]")?;
+
+    Ok(WalkResult {
+        num_errors: traversal.io_errors,
+        total_bytes: traversal.total_bytes.unwrap_or(0),
+        ..Default::default()
+    })
+}
+
+/// Write the entry at `index`, along with everything below it if it is a directory.
+fn write_entry(out: &mut impl io::Write, tree: &Tree, index: TreeIndex) -> Result<(), io::Error> {
+    let entry = &tree[index];
+    if !entry.is_dir {
+        return write_info(out, entry, entry.size);
+    }
+    write!(out, "[")?;
+    write_info(out, entry, entry.own_size)?;
+    for child in children_of(tree, index) {
+        writeln!(out, ",")?;
+        write_entry(out, tree, child)?;
+    }
+    write!(out, "]")
+}
+
+/// Write the object describing `entry` with `disk_size` as its size on disk.
+fn write_info(
+    out: &mut impl io::Write,
+    entry: &EntryData,
+    disk_size: u64,
+) -> Result<(), io::Error> {
+    write!(
+        out,
+        r#"{{"name":"{}","asize":{},"dsize":{}{}}}"#,
+        json_escaped(&entry.name.to_string_lossy()),
+        entry.apparent_size,
+        disk_size,
+        if entry.metadata_io_error {
+            r#","read_error":true"#
+        } else {
+            ""
+        }
+    )
+}
+
+/// The entries of the directory at `index`, by name.
+fn children_of(tree: &Tree, index: TreeIndex) -> Vec<TreeIndex> {
+    tree.neighbors_directed(index, Direction::Outgoing)
+        .sorted_by(|&lhs, &rhs| tree[lhs].name.cmp(&tree[rhs].name))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{test_options, TempDir};
+
+    #[test]
+    fn trees_are_written_as_nested_arrays_with_the_size_dua_counts() -> Result<(), Error> {
+        let dir = TempDir::new("export-ncdu")?;
+        std::fs::create_dir_all(dir.join("sub").join("empty"))?;
+        std::fs::write(dir.join("sub").join("file \"quoted\""), vec![0; 100])?;
+        std::fs::write(dir.join("small"), vec![0; 10])?;
+
+        std::fs::hard_link(dir.join("small"), dir.join("sub").join("link"))?;
+
+        let mut out = Vec::new();
+        let res = export_ncdu(&mut out, test_options(), &dir)?;
+        let out = String::from_utf8(out)?;
+        let lines: Vec<_> = out.lines().collect();
+        assert!(lines[0].starts_with(r#"[1,2,{"progname":"dua","progver":""#));
+        // directories are written with their own size and files with their size on disk, which depend on the
+        // filesystem
+        let is_dir = |line: &str, name: &str, end: &str| {
+            line.starts_with(&format!(r#"[{{"name":"{}","asize":"#, name)) && line.ends_with(end)
+        };
+        let is_file = |line: &str, name: &str, asize: u64, end: &str| {
+            line.starts_with(&format!(
+                r#"{{"name":"{}","asize":{},"dsize":"#,
+                name, asize
+            )) && line.ends_with(end)
+        };
+        assert_eq!(lines.len(), 7);
+        assert!(is_dir(
+            lines[1],
+            &json_escaped(&dir.to_string_lossy()),
+            "},"
+        ));
+        assert!(is_file(lines[2], "small", 10, "},"));
+        assert!(is_dir(lines[3], "sub", "},"));
+        assert!(
+            is_dir(lines[4], "empty", "}],"),
+            "empty directories are arrays as well"
+        );
+        assert!(
+            is_file(lines[5], r#"file \"quoted\""#, 100, "},"),
+            "entries are sorted by name"
+        );
+        assert_eq!(
+            lines[6], r#"{"name":"link","asize":0,"dsize":0}]]]"#,
+            "the second hard link has no size as it was counted already"
+        );
+        assert_eq!(res.num_errors, 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+//! Stream the size of directories as JSON Lines while the walk progresses.
+use crate::{InodeFilter, WalkOptions, WalkResult};
+use failure::Error;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// A directory whose entries are still being walked
+struct OpenDirectory {
+    path: PathBuf,
+    depth: usize,
+    num_bytes: u64,
+    num_entries: u64,
+    num_errors: u64,
+}
+
+impl OpenDirectory {
+    fn write(&self, out: &mut impl io::Write) -> Result<(), io::Error> {
+        writeln!(
+            out,
+            r#"{{"path":"{}","depth":{},"bytes":{},"entries":{},"errors":{}}}"#,
+            json_escaped(&self.path.to_string_lossy()),
+            self.depth,
+            self.num_bytes,
+            self.num_entries,
+            self.num_errors
+        )
+    }
+}
+
+/// Walk the given `paths` and write one JSON object per line to `out` for each of them and each directory below them,
+/// holding its `path`, its `depth` below the input path, the amount of `bytes` and `entries` within it including
+/// itself, and the amount of IO `errors` encountered.
+///
+/// Objects are written as soon as a directory is complete, which is when the walk leaves it. Hence each object comes
+/// after the ones of all directories below it and before the ones of the directories following it, and the object of
+/// an input path is the last one written for it. Input paths are handled in the order they are given.
+pub fn ndjson(
+    mut out: impl io::Write,
+    options: WalkOptions,
+    paths: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> Result<WalkResult, Error> {
+    let mut res = WalkResult::default();
+    let mut inodes = InodeFilter::default();
+    for path in paths {
+        let path = path.as_ref();
+        let mut open = Vec::<OpenDirectory>::new();
+        for entry in options.iter_from_path(path) {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(_) => {
+                    res.num_errors += 1;
+                    if let Some(dir) = open.last_mut() {
+                        dir.num_errors += 1;
+                    }
+                    continue;
+                }
+            };
+            close_directories_at_or_below(&mut out, &mut open, entry.depth)?;
+
+            let mut num_errors = 0;
+            let file_size = match entry.metadata {
+                Some(Ok(ref m)) if !m.is_dir() && (options.count_hard_links || inodes.add(m)) => {
+                    if options.apparent_size {
+                        m.len()
+                    } else {
+                        filesize::file_real_size_fast(entry.path(), m).unwrap_or_else(|_| {
+                            num_errors += 1;
+                            0
+                        })
+                    }
+                }
+                Some(Ok(_)) => 0,
+                Some(Err(_)) => {
+                    num_errors += 1;
+                    0
+                }
+                None => unreachable!(
+                    "we ask for metadata, so we at least have Some(Err(..))). Issue in jwalk?"
+                ),
+            };
+            res.num_errors += num_errors;
+            res.total_bytes += file_size;
+            let is_dir = matches!(entry.metadata, Some(Ok(ref m)) if m.is_dir());
+            if is_dir || entry.depth == 0 {
+                open.push(OpenDirectory {
+                    path: if entry.depth == 0 {
+                        path.to_owned()
+                    } else {
+                        entry.path()
+                    },
+                    depth: entry.depth,
+                    num_bytes: file_size,
+                    num_entries: 1,
+                    num_errors,
+                });
+            } else if let Some(dir) = open.last_mut() {
+                dir.num_bytes += file_size;
+                dir.num_entries += 1;
+                dir.num_errors += num_errors;
+            }
+        }
+        close_directories_at_or_below(&mut out, &mut open, 0)?;
+    }
+    Ok(res)
+}
+
+/// Write and remove all directories in `open` at `depth` or deeper, adding their totals to their parents.
+fn close_directories_at_or_below(
+    out: &mut impl io::Write,
+    open: &mut Vec<OpenDirectory>,
+    depth: usize,
+) -> Result<(), io::Error> {
+    while open.last().is_some_and(|dir| dir.depth >= depth) {
+        let dir = open.pop().expect("a directory as we just checked");
+        dir.write(out)?;
+        if let Some(parent) = open.last_mut() {
+            parent.num_bytes += dir.num_bytes;
+            parent.num_entries += dir.num_entries;
+            parent.num_errors += dir.num_errors;
+        }
+    }
+    Ok(())
+}
+
+/// Escape `s` for use within a JSON string.
+pub fn json_escaped(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{test_options, TempDir};
+    use std::fs;
+
+    #[test]
+    fn directories_are_written_after_everything_below_them() -> Result<(), Error> {
+        let root = TempDir::new("ndjson-test")?;
+        fs::create_dir_all(root.join("a/deep"))?;
+        fs::create_dir_all(root.join("b"))?;
+        fs::write(root.join("a/deep/file"), "12345")?;
+        fs::write(root.join("a/file"), "12")?;
+        fs::write(root.join("b/file"), "1")?;
+        fs::write(root.join("c"), "123")?;
+
+        let mut out = Vec::new();
+        let res = ndjson(&mut out, test_options(), [&root, &root.join("c")]);
+        let res = res?;
+        assert_eq!(res.num_errors, 0);
+        assert_eq!(
+            res.total_bytes, 14,
+            "'c' is counted once for each input path"
+        );
+
+        let root = json_escaped(&root.to_string_lossy());
+        let record = |path: &str, depth, bytes, entries| {
+            format!(
+                r#"{{"path":"{}{}","depth":{},"bytes":{},"entries":{},"errors":0}}"#,
+                root, path, depth, bytes, entries
+            )
+        };
+        assert_eq!(
+            String::from_utf8(out)?.lines().collect::<Vec<_>>(),
+            vec![
+                record("/a/deep", 2, 5, 2),
+                record("/a", 1, 7, 4),
+                record("/b", 1, 1, 2),
+                record("", 0, 11, 8),
+                record("/c", 0, 3, 1),
+            ],
+            "directories are written post-order, followed by the next input path"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn paths_are_escaped_as_json_strings() {
+        assert_eq!(json_escaped(r#"a "b"\c"#), r#"a \"b\"\\c"#);
+        assert_eq!(json_escaped("new\nline\u{1}"), r#"new\nline\u0001"#);
+        assert_eq!(json_escaped("和製"), "和製");
+    }
+}
@@ -1,5 +1,8 @@
-use dua::ByteFormat as LibraryByteFormat;
-use std::path::PathBuf;
+use dua::{
+    parse_palette, ByteFormat as LibraryByteFormat, ColumnOrder, ErrorAnnotation, ErrorClass,
+    FileId, Heatmap, OutputOrder, TimeStyle, TotalPosition,
+};
+use std::{convert::TryFrom, path::PathBuf, str::FromStr, time::Duration};
 use structopt::{clap::arg_enum, StructOpt};
 
 arg_enum! {
@@ -29,6 +32,321 @@ impl From<ByteFormat> for LibraryByteFormat {
     }
 }
 
+#[derive(PartialEq, Debug)]
+pub enum Columns {
+    SizePath,
+    PathSize,
+}
+
+impl FromStr for Columns {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "size-path" => Ok(Columns::SizePath),
+            "path-size" => Ok(Columns::PathSize),
+            _ => Err(format!("valid values: {}", Columns::VARIANTS.join(", "))),
+        }
+    }
+}
+
+impl Columns {
+    pub const VARIANTS: &'static [&'static str] = &["size-path", "path-size"];
+}
+
+impl From<Columns> for ColumnOrder {
+    fn from(input: Columns) -> Self {
+        match input {
+            Columns::SizePath => ColumnOrder::SizePath,
+            Columns::PathSize => ColumnOrder::PathSize,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum ErrorAnnotations {
+    Human,
+    Field,
+    None,
+}
+
+impl FromStr for ErrorAnnotations {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(ErrorAnnotations::Human),
+            "field" => Ok(ErrorAnnotations::Field),
+            "none" => Ok(ErrorAnnotations::None),
+            _ => Err(format!(
+                "valid values: {}",
+                ErrorAnnotations::VARIANTS.join(", ")
+            )),
+        }
+    }
+}
+
+impl ErrorAnnotations {
+    pub const VARIANTS: &'static [&'static str] = &["human", "field", "none"];
+}
+
+impl From<ErrorAnnotations> for ErrorAnnotation {
+    fn from(input: ErrorAnnotations) -> Self {
+        match input {
+            ErrorAnnotations::Human => ErrorAnnotation::Human,
+            ErrorAnnotations::Field => ErrorAnnotation::Field,
+            ErrorAnnotations::None => ErrorAnnotation::None,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum ErrorKinds {
+    PermissionDenied,
+    NotFound,
+    OutOfMemory,
+    Hardware,
+    Other,
+}
+
+impl FromStr for ErrorKinds {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "permission-denied" => Ok(ErrorKinds::PermissionDenied),
+            "not-found" => Ok(ErrorKinds::NotFound),
+            "out-of-memory" => Ok(ErrorKinds::OutOfMemory),
+            "hardware" => Ok(ErrorKinds::Hardware),
+            "other" => Ok(ErrorKinds::Other),
+            _ => Err(format!("valid values: {}", ErrorKinds::VARIANTS.join(", "))),
+        }
+    }
+}
+
+impl ErrorKinds {
+    pub const VARIANTS: &'static [&'static str] = &[
+        "permission-denied",
+        "not-found",
+        "out-of-memory",
+        "hardware",
+        "other",
+    ];
+}
+
+impl From<ErrorKinds> for ErrorClass {
+    fn from(input: ErrorKinds) -> Self {
+        match input {
+            ErrorKinds::PermissionDenied => ErrorClass::PermissionDenied,
+            ErrorKinds::NotFound => ErrorClass::NotFound,
+            ErrorKinds::OutOfMemory => ErrorClass::OutOfMemory,
+            ErrorKinds::Hardware => ErrorClass::Hardware,
+            ErrorKinds::Other => ErrorClass::Other,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum TimeStyles {
+    Iso,
+    Relative,
+    Locale,
+}
+
+impl FromStr for TimeStyles {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "iso" => Ok(TimeStyles::Iso),
+            "relative" => Ok(TimeStyles::Relative),
+            "locale" => Ok(TimeStyles::Locale),
+            _ => Err(format!("valid values: {}", TimeStyles::VARIANTS.join(", "))),
+        }
+    }
+}
+
+impl TimeStyles {
+    pub const VARIANTS: &'static [&'static str] = &["iso", "relative", "locale"];
+}
+
+impl From<TimeStyles> for TimeStyle {
+    fn from(input: TimeStyles) -> Self {
+        match input {
+            TimeStyles::Iso => TimeStyle::Iso,
+            TimeStyles::Relative => TimeStyle::Relative,
+            TimeStyles::Locale => TimeStyle::Locale,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum TotalPositions {
+    First,
+    Last,
+}
+
+impl FromStr for TotalPositions {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "first" => Ok(TotalPositions::First),
+            "last" => Ok(TotalPositions::Last),
+            _ => Err(format!(
+                "valid values: {}",
+                TotalPositions::VARIANTS.join(", ")
+            )),
+        }
+    }
+}
+
+impl TotalPositions {
+    pub const VARIANTS: &'static [&'static str] = &["first", "last"];
+}
+
+impl From<TotalPositions> for TotalPosition {
+    fn from(input: TotalPositions) -> Self {
+        match input {
+            TotalPositions::First => TotalPosition::First,
+            TotalPositions::Last => TotalPosition::Last,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum SortBy {
+    SizeAsc,
+    SizeDesc,
+    Name,
+    Count,
+}
+
+impl FromStr for SortBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "size-asc" => Ok(SortBy::SizeAsc),
+            "size-desc" => Ok(SortBy::SizeDesc),
+            "name" => Ok(SortBy::Name),
+            "count" => Ok(SortBy::Count),
+            _ => Err(format!("valid values: {}", SortBy::VARIANTS.join(", "))),
+        }
+    }
+}
+
+impl SortBy {
+    pub const VARIANTS: &'static [&'static str] = &["size-asc", "size-desc", "name", "count"];
+}
+
+impl From<SortBy> for OutputOrder {
+    fn from(input: SortBy) -> Self {
+        match input {
+            SortBy::SizeAsc => OutputOrder::SizeAscending,
+            SortBy::SizeDesc => OutputOrder::SizeDescending,
+            SortBy::Name => OutputOrder::PathAscending,
+            SortBy::Count => OutputOrder::EntryCountAscending,
+        }
+    }
+}
+
+/// The largest amount of threads we allow, as more of them only compete for the same disks
+pub const MAX_THREADS: usize = 256;
+
+/// Parse the amount of threads to walk with, which is at least 1 and at most [`MAX_THREADS`].
+/// 0 is rejected as it's unclear whether it means 'none' or 'automatic', which is what leaving it unset does.
+pub fn parse_threads(threads: &str) -> Result<usize, String> {
+    match threads.parse::<usize>() {
+        Ok(n) if (1..=MAX_THREADS).contains(&n) => Ok(n),
+        Ok(0) => Err(format!(
+            "invalid amount of threads '0', expected 1 to {}. Leave it unset to use one per logical processor",
+            MAX_THREADS
+        )),
+        _ => Err(format!(
+            "invalid amount of threads '{}', expected 1 to {}",
+            threads, MAX_THREADS
+        )),
+    }
+}
+
+/// Parse the fraction of directories to walk when sampling, which is more than 0 and at most 1.
+pub fn parse_fraction(fraction: &str) -> Result<f64, String> {
+    match fraction.parse::<f64>() {
+        Ok(f) if f > 0.0 && f <= 1.0 => Ok(f),
+        _ => Err(format!(
+            "invalid fraction '{}', expected a number larger than 0 and at most 1, like '0.1'",
+            fraction
+        )),
+    }
+}
+
+/// Parse the colors of a heatmap like 'green,yellow,red'.
+pub fn parse_heatmap(palette: &str) -> Result<Heatmap, String> {
+    parse_palette(palette).map(Heatmap::new)
+}
+
+/// Parse a size like '1GB', '500 MiB' or '1024' into bytes.
+pub fn parse_size(size: &str) -> Result<u64, String> {
+    byte_unit::Byte::from_str(size)
+        .ok()
+        .and_then(|bytes| u64::try_from(bytes.get_bytes()).ok())
+        .ok_or_else(|| {
+            format!(
+                "invalid size '{}', expected a number optionally followed by a unit like 'MB' or 'GiB'",
+                size
+            )
+        })
+}
+
+/// Parse an interval like '500ms', '5s', '2m' or '1h', or a plain number of seconds like '10'.
+pub fn parse_interval(interval: &str) -> Result<Duration, String> {
+    let (number, unit) = interval.split_at(
+        interval
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(interval.len()),
+    );
+    let millis_per_unit = match unit {
+        "ms" => 1,
+        "" | "s" => 1000,
+        "m" => 60 * 1000,
+        "h" => 60 * 60 * 1000,
+        _ => 0,
+    };
+    match number.parse::<u64>() {
+        Ok(n) if n > 0 && millis_per_unit > 0 => Ok(Duration::from_millis(
+            n.saturating_mul(millis_per_unit),
+        )),
+        _ => Err(format!(
+            "invalid interval '{}', expected a number larger than 0 optionally followed by 'ms', 's', 'm' or 'h'",
+            interval
+        )),
+    }
+}
+
+/// Parse the single character separating comma-separated values, with '\t' standing for a tab.
+pub fn parse_delimiter(delimiter: &str) -> Result<char, String> {
+    let mut chars = delimiter.chars();
+    match (delimiter, chars.next(), chars.next()) {
+        ("\\t", _, _) => Ok('\t'),
+        (_, Some(c), None) if !matches!(c, '"' | '\n' | '\r') => Ok(c),
+        _ => Err(format!(
+            "invalid delimiter '{}', expected a single character other than a quote or a line break",
+            delimiter
+        )),
+    }
+}
+
+/// Parse a label like 'Photos:/mnt/media/photos' into the path and its label.
+pub fn parse_label(label: &str) -> Result<(PathBuf, String), String> {
+    match label.split_once(':') {
+        Some((name, path)) if !name.is_empty() && !path.is_empty() => {
+            Ok((PathBuf::from(path), name.to_owned()))
+        }
+        _ => Err(format!("invalid label '{}', expected NAME:PATH", label)),
+    }
+}
+
 #[derive(Debug, StructOpt)]
 #[structopt(name = "dua", about = "A tool to learn about disk usage, fast!")]
 #[structopt(setting = structopt::clap::AppSettings::ColoredHelp)]
@@ -36,9 +354,25 @@ pub struct Args {
     #[structopt(subcommand)]
     pub command: Option<Command>,
 
-    /// The amount of threads to use. Defaults to the amount of logical processors.
-    /// Set to 1 to use only a single thread.
-    #[structopt(short = "t", long = "threads")]
+    /// The configuration file with defaults for options and key bindings of the interactive mode.
+    /// Defaults to '$XDG_CONFIG_HOME/dua/config.toml' or '~/.config/dua/config.toml', which is ignored if it doesn't exist.
+    #[structopt(long, parse(from_os_str))]
+    pub config: Option<PathBuf>,
+
+    /// Write the results to the given file instead of stdout, replacing its content. Colors are never used then.
+    /// Not available in interactive mode.
+    #[structopt(short = "o", long, parse(from_os_str))]
+    pub output: Option<PathBuf>,
+
+    /// Log what the walk is doing to stderr, like the time each input path took. Pass it twice to also log
+    /// excluded paths and symbolic links, and three times for even more detail. Without it, the level named by
+    /// RUST_LOG like 'debug' is used, and nothing is logged by default.
+    #[structopt(short = "v", long = "verbose", parse(from_occurrences))]
+    pub verbosity: u64,
+
+    /// The amount of threads to use, from 1 to 256. Defaults to DUA_THREADS if set, then to the 'threads' of the
+    /// configuration file, then to the amount of logical processors. Set to 1 to use only a single thread.
+    #[structopt(short = "t", long = "threads", parse(try_from_str = parse_threads))]
     pub threads: Option<usize>,
 
     /// The format with which to print byte counts.
@@ -49,27 +383,270 @@ pub struct Args {
     /// GiB - only gibibytes
     /// MB - only megabytes
     /// MiB - only mebibytes
+    /// Defaults to DUA_FORMAT if set, then to the 'format' of the configuration file.
     #[structopt(short = "f", long)]
     pub format: Option<ByteFormat>,
 
     /// Display apparent size instead of disk usage.
+    /// Disk usage counts the blocks shared by files cloned with `cp --reflink` or in snapshots of copy-on-write
+    /// filesystems like btrfs, XFS or APFS once for each clone, so totals can exceed the space actually used.
     #[structopt(short = "A", long)]
     pub apparent_size: bool,
 
+    /// Display apparent sizes as plain bytes, like 'du -b' does. Equivalent to '--apparent-size --format bytes'.
+    #[structopt(short = "b", long, conflicts_with_all = &["format", "both-sizes"])]
+    pub bytes: bool,
+
+    /// Display the apparent size next to the disk usage, with a header naming each column.
+    /// Sorting is performed by disk usage.
+    #[structopt(long, conflicts_with = "apparent-size")]
+    pub both_sizes: bool,
+
+    /// Display all sizes in the same unit, the one most suitable for the largest of them.
+    /// Only affects the Metric and Binary formats.
+    #[structopt(long)]
+    pub shared_unit: bool,
+
+    /// Color sizes by their magnitude compared to the largest size written, from green for small ones over yellow to red
+    /// for the largest ones, instead of coloring all of them green.
+    #[structopt(long)]
+    pub heatmap: bool,
+
+    /// The colors of the heatmap from small to large sizes, like 'green,yellow,light-red' or 'blue,208', using the names
+    /// of the 16 colors of the terminal or ANSI values from 0 to 255. Implies --heatmap.
+    #[structopt(long, value_name = "COLORS", parse(try_from_str = parse_heatmap))]
+    pub heatmap_palette: Option<Heatmap>,
+
+    /// Color paths like 'ls' does according to the LS_COLORS environment variable, by the kind of entry and the end
+    /// of the names of files, instead of dimming files. Has no effect if LS_COLORS is unset or colors aren't used.
+    #[structopt(long)]
+    pub ls_colors: bool,
+
+    /// Display the amount of files and directories next to the size, with a header naming each column.
+    /// Useful when a filesystem runs out of inodes rather than space.
+    #[structopt(long)]
+    pub entry_count: bool,
+
+    /// Display an estimate of the space to be saved by compressing files of 1MB or more, with a header naming each
+    /// column. The estimate is based on compressing the first 64KB of each of these files.
+    #[structopt(long)]
+    pub estimate_compression: bool,
+
+    /// Write the exact amount of bytes in parentheses after each formatted size, including the one of the total.
+    #[structopt(long)]
+    pub verbose_size: bool,
+
+    /// Group the digits of byte counts in the 'bytes' format and of entry counts by thousands, using the separator
+    /// of the locale configured by LC_ALL, LC_NUMERIC or LANG. Digits are not grouped if the locale is unknown.
+    #[structopt(long)]
+    pub group_digits: bool,
+
+    /// Skip entries matching the given pattern along with everything below them. Can be given multiple times.
+    /// Patterns without '/' match names, like '*.log', others match the trailing components of paths, like
+    /// 'target/debug'. '*' and '?' don't match '/', while '**' does. Paths given on the command-line are never skipped.
+    #[structopt(long = "exclude", number_of_values = 1)]
+    pub exclude: Vec<String>,
+
+    /// Skip entries matching any of the patterns in the given file, one per line, in addition to the ones given
+    /// with --exclude. Empty lines and lines starting with '#' are ignored. Can be given multiple times.
+    #[structopt(long = "exclude-from", number_of_values = 1, parse(from_os_str))]
+    pub exclude_from: Vec<PathBuf>,
+
+    /// Skip directories containing a 'CACHEDIR.TAG' file with the standard signature along with everything below
+    /// them, like 'tar --exclude-caches-all' does. Paths given on the command-line are never skipped.
+    #[structopt(long)]
+    pub exclude_caches: bool,
+
+    /// Stop walking once the given time like '30s' or '5m' has passed, and print the sizes seen until then, which are
+    /// too small. Input paths which weren't reached are left out. The exit code is 3 in that case. Only the sizes of
+    /// paths can be limited this way, so it's an error with the other modes of the aggregate subcommand or the
+    /// interactive mode.
+    #[structopt(long, value_name = "DURATION", parse(try_from_str = parse_interval))]
+    pub max_time: Option<Duration>,
+
+    /// Don't count files larger than the given size like '4GB' in the sizes of aggregated paths. Unlike
+    /// excluded entries, they are still visited and counted as entries.
+    #[structopt(long, value_name = "SIZE", parse(try_from_str = parse_size))]
+    pub exclude_larger_than: Option<u64>,
+
+    /// Don't count files smaller than the given size like '1MB' in the sizes of aggregated paths. Together with
+    /// --exclude-larger-than, only files within a band of sizes are counted.
+    #[structopt(long, value_name = "SIZE", parse(try_from_str = parse_size))]
+    pub exclude_smaller_than: Option<u64>,
+
+    /// End the walk with an error on the first IO error of the given kind, instead of counting it like all others.
+    /// 'hardware' errors like EIO and 'out-of-memory' errors may indicate a problem with the system, whereas
+    /// 'permission-denied' and 'not-found' errors are expected when walking directories of others or ones which change.
+    /// Can be given multiple times. Only applies to the sizes of paths, so it's an error with the other modes of the
+    /// aggregate subcommand or the interactive mode.
+    #[structopt(long = "fatal-error", value_name = "KIND", number_of_values = 1, possible_values = ErrorKinds::VARIANTS)]
+    pub fatal_errors: Vec<ErrorKinds>,
+
+    /// Follow symbolic links given as input paths, but none below them, which is the default. Each link below is
+    /// counted with the size of the link itself.
+    #[structopt(
+        long,
+        conflicts_with_all = &["no-follow-root-symlinks", "follow-dir-symlinks", "follow-file-symlinks"]
+    )]
+    pub follow_root_symlink_only: bool,
+
+    /// Count input paths which are symbolic links with the size of the link itself, instead of walking what they
+    /// point to.
+    #[structopt(long)]
+    pub no_follow_root_symlinks: bool,
+
+    /// Walk the directories symbolic links below the input paths point to, instead of counting the size of the link.
+    /// Links to a directory containing them are not followed to avoid walking in circles.
+    #[structopt(long)]
+    pub follow_dir_symlinks: bool,
+
+    /// Count symbolic links to files below the input paths with the size of the file they point to, instead of the
+    /// size of the link. Files linked to from multiple places are counted each time.
+    #[structopt(long)]
+    pub follow_file_symlinks: bool,
+
+    /// Display the given name instead of an input path, written as NAME:PATH like 'Photos:/mnt/media/photos'.
+    /// PATH has to be given exactly like the input path. Can be given multiple times.
+    #[structopt(long = "label", value_name = "NAME:PATH", number_of_values = 1, parse(try_from_str = parse_label))]
+    pub labels: Vec<(PathBuf, String)>,
+
+    /// Display paths below BASE relative to it, like 'photos/2020' for '/mnt/media/photos/2020' with '/mnt/media',
+    /// to produce output which doesn't depend on where the paths are mounted. Other paths are displayed unchanged.
+    #[structopt(long, value_name = "BASE", parse(from_os_str))]
+    pub relative_to: Option<PathBuf>,
+
+    /// The order of the columns of each line.
+    /// size-path - the size followed by the path, like `du` does (default)
+    /// path-size - the path followed by the size, with paths padded to keep the sizes aligned
+    #[structopt(long, default_value = "size-path", possible_values = Columns::VARIANTS)]
+    pub columns: Columns,
+
+    /// How to note IO errors encountered while walking a path, at the end of its line.
+    /// human - a description like '<2 IO Errors>' if there were errors, noting the amount of paths with errors on
+    /// the total like '<2 IO Errors across 1 path>' (default)
+    /// field - the amount of errors on every line, separated from the rest of the line by a tab
+    /// none - nothing, the exit code still indicates errors
+    #[structopt(long, default_value = "human", possible_values = ErrorAnnotations::VARIANTS)]
+    pub error_annotation: ErrorAnnotations,
+
+    /// How to display points in time, like modification times.
+    /// iso - ISO-8601 in UTC like '2020-02-25T10:31:02Z', stable for use in scripts (default)
+    /// relative - the time passed since then, like '2h ago'
+    /// locale - date and time in UTC, with the date written as the locale in LC_ALL, LC_TIME or LANG does
+    #[structopt(long, default_value = "iso", possible_values = TimeStyles::VARIANTS)]
+    pub time_style: TimeStyles,
+
+    /// Behave like `du -s`: write a single line for each input path, in the order given, without a total.
+    /// Without input paths, the current working directory is summarized as a whole.
+    /// `dua -s dir` is equivalent to `dua aggregate --no-sort --no-total dir`.
+    #[structopt(short = "s", long)]
+    pub summarize: bool,
+
+    /// Fail if no input paths are given instead of using the entries of the current working directory, or the directory
+    /// itself when summarizing. Useful in scripts whose list of paths may turn out empty.
+    #[structopt(long)]
+    pub no_implicit_cwd: bool,
+
+    /// Print nothing but check whether the total size of all input paths exceeds SIZE, like '500GB', for use in
+    /// monitoring. Exits with 2 if it does, even if errors occurred as they can only hide more space, and otherwise
+    /// with 1 if there were errors or with 0. Not available with subcommands.
+    #[structopt(long, value_name = "SIZE", parse(try_from_str = parse_size), conflicts_with = "output")]
+    pub fail_over: Option<u64>,
+
+    /// Write only the size, without path and without newline, like '1.26 MB' for embedding it into shell prompts.
+    /// Meant for a single path, as the total of all input paths is written otherwise. Not available with subcommands.
+    #[structopt(long, conflicts_with = "fail-over")]
+    pub oneline: bool,
+
+    /// Write one JSON object per line for each input path like
+    /// '{"path":"a","depth":0,"collapsed_levels":0,"bytes":4096,"entries":1,"errors":0}', followed by
+    /// '{"total":{"bytes":4096,"entries":1,"errors":0}}' if the total is written. With --by-owner, each object names
+    /// its '"owner"' instead of a path and depth. Sizes are in bytes regardless of --format.
+    /// Only the sizes of paths are written this way, not the other modes of the aggregate subcommand.
+    #[structopt(long, conflicts_with = "oneline")]
+    pub json: bool,
+
+    /// Write a row of comma-separated values for each input path like 'a,0,0,4096,1,0', headed by
+    /// 'path,depth,collapsed_levels,bytes,entries,errors' and followed by a row for the total if it is written. With
+    /// --by-owner, the header is 'owner,bytes,entries,errors'. Sizes are in bytes regardless of --format. Paths containing the
+    /// delimiter, quotes or line breaks are quoted. Only the sizes of paths are written this way, not the other modes of
+    /// the aggregate subcommand.
+    #[structopt(long, conflicts_with_all = &["oneline", "json"])]
+    pub csv: bool,
+
+    /// The character separating the values of --csv instead of a comma, like ';' or '\t' for a tab
+    #[structopt(long, value_name = "CHAR", requires = "csv", parse(try_from_str = parse_delimiter))]
+    pub delimiter: Option<char>,
+
+    /// Clear the screen and write the sizes of all input paths again every INTERVAL, like '500ms', '5s' or '2m',
+    /// each time headed by the time the walk started. Runs until interrupted with Ctrl-C.
+    /// Not available with subcommands.
+    #[structopt(long, value_name = "INTERVAL", parse(try_from_str = parse_interval), conflicts_with_all = &["fail-over", "oneline", "output"])]
+    pub watch: Option<Duration>,
+
+    /// Expand input paths containing '*', '?' or '[...]' into the paths they match, like shells on Unix do, warning
+    /// about patterns which match nothing. Always done on Windows, whose shells pass patterns on as they are.
+    #[structopt(long)]
+    pub expand_globs: bool,
+
+    /// Exit with 0 even if entries could not be read, instead of 1. Errors are still counted and annotated as usual,
+    /// and part of the statistics.
+    #[structopt(long)]
+    pub ignore_errors: bool,
+
     /// Count hard-linked files each time they are seen
     #[structopt(short = "l", long)]
     pub count_hard_links: bool,
 
+    /// Print the optional capabilities of this build as JSON object, for use by programs wrapping dua, and exit.
+    #[structopt(long, hidden = true)]
+    pub print_capabilities: bool,
+
     /// One or more input files or directories. If unset, we will use all entries in the current working directory.
     #[structopt(parse(from_os_str))]
     pub input: Vec<PathBuf>,
 }
 
+impl Args {
+    /// The order of aggregated paths implied by the command and its flags
+    pub fn output_order(&self) -> OutputOrder {
+        match self.command {
+            Some(Command::Interactive { .. }) | Some(Command::Aggregate { no_sort: true, .. }) => {
+                OutputOrder::Unsorted
+            }
+            Some(Command::Aggregate {
+                sort_by: Some(sort_by),
+                ..
+            }) => sort_by.into(),
+            _ if self.summarize => OutputOrder::Unsorted,
+            _ => OutputOrder::SizeAscending,
+        }
+    }
+}
+
 #[derive(Debug, StructOpt)]
 pub enum Command {
     /// Launch the terminal user interface
     #[structopt(name = "interactive", alias = "i")]
     Interactive {
+        /// The string to show in place of the parts of paths which don't fit, like '...' for terminals without unicode support
+        #[structopt(long = "ellipsis", default_value = "…")]
+        ellipsis: String,
+        /// The amount of marked paths to show when asking for confirmation before deleting them
+        #[structopt(long = "paths-to-confirm", default_value = "5")]
+        paths_to_confirm: usize,
+        /// Highlight the size of entries larger than the given size, like '1GB' or '500MiB'.
+        /// Defaults to the 'large-entry-size' of the configuration file, if set.
+        #[structopt(long = "large-entry-size", parse(try_from_str = parse_size))]
+        large_entry_size: Option<u64>,
+        /// Once the application is closed, write a JSON object to stdout listing the entries which are still marked
+        /// and the ones which were deleted along with their size, as well as the amount of bytes freed by deleting them.
+        #[structopt(long)]
+        report_on_exit: bool,
+        /// Quit right away even if entries are marked, instead of asking whether to discard them.
+        /// Never asked with --report-on-exit, as the marked entries are part of the report.
+        #[structopt(long)]
+        no_confirm_quit: bool,
         /// One or more input files or directories. If unset, we will use all entries in the current working directory.
         #[structopt(parse(from_os_str))]
         input: Vec<PathBuf>,
@@ -80,15 +657,158 @@ pub enum Command {
         /// If set, print additional statistics about the file traversal to stderr
         #[structopt(long = "stats")]
         statistics: bool,
+        /// If set, the statistics also contain the time it took to walk each input path, the slowest first,
+        /// to find the ones slowing down the walk like network mounts.
+        #[structopt(long, requires = "statistics")]
+        time_per_root: bool,
         /// If set, paths will be printed in their order of occurrence on the command-line.
-        /// Otherwise they are sorted by their size in bytes, ascending.
+        /// Otherwise they are sorted by their size in bytes, ascending, or as given by --sort-by.
         #[structopt(long)]
         no_sort: bool,
+        /// The order in which to print paths, with paths in alphabetical order if they are equal otherwise.
+        /// size-asc - by their size, the largest last near the prompt (default)
+        /// size-desc - by their size, the largest first
+        /// name - by their path, in alphabetical order
+        /// count - by the amount of files and directories within them, ascending
+        #[structopt(long, value_name = "ORDER", possible_values = SortBy::VARIANTS, conflicts_with = "no-sort")]
+        sort_by: Option<SortBy>,
         /// If set, no total column will be computed for multiple inputs
         #[structopt(long)]
         no_total: bool,
+        /// The label of the line with the total size, 'total' by default
+        #[structopt(long, conflicts_with = "no-total")]
+        total_label: Option<String>,
+        /// If set, the label of the line with the total size is written in bold
+        #[structopt(long, conflicts_with = "no-total")]
+        bold_total: bool,
+        /// If set, the share of the total of the largest input paths is written after the label of the total line,
+        /// like 'total (a 60%, b 30%, 2 more 10%)'. It is left out with '--error-annotation field'.
+        #[structopt(long, conflicts_with = "no-total")]
+        total_shares: bool,
+        /// Where to write the line with the total size.
+        /// first - before all paths, right after the header if there is one
+        /// last - after all paths (default)
+        #[structopt(long, possible_values = TotalPositions::VARIANTS, conflicts_with = "no-total")]
+        total: Option<TotalPositions>,
+        /// If set, only the line with the total size across all input paths is written, even for a single path
+        #[structopt(long, conflicts_with_all = &["no-total", "total", "biggest", "by-owner", "rollup", "skip-empty"])]
+        total_only: bool,
+        /// If set, only the largest of all input paths will be printed, without total
+        #[structopt(long, conflicts_with = "no-sort")]
+        biggest: bool,
+        /// If set, only the given amount of largest input paths will be printed, or of files with --files-only
+        #[structopt(long, value_name = "N", conflicts_with_all = &["no-sort", "biggest"])]
+        top: Option<usize>,
+        /// If set, print the largest individual files within all input paths instead of the paths themselves,
+        /// ascending by size. Requires --top to limit their amount.
+        #[structopt(long, requires = "top", conflicts_with_all = &["statistics", "no-total", "total-label", "bold-total", "total", "total-only", "by-owner", "rollup", "skip-empty", "ndjson", "find-inode"])]
+        files_only: bool,
+        /// If set, print the space used by each owner of the files within all input paths instead of the
+        /// paths themselves, sorted descending by size. Only supported on Unix.
+        #[structopt(long, conflicts_with = "biggest")]
+        by_owner: bool,
+        /// If set, print each directory directly within the input paths with the space used by everything below it
+        /// instead of the paths themselves, along with '<path>/(files)' for the files directly within them, sorted
+        /// descending by size.
+        #[structopt(long, conflicts_with_all = &["biggest", "by-owner"])]
+        rollup: bool,
+        /// If set, input paths which consume no space aren't printed, but are still counted in the total
+        #[structopt(long)]
+        skip_empty: bool,
+        /// If set, write one JSON object per line for each input path and each directory below it as soon as its
+        /// size is known, which is after all directories below it were written.
+        #[structopt(long, conflicts_with_all = &["statistics", "biggest", "by-owner", "rollup", "no-total", "total-label", "bold-total", "total-only"])]
+        ndjson: bool,
+        /// If set, print every path referring to the file with the given device and inode, like '2049:131' as printed
+        /// by 'stat -c %d:%i FILE', followed by the size they share. Only supported on Unix.
+        #[structopt(long, conflicts_with_all = &["statistics", "biggest", "by-owner", "rollup", "no-total", "total-label", "bold-total", "total-only", "ndjson"])]
+        find_inode: Option<FileId>,
+        /// If set, estimate the size of each input path by walking only the given fraction of the directories within
+        /// each directory, like '0.1', picked at random. Sizes are approximate and written as '~SIZE ±MARGIN' with the
+        /// margin of error at a confidence of 95%, and the amount of directories walked and skipped goes to stderr.
+        #[structopt(long, value_name = "FRACTION", parse(try_from_str = parse_fraction), conflicts_with_all = &["statistics", "no-sort", "no-total", "total-label", "bold-total", "total", "total-only", "biggest", "top", "by-owner", "rollup", "skip-empty", "ndjson", "find-inode"])]
+        sample: Option<f64>,
+        /// The seed of the random choice of directories with --sample, to pick the same ones again. Random by default.
+        #[structopt(long, value_name = "SEED", requires = "sample")]
+        sample_seed: Option<u64>,
+        /// If set, print each directory down to the given depth below the input paths along with the input paths
+        /// themselves, like 'du --max-depth'. Directories come before their parents, and sizes include everything
+        /// below them. Directories at the given depth note how many levels of directories they contain, like
+        /// 'dir (+3 levels)'.
+        #[structopt(long, value_name = "DEPTH", conflicts_with_all = &["statistics", "no-sort", "no-total", "total-label", "bold-total", "total", "total-only", "biggest", "top", "by-owner", "rollup", "skip-empty", "ndjson", "find-inode", "sample"])]
+        max_depth_total: Option<usize>,
+        /// If set, write the tree of all input paths to the given file as a graph in the DOT language of Graphviz
+        /// instead of printing their sizes, with a node labelled with the name and size of each entry and an edge from
+        /// each directory to each of its entries.
+        #[structopt(long, value_name = "FILE", parse(from_os_str), conflicts_with_all = &["statistics", "no-sort", "no-total", "total-label", "bold-total", "total", "total-only", "biggest", "top", "by-owner", "rollup", "skip-empty", "ndjson", "find-inode", "sample", "max-depth-total"])]
+        export_dot: Option<PathBuf>,
+        /// Leave entries deeper than the given depth below the input paths out of the graph of --export-dot
+        #[structopt(long, value_name = "DEPTH", requires = "export-dot")]
+        dot_max_depth: Option<usize>,
+        /// Leave entries smaller than the given size like '1GB' out of the graph of --export-dot, along with
+        /// everything below them
+        #[structopt(long, value_name = "SIZE", parse(try_from_str = parse_size), requires = "export-dot")]
+        dot_min_size: Option<u64>,
+        /// If set, print groups of files with identical contents which aren't hard links of each other, along with
+        /// the space reclaimable by keeping only one file of each group, the largest amount first. Only files sharing
+        /// their length with another one are read, to compare their contents.
+        #[structopt(long, conflicts_with_all = &["statistics", "no-sort", "no-total", "total-label", "bold-total", "total", "total-only", "biggest", "top", "by-owner", "rollup", "skip-empty", "ndjson", "find-inode", "sample", "max-depth-total", "export-dot"])]
+        find_duplicates: bool,
+        /// If set, write the tree of the input directory to the given file in the JSON format of ncdu instead of
+        /// printing its size, to browse it later with 'ncdu -f FILE'. Both the apparent size and the disk usage are
+        /// written, counting hard links once. Walks the current directory without input paths, and requires a single
+        /// one otherwise.
+        #[structopt(long, value_name = "FILE", parse(from_os_str), conflicts_with_all = &["statistics", "no-sort", "no-total", "total-label", "bold-total", "total", "total-only", "biggest", "top", "by-owner", "rollup", "skip-empty", "ndjson", "find-inode", "sample", "max-depth-total", "export-dot", "find-duplicates"])]
+        export_ncdu: Option<PathBuf>,
         /// One or more input files or directories. If unset, we will use all entries in the current working directory.
         #[structopt(parse(from_os_str))]
         input: Vec<PathBuf>,
     },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thread_counts_are_limited_to_a_sane_range() {
+        assert_eq!(parse_threads("1"), Ok(1));
+        assert_eq!(parse_threads("256"), Ok(MAX_THREADS));
+        assert!(
+            parse_threads("0").unwrap_err().contains("Leave it unset"),
+            "0 is ambiguous"
+        );
+        for invalid in &["257", "-1", "", "four", "18446744073709551616"] {
+            assert_eq!(
+                parse_threads(invalid),
+                Err(format!(
+                    "invalid amount of threads '{}', expected 1 to 256",
+                    invalid
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn delimiters_are_single_characters_which_do_not_need_quoting() {
+        assert_eq!(parse_delimiter(","), Ok(','));
+        assert_eq!(parse_delimiter(";"), Ok(';'));
+        assert_eq!(parse_delimiter("\\t"), Ok('\t'));
+        assert_eq!(parse_delimiter("\t"), Ok('\t'));
+        for invalid in &["", ",,", "\"", "\n", "tab"] {
+            assert!(parse_delimiter(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn intervals_are_read_with_an_optional_unit() {
+        assert_eq!(parse_interval("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_interval("5s"), Ok(Duration::from_secs(5)));
+        assert_eq!(parse_interval("10"), Ok(Duration::from_secs(10)));
+        assert_eq!(parse_interval("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_interval("1h"), Ok(Duration::from_secs(3600)));
+        for invalid in &["0s", "", "s", "5 s", "5d", "-1", "1.5s"] {
+            assert!(parse_interval(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
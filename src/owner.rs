@@ -0,0 +1,50 @@
+//! Obtain the owners of files and their names, to be able to aggregate disk usage per user.
+use std::{collections::HashMap, fs};
+
+/// The user id of the owner of the file described by `metadata`, if available on this platform.
+#[cfg(all(unix, feature = "by-owner"))]
+pub fn uid_of(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.uid())
+}
+
+#[cfg(not(all(unix, feature = "by-owner")))]
+pub fn uid_of(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+/// Read the names of all users from the local password database.
+/// Users we can't resolve this way, like the ones provided by a directory service, will have no name.
+pub fn user_names() -> HashMap<u32, String> {
+    fs::read_to_string("/etc/passwd")
+        .map(|content| parse_passwd(&content))
+        .unwrap_or_default()
+}
+
+fn parse_passwd(content: &str) -> HashMap<u32, String> {
+    content
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let uid = fields.nth(1)?.parse().ok()?;
+            Some((uid, name.to_owned()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_user_names_and_ignores_malformed_lines() {
+        let names = parse_passwd(
+            "# comment\nroot:x:0:0:root:/root:/bin/bash\nbroken\nnobody:x:nan:0::/:\nbyron:*:501:20::/Users/byron:/bin/zsh\n",
+        );
+        assert_eq!(names.len(), 2);
+        assert_eq!(names.get(&0).map(String::as_str), Some("root"));
+        assert_eq!(names.get(&501).map(String::as_str), Some("byron"));
+    }
+}
@@ -0,0 +1,173 @@
+//! Walk paths on other hosts with the system's `ssh` client, which honors the user's ssh configuration and agent.
+use failure::{bail, Error, ResultExt};
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, Read},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+/// The exit code of `ssh` if it failed itself, like when the host can't be reached or logging in was refused
+const SSH_FAILURE: i32 = 255;
+
+/// A path on another host, written as `ssh://[user@]host/path`
+#[derive(Debug, PartialEq)]
+pub struct RemotePath {
+    host: String,
+    path: String,
+}
+
+/// A file or directory below a remote path
+#[derive(Debug, PartialEq)]
+pub struct RemoteEntry {
+    pub is_dir: bool,
+    /// The apparent size of the entry, as the amount of blocks is not reliably available
+    pub size: u64,
+}
+
+impl RemotePath {
+    /// Returns `None` if `path` does not refer to another host.
+    pub fn parse(path: &Path) -> Option<RemotePath> {
+        let rest = path.to_str()?.strip_prefix("ssh://")?;
+        let (host, path) = rest.split_at(rest.find('/')?);
+        if host.is_empty() {
+            return None;
+        }
+        Some(RemotePath {
+            host: host.to_owned(),
+            path: path.to_owned(),
+        })
+    }
+
+    /// Call `on_entry` for the path and each entry below it, and return the amount of errors encountered.
+    /// This requires `find` with support for `-printf` on the remote host, and a login which doesn't ask for a
+    /// password, like with a key held by an agent.
+    pub fn walk(&self, mut on_entry: impl FnMut(RemoteEntry)) -> Result<u64, Error> {
+        if !cfg!(feature = "ssh") {
+            bail!(
+                "Remote paths like '{}' are only supported with the 'ssh' feature enabled",
+                self
+            );
+        }
+        let mut child = Command::new("ssh")
+            .args(["-o", "BatchMode=yes", "--"])
+            .arg(&self.host)
+            .arg(format!("find {} -printf '%y %s\\n'", quoted(&self.path)))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|_| format!("Could not run 'ssh' to walk '{}'", self))?;
+        let mut stderr = child.stderr.take().expect("piped stderr");
+        // read stderr while the entries come in, or a chatty `find` would block once the pipe is full
+        let stderr = std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            stderr.read_to_end(&mut buf).map(|_| buf)
+        });
+        let mut num_entries = 0;
+        let num_malformed_lines = parse_find_output(
+            BufReader::new(child.stdout.take().expect("piped stdout")),
+            |entry| {
+                num_entries += 1;
+                on_entry(entry)
+            },
+        )
+        .with_context(|_| format!("Could not read the entries of '{}'", self))?;
+        let status = child
+            .wait()
+            .with_context(|_| format!("Could not run 'ssh' to walk '{}'", self))?;
+        let stderr = stderr
+            .join()
+            .expect("no panic in the stderr reader")
+            .with_context(|_| format!("Could not read the errors of walking '{}'", self))?;
+        let stderr = String::from_utf8_lossy(&stderr);
+        // ssh itself fails with 255, while `find` fails with 1 if only some entries couldn't be read
+        if status.code() == Some(SSH_FAILURE) || (!status.success() && num_entries == 0) {
+            bail!("Could not walk '{}' ({}): {}", self, status, stderr.trim());
+        }
+        let num_stderr_lines = stderr.lines().filter(|l| !l.is_empty()).count();
+        Ok(num_malformed_lines + num_stderr_lines as u64)
+    }
+}
+
+impl fmt::Display for RemotePath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ssh://{}{}", self.host, self.path)
+    }
+}
+
+/// Quote `s` for use in a POSIX shell, which is what the remote host runs our command with.
+fn quoted(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Parse lines of `<type> <size>` as produced by `find -printf '%y %s\n'`, returning the amount of unparseable lines.
+fn parse_find_output(
+    stdout: impl BufRead,
+    mut on_entry: impl FnMut(RemoteEntry),
+) -> Result<u64, io::Error> {
+    let mut num_errors = 0;
+    for line in stdout.split(b'\n') {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        let entry = std::str::from_utf8(&line).ok().and_then(|line| {
+            let mut tokens = line.splitn(2, ' ');
+            let is_dir = tokens.next()? == "d";
+            let size = tokens.next()?.parse().ok()?;
+            Some(RemoteEntry { is_dir, size })
+        });
+        match entry {
+            Some(entry) => on_entry(entry),
+            None => num_errors += 1,
+        }
+    }
+    Ok(num_errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_remote_paths_and_ignores_local_ones() {
+        assert_eq!(
+            RemotePath::parse(Path::new("ssh://user@host/var/log")),
+            Some(RemotePath {
+                host: "user@host".into(),
+                path: "/var/log".into()
+            })
+        );
+        assert_eq!(RemotePath::parse(Path::new("ssh:///var/log")), None);
+        assert_eq!(RemotePath::parse(Path::new("ssh://host")), None);
+        assert_eq!(RemotePath::parse(Path::new("/var/log")), None);
+    }
+
+    #[test]
+    fn it_parses_find_output_and_counts_malformed_lines() -> Result<(), io::Error> {
+        let mut entries = Vec::new();
+        let num_errors =
+            parse_find_output(&b"d 4096\nf 12\nl 7\nf\n\nf x\n"[..], |e| entries.push(e))?;
+        assert_eq!(
+            entries,
+            vec![
+                RemoteEntry {
+                    is_dir: true,
+                    size: 4096
+                },
+                RemoteEntry {
+                    is_dir: false,
+                    size: 12
+                },
+                RemoteEntry {
+                    is_dir: false,
+                    size: 7
+                },
+            ]
+        );
+        assert_eq!(num_errors, 2);
+        assert_eq!(quoted("it's"), r"'it'\''s'");
+        Ok(())
+    }
+}
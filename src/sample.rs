@@ -0,0 +1,429 @@
+//! Estimate the size of paths by walking only a random sample of the directories below them.
+use crate::{
+    aggregate::write_path_bytes, is_cache_dir, FileId, InodeFilter, Symlinks, WalkOptions,
+    WalkResult,
+};
+use failure::Error;
+use std::{collections::HashSet, fs, io, path::Path, sync::Mutex};
+
+/// The amount of standard deviations covering 95% of a normal distribution
+const Z_95: f64 = 1.96;
+
+/// Statistics about the directories seen while sampling
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct SampleStatistics {
+    /// The amount of directories whose entries were read
+    pub num_directories_walked: u64,
+    /// The amount of directories which were skipped, and whose size was estimated from the ones walked next to them
+    pub num_directories_skipped: u64,
+}
+
+/// The estimated amount of bytes below a path, and the variance of the estimate
+#[derive(Default, Debug, Clone, Copy)]
+struct Estimate {
+    num_bytes: f64,
+    variance: f64,
+}
+
+impl Estimate {
+    /// Half the width of the interval containing the actual size with a probability of 95%.
+    fn margin(&self) -> u64 {
+        (Z_95 * self.variance.sqrt()).round() as u64
+    }
+}
+
+/// A xorshift generator, which is good enough to pick directories and allows reproducing samples with the same seed
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng((seed ^ 0x9E37_79B9_7F4A_7C15).max(1))
+    }
+
+    /// A number from 0 to `n`, excluding `n`.
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+struct Sampler<'a> {
+    options: &'a WalkOptions,
+    fraction: f64,
+    rng: Rng,
+    inodes: InodeFilter,
+    /// The directories walked so far, to not walk those again which symbolic links lead to
+    visited: Mutex<HashSet<FileId>>,
+    res: WalkResult,
+    stats: SampleStatistics,
+}
+
+impl Sampler<'_> {
+    /// Estimate the size of `path` with `metadata`, counting all of its files but walking only the given fraction of
+    /// the directories within it, picked at random with a probability proportional to their amount of entries.
+    fn estimate(&mut self, path: &Path, metadata: &fs::Metadata) -> Estimate {
+        if !metadata.is_dir() {
+            return Estimate {
+                num_bytes: self.size_of(path, metadata) as f64,
+                variance: 0.0,
+            };
+        }
+        if !Symlinks::visit(&self.visited, metadata) {
+            log::warn!(
+                "not following the symbolic link {} as it leads to a directory walked already",
+                path.display()
+            );
+            return Estimate::default();
+        }
+        self.stats.num_directories_walked += 1;
+        let entries = match fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(_) => {
+                self.res.num_errors += 1;
+                return Estimate::default();
+            }
+        };
+        let mut num_bytes = 0;
+        let mut dirs = Vec::new();
+        for entry in entries {
+            let (entry_path, mut metadata) = match entry.and_then(|e| Ok((e.path(), e.metadata()?)))
+            {
+                Ok(entry) => entry,
+                Err(_) => {
+                    self.res.num_errors += 1;
+                    continue;
+                }
+            };
+            if metadata.file_type().is_symlink() {
+                if let Some(target) = self.options.symlinks.followed_target(&entry_path) {
+                    metadata = target;
+                }
+            }
+            if self
+                .options
+                .exclude
+                .iter()
+                .any(|glob| glob.is_match(&entry_path))
+            {
+                continue;
+            }
+            if metadata.is_dir() {
+                if !(self.options.exclude_caches && is_cache_dir(&entry_path)) {
+                    // the amount of entries is a cheap hint at the size, as they are read without their metadata
+                    let weight = fs::read_dir(&entry_path).map_or(0, |entries| entries.count());
+                    dirs.push((entry_path, metadata, weight as u64 + 1));
+                }
+            } else {
+                num_bytes += self.size_of(&entry_path, &metadata);
+            }
+        }
+
+        let num_dirs = dirs.len();
+        let num_sampled = match num_dirs {
+            n if n <= 2 => n,
+            n => ((self.fraction * n as f64).ceil() as usize).clamp(2, n),
+        };
+        // Directories are picked with a probability proportional to their weight, and those which would be picked
+        // for sure as they weigh at least as much as the directories each pick stands in for are walked in any case,
+        // which makes the estimate exact once all directories are picked.
+        dirs.sort_by(|(_, _, lweight), (_, _, rweight)| rweight.cmp(lweight));
+        let mut num_certain = 0;
+        let mut remaining_weight: u64 = dirs.iter().map(|(_, _, weight)| weight).sum();
+        while num_certain < num_sampled
+            && dirs[num_certain].2 * (num_sampled - num_certain) as u64 >= remaining_weight
+        {
+            remaining_weight -= dirs[num_certain].2;
+            num_certain += 1;
+        }
+        let mut estimate = Estimate {
+            num_bytes: num_bytes as f64,
+            variance: 0.0,
+        };
+        for (dir, metadata, _) in &dirs[..num_certain] {
+            let certain = self.estimate(dir, metadata);
+            estimate.num_bytes += certain.num_bytes;
+            estimate.variance += certain.variance;
+        }
+
+        // The others are picked systematically, at evenly spaced points of their weights laid out one after another
+        // with a random offset, so each is picked at most once. The probability of picking a directory is its share of
+        // the weight times the amount of picks, and its estimate is divided by it. The variance adds up the one of
+        // picking directories at random, approximated as if they were picked independently, and the one of the
+        // estimates of the directories picked.
+        let num_picks = num_sampled - num_certain;
+        let others = &dirs[num_certain..];
+        self.stats.num_directories_skipped += (others.len() - num_picks) as u64;
+        if num_picks == 0 {
+            return estimate;
+        }
+        let offset = self.rng.below(remaining_weight as usize) as u64;
+        let mut picks = (0..num_picks as u64)
+            .map(|pick| (offset + pick * remaining_weight) / num_picks as u64)
+            .peekable();
+        let mut weight_so_far = 0;
+        let mut picked = Vec::with_capacity(num_picks);
+        for (dir, metadata, weight) in others {
+            weight_so_far += weight;
+            if picks.next_if(|point| *point < weight_so_far).is_some() {
+                let probability = (num_picks as u64 * weight) as f64 / remaining_weight as f64;
+                picked.push((self.estimate(dir, metadata), probability));
+            }
+        }
+        let scaled: Vec<_> = picked
+            .iter()
+            .map(|(e, probability)| num_picks as f64 * e.num_bytes / probability)
+            .collect();
+        let mean = scaled.iter().sum::<f64>() / num_picks as f64;
+        let sample_variance = if num_picks > 1 {
+            scaled.iter().map(|z| (z - mean).powi(2)).sum::<f64>() / (num_picks - 1) as f64
+        } else {
+            0.0
+        };
+        let finite_population = 1.0 - num_picks as f64 / others.len() as f64;
+        estimate.num_bytes += mean;
+        estimate.variance += finite_population * sample_variance / num_picks as f64
+            + picked
+                .iter()
+                .map(|(e, probability)| e.variance / probability)
+                .sum::<f64>();
+        estimate
+    }
+
+    fn size_of(&mut self, path: &Path, metadata: &fs::Metadata) -> u64 {
+        if !self.options.count_hard_links && !self.inodes.add(metadata) {
+            return 0;
+        }
+        if self.options.apparent_size {
+            metadata.len()
+        } else {
+            filesize::file_real_size_fast(path, metadata).unwrap_or_else(|_| {
+                self.res.num_errors += 1;
+                0
+            })
+        }
+    }
+}
+
+/// Estimate the size of each of the given `paths` by walking only a `fraction` of the directories below them, and
+/// write it to `out` prefixed with `~` and followed by `±` and the margin of error at a confidence of 95%, then the
+/// path. With multiple paths, the estimated total is written last.
+///
+/// All files within a walked directory are counted, but only the given `fraction` of the directories within it are
+/// walked, at least two of them and picked at random by a generator seeded with `seed`. Directories with more entries
+/// are more likely to be picked, and the size of each walked directory is weighted by the amount of directories it
+/// stands in for, the skipped ones included. Hence the estimate is exact with a `fraction` of 1 and gets less accurate
+/// the smaller it is, but also faster to obtain. Directories which symbolic links lead to are walked only once.
+/// Paths matching `options.exclude` are skipped, and so are cache directories if `options.exclude_caches` is set.
+pub fn sample(
+    mut out: impl io::Write,
+    options: WalkOptions,
+    fraction: f64,
+    seed: u64,
+    paths: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> Result<(WalkResult, SampleStatistics), Error> {
+    let mut sampler = Sampler {
+        options: &options,
+        fraction,
+        rng: Rng::new(seed),
+        inodes: InodeFilter::default(),
+        visited: Mutex::default(),
+        res: WalkResult::default(),
+        stats: SampleStatistics::default(),
+    };
+    let mut total = Estimate::default();
+    let mut num_roots = 0;
+    for path in paths {
+        let path = path.as_ref();
+        // like a walk, each path is walked in full, even if it was walked as part of another one already
+        sampler.visited = Mutex::default();
+        let metadata = if options.symlinks.follow_root {
+            fs::metadata(path)
+        } else {
+            fs::symlink_metadata(path)
+        };
+        let estimate = match metadata {
+            Ok(metadata) => sampler.estimate(path, &metadata),
+            Err(_) => {
+                sampler.res.num_errors += 1;
+                Estimate::default()
+            }
+        };
+        total.num_bytes += estimate.num_bytes;
+        total.variance += estimate.variance;
+        num_roots += 1;
+        write_estimate(&mut out, &options, &estimate)?;
+        write_path_bytes(&mut out, options.displayed_path(path))?;
+        writeln!(out)?;
+    }
+    if num_roots > 1 {
+        write_estimate(&mut out, &options, &total)?;
+        writeln!(out, "total")?;
+    }
+    sampler.res.total_bytes = total.num_bytes.round() as u64;
+    Ok((sampler.res, sampler.stats))
+}
+
+fn write_estimate(
+    out: &mut impl io::Write,
+    options: &WalkOptions,
+    estimate: &Estimate,
+) -> Result<(), io::Error> {
+    let format = |bytes: u64| {
+        options
+            .byte_format
+            .display(bytes)
+            .grouped(options.thousands_separator)
+            .to_string()
+    };
+    write!(
+        out,
+        "{:>width$} {:>width$} ",
+        format!("~{}", format(estimate.num_bytes.round() as u64)),
+        format!("±{}", format(estimate.margin())),
+        width = options.byte_format.width() + 1
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{test_options, TempDir};
+
+    #[test]
+    fn skipped_directories_are_estimated_from_the_walked_ones() -> Result<(), Error> {
+        let root = TempDir::new("sample")?;
+        for i in 0..10 {
+            let dir = root.join("dirs").join(i.to_string());
+            fs::create_dir_all(dir.join("sub"))?;
+            fs::write(dir.join("sub").join("file"), [0; 100])?;
+        }
+        fs::write(root.join("file"), [0; 5])?;
+
+        let run = |fraction| -> Result<(String, SampleStatistics), Error> {
+            let mut out = Vec::new();
+            let (_, stats) = sample(&mut out, test_options(), fraction, 42, [&root])?;
+            Ok((String::from_utf8(out)?, stats))
+        };
+        let all = run(1.0);
+        let some = run(0.3);
+
+        let (all, all_stats) = all?;
+        assert_eq!(
+            all.split_whitespace().take(4).collect::<Vec<_>>(),
+            vec!["~1005", "b", "±0", "b"],
+            "walking everything is exact"
+        );
+        assert_eq!(
+            all_stats,
+            SampleStatistics {
+                num_directories_walked: 22,
+                num_directories_skipped: 0,
+            }
+        );
+
+        let (some, some_stats) = some?;
+        assert_eq!(
+            some.split_whitespace().take(4).collect::<Vec<_>>(),
+            vec!["~1005", "b", "±0", "b"],
+            "directories of equal size are estimated exactly"
+        );
+        assert_eq!(
+            some_stats,
+            SampleStatistics {
+                num_directories_walked: 8,
+                num_directories_skipped: 7,
+            },
+            "the root, 'dirs', and three of its directories along with theirs are walked"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn estimates_of_directories_of_different_size_have_a_margin_of_error() -> Result<(), Error> {
+        let root = TempDir::new("sample-margin")?;
+        for i in 0..10 {
+            let dir = root.join("dirs").join(i.to_string());
+            fs::create_dir_all(&dir)?;
+            fs::write(dir.join("file"), vec![0; 10 * (i + 1)])?;
+        }
+
+        let mut out = Vec::new();
+        let res = sample(&mut out, test_options(), 0.5, 7, [root.join("dirs")]);
+        let (res, _) = res?;
+
+        let out = String::from_utf8(out)?;
+        let margin: u64 = out
+            .split_whitespace()
+            .nth(2)
+            .and_then(|margin| margin.trim_start_matches('±').parse().ok())
+            .expect("a margin");
+        assert!(margin > 0, "{}", out);
+        assert!(
+            (res.total_bytes as i64 - 550).unsigned_abs() <= margin * 2,
+            "the estimate of {} is near the actual size",
+            res.total_bytes
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn directories_with_many_entries_are_always_walked() -> Result<(), Error> {
+        let root = TempDir::new("sample-weighted")?;
+        fs::create_dir_all(root.join("big"))?;
+        for i in 0..20 {
+            fs::write(root.join("big").join(i.to_string()), [0; 100])?;
+        }
+        for i in 0..9 {
+            let dir = root.join(format!("small-{}", i));
+            fs::create_dir_all(&dir)?;
+            fs::write(dir.join("file"), [0; 10])?;
+        }
+
+        let mut out = Vec::new();
+        let (res, stats) = sample(&mut out, test_options(), 0.2, 3, [&root])?;
+        assert_eq!(
+            String::from_utf8(out)?.split_whitespace().take(4).collect::<Vec<_>>(),
+            vec!["~2090", "b", "±0", "b"],
+            "the directory holding most entries is walked, and one of the others stands in for the rest"
+        );
+        assert_eq!(res.total_bytes, 2090);
+        assert_eq!(
+            stats,
+            SampleStatistics {
+                num_directories_walked: 3,
+                num_directories_skipped: 8,
+            }
+        );
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn directories_linking_to_each_other_are_walked_once() -> Result<(), Error> {
+        use crate::Symlinks;
+        use std::os::unix::fs::symlink;
+
+        let root = TempDir::new("sample-symlinks")?;
+        fs::create_dir_all(root.join("a"))?;
+        fs::create_dir_all(root.join("b"))?;
+        fs::write(root.join("a").join("file"), [0; 10])?;
+        fs::write(root.join("b").join("file"), [0; 20])?;
+        symlink("../b", root.join("a").join("to-b"))?;
+        symlink("../a", root.join("b").join("to-a"))?;
+
+        let mut out = Vec::new();
+        let options = WalkOptions {
+            symlinks: Symlinks {
+                follow_dirs: true,
+                ..Default::default()
+            },
+            ..test_options()
+        };
+        let (res, stats) = sample(&mut out, options, 1.0, 1, [&root])?;
+        assert_eq!(res.total_bytes, 30);
+        assert_eq!(stats.num_directories_walked, 3);
+        Ok(())
+    }
+}
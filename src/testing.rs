@@ -0,0 +1,79 @@
+//! Helpers shared by the tests of the walks.
+use crate::{
+    ByteFormat, Color, ColumnOrder, ErrorAnnotation, OutputFormat, OutputOrder, Symlinks,
+    TimeStyle, WalkOptions,
+};
+use std::{
+    fs, io,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+/// Options walking with a single thread and counting apparent sizes in bytes, for sizes which don't depend on the
+/// filesystem.
+pub(crate) fn test_options() -> WalkOptions {
+    WalkOptions {
+        threads: 1,
+        byte_format: ByteFormat::Bytes,
+        count_hard_links: false,
+        apparent_size: true,
+        both_sizes: false,
+        shared_unit: false,
+        show_entry_count: false,
+        estimate_compression: false,
+        verbose_size: false,
+        thousands_separator: None,
+        column_order: ColumnOrder::SizePath,
+        error_annotation: ErrorAnnotation::Human,
+        output_format: OutputFormat::Text,
+        time_style: TimeStyle::Iso,
+        color: Color::None,
+        output_order: OutputOrder::Unsorted,
+        exclude: Vec::new(),
+        exclude_caches: false,
+        exclude_larger_than: None,
+        exclude_smaller_than: None,
+        labels: Vec::new(),
+        relative_to: None,
+        heatmap: None,
+        symlinks: Symlinks::default(),
+        ls_colors: None,
+        max_time: None,
+        fatal_errors: Vec::new(),
+    }
+}
+
+/// A directory below the temporary directory, removed along with everything in it once dropped, even if the test
+/// panics.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Create an empty directory whose name contains `name`, which has to be unique among all tests.
+    pub(crate) fn new(name: &str) -> Result<TempDir, io::Error> {
+        let path = std::env::temp_dir().join(format!("dua-{}-{}", name, std::process::id()));
+        // a previous run with the same pid may have been killed before it could clean up
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path)?;
+        Ok(TempDir(path))
+    }
+}
+
+impl Deref for TempDir {
+    type Target = PathBuf;
+
+    fn deref(&self) -> &PathBuf {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
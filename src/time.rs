@@ -0,0 +1,184 @@
+//! Format points in time the same way wherever they are displayed.
+use crate::locale::{self, DateOrder};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_MINUTE: u64 = 60;
+const SECONDS_PER_HOUR: u64 = 60 * SECONDS_PER_MINUTE;
+const SECONDS_PER_DAY: u64 = 24 * SECONDS_PER_HOUR;
+
+/// How to display a point in time, like the modification time of an entry
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TimeStyle {
+    /// ISO-8601 in UTC, like `2020-02-25T10:31:02Z`, which is stable and meant for scripts
+    #[default]
+    Iso,
+    /// The time passed since then in the largest fitting unit, like `2h ago`
+    Relative,
+    /// Date and time in UTC, with the date written like the locale configured by LC_ALL, LC_TIME or LANG does,
+    /// like `25.02.2020 10:31`. Uses `2020-02-25 10:31` if the locale is unknown.
+    Locale,
+}
+
+impl TimeStyle {
+    /// Format `time` in our style, with `now` being the reference for relative times.
+    pub fn format(self, time: SystemTime, now: SystemTime) -> String {
+        match self {
+            TimeStyle::Iso => {
+                let (date, (hours, minutes, seconds)) = utc(time);
+                format!(
+                    "{}T{:02}:{:02}:{:02}Z",
+                    format_date(date, DateOrder::YearMonthDay('-')),
+                    hours,
+                    minutes,
+                    seconds
+                )
+            }
+            TimeStyle::Relative => match now.duration_since(time) {
+                Ok(elapsed) => format!("{} ago", format_duration(elapsed.as_secs())),
+                Err(err) => format!("in {}", format_duration(err.duration().as_secs())),
+            },
+            TimeStyle::Locale => {
+                let (date, (hours, minutes, _)) = utc(time);
+                format!(
+                    "{} {:02}:{:02}",
+                    format_date(
+                        date,
+                        locale::date_order().unwrap_or(DateOrder::YearMonthDay('-'))
+                    ),
+                    hours,
+                    minutes
+                )
+            }
+        }
+    }
+}
+
+fn format_duration(seconds: u64) -> String {
+    match seconds {
+        s if s < SECONDS_PER_MINUTE => format!("{}s", s),
+        s if s < SECONDS_PER_HOUR => format!("{}m", s / SECONDS_PER_MINUTE),
+        s if s < SECONDS_PER_DAY => format!("{}h", s / SECONDS_PER_HOUR),
+        s if s < 30 * SECONDS_PER_DAY => format!("{}d", s / SECONDS_PER_DAY),
+        s if s < 365 * SECONDS_PER_DAY => format!("{}mo", s / (30 * SECONDS_PER_DAY)),
+        s => format!("{}y", s / (365 * SECONDS_PER_DAY)),
+    }
+}
+
+fn format_date((year, month, day): (i64, u32, u32), order: DateOrder) -> String {
+    match order {
+        DateOrder::YearMonthDay(sep) => {
+            format!("{}{sep}{:02}{sep}{:02}", year, month, day, sep = sep)
+        }
+        DateOrder::DayMonthYear(sep) => {
+            format!("{:02}{sep}{:02}{sep}{}", day, month, year, sep = sep)
+        }
+        DateOrder::MonthDayYear(sep) => {
+            format!("{:02}{sep}{:02}{sep}{}", month, day, year, sep = sep)
+        }
+    }
+}
+
+/// The date as `(year, month, day)` and the time of day as `(hours, minutes, seconds)` of `time` in UTC.
+fn utc(time: SystemTime) -> ((i64, u32, u32), (u64, u64, u64)) {
+    let seconds_since_epoch = match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(err) => -(err.duration().as_secs() as i64),
+    };
+    let days = seconds_since_epoch.div_euclid(SECONDS_PER_DAY as i64);
+    let seconds_of_day = seconds_since_epoch.rem_euclid(SECONDS_PER_DAY as i64) as u64;
+    (
+        civil_from_days(days),
+        (
+            seconds_of_day / SECONDS_PER_HOUR,
+            seconds_of_day % SECONDS_PER_HOUR / SECONDS_PER_MINUTE,
+            seconds_of_day % SECONDS_PER_MINUTE,
+        ),
+    )
+}
+
+/// Convert days since 1970-01-01 into a date of the proleptic Gregorian calendar, as described in
+/// <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn at(seconds_since_epoch: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(seconds_since_epoch)
+    }
+
+    #[test]
+    fn iso_times_are_in_utc() {
+        assert_eq!(TimeStyle::Iso.format(at(0), at(0)), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            TimeStyle::Iso.format(at(1_582_626_662), at(0)),
+            "2020-02-25T10:31:02Z"
+        );
+        assert_eq!(
+            TimeStyle::Iso.format(at(951_782_400), at(0)),
+            "2000-02-29T00:00:00Z",
+            "leap days are known"
+        );
+        assert_eq!(
+            TimeStyle::Iso.format(UNIX_EPOCH - Duration::from_secs(1), at(0)),
+            "1969-12-31T23:59:59Z"
+        );
+    }
+
+    #[test]
+    fn relative_times_use_the_largest_fitting_unit() {
+        let now = at(100 * 365 * SECONDS_PER_DAY);
+        let ago = |seconds| TimeStyle::Relative.format(now - Duration::from_secs(seconds), now);
+        assert_eq!(ago(0), "0s ago");
+        assert_eq!(ago(59), "59s ago");
+        assert_eq!(ago(2 * SECONDS_PER_HOUR + 59), "2h ago");
+        assert_eq!(ago(3 * SECONDS_PER_DAY), "3d ago");
+        assert_eq!(ago(65 * SECONDS_PER_DAY), "2mo ago");
+        assert_eq!(ago(800 * SECONDS_PER_DAY), "2y ago");
+        assert_eq!(
+            TimeStyle::Relative.format(now + Duration::from_secs(300), now),
+            "in 5m"
+        );
+    }
+
+    #[test]
+    fn locale_times_write_dates_in_the_order_of_the_locale() {
+        let date = (2020, 2, 5);
+        assert_eq!(
+            format_date(date, DateOrder::DayMonthYear('.')),
+            "05.02.2020"
+        );
+        assert_eq!(
+            format_date(date, DateOrder::MonthDayYear('/')),
+            "02/05/2020"
+        );
+        assert_eq!(
+            format_date(date, DateOrder::YearMonthDay('-')),
+            "2020-02-05"
+        );
+        let formatted = TimeStyle::Locale.format(at(1_580_906_100), at(0));
+        assert!(
+            formatted.ends_with(" 12:35") && formatted.contains("2020"),
+            "{}",
+            formatted
+        );
+    }
+}
@@ -1,18 +1,46 @@
-use crate::{get_size_or_panic, InodeFilter, WalkOptions};
+use crate::{get_size_or_panic, InodeFilter, Symlinks, WalkOptions};
 use failure::Error;
 use petgraph::{graph::NodeIndex, stable_graph::StableGraph, Directed, Direction};
-use std::{ffi::OsString, path::PathBuf, time::Duration, time::Instant};
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+    time::Instant,
+};
 
 pub type TreeIndex = NodeIndex;
 pub type Tree = StableGraph<EntryData, (), Directed>;
 
+/// An estimate of the memory in bytes taken by a [`Tree`] of `num_nodes` entries whose names are `num_name_bytes` long
+/// in total: a node per entry, an edge to each entry from its directory and the names themselves. Unused capacity of
+/// the tree and of the names isn't part of it, so the actual amount is up to about twice as much.
+pub fn estimated_tree_size(num_nodes: u64, num_name_bytes: u64) -> u64 {
+    let node_size = std::mem::size_of::<petgraph::graph::Node<Option<EntryData>>>()
+        + std::mem::size_of::<petgraph::graph::Edge<Option<()>>>();
+    num_nodes
+        .saturating_mul(node_size as u64)
+        .saturating_add(num_name_bytes)
+}
+
 #[derive(Eq, PartialEq, Debug, Default, Clone)]
 pub struct EntryData {
     pub name: OsString,
     /// The entry's size in bytes. If it's a directory, the size is the aggregated file size of all children
     pub size: u64,
+    /// The size of a directory itself in bytes, which is the space taken by the list of its entries and not part of
+    /// `size`. Always 0 for files.
+    pub own_size: u64,
+    /// The length of the content of a file, or of the list of entries of a directory, in bytes. It is what `size` of
+    /// files or `own_size` of directories hold if apparent sizes are counted, and 0 for hard links counted already.
+    pub apparent_size: u64,
+    /// If set, the entry is a directory, even if it has no entries
+    pub is_dir: bool,
     /// If set, the item meta-data could not be obtained
     pub metadata_io_error: bool,
+    /// If set, the entries of this directory or of a directory below it could not all be read, so `size` is only a
+    /// lower bound
+    pub incomplete: bool,
 }
 
 const REFRESH_RATE: Duration = Duration::from_millis(100);
@@ -32,167 +60,325 @@ pub struct Traversal {
     pub total_bytes: Option<u64>,
 }
 
-impl Traversal {
-    pub fn from_walk(
-        mut walk_options: WalkOptions,
-        input: Vec<PathBuf>,
-        mut update: impl FnMut(&Traversal) -> Result<(), Error>,
-    ) -> Result<Traversal, Error> {
-        fn set_size_or_panic(tree: &mut Tree, node_idx: TreeIndex, current_size_at_depth: u64) {
-            tree.node_weight_mut(node_idx)
-                .expect("node for parent index we just retrieved")
-                .size = current_size_at_depth;
-        }
-        fn parent_or_panic(tree: &mut Tree, parent_node_idx: TreeIndex) -> TreeIndex {
-            tree.neighbors_directed(parent_node_idx, Direction::Incoming)
-                .next()
-                .expect("every node in the iteration has a parent")
-        }
-        fn pop_or_panic(v: &mut Vec<u64>) -> u64 {
-            v.pop().expect("sizes per level to be in sync with graph")
+/// A single step of a filesystem walk, as produced by [`walk()`] and consumed by a [`TraversalBuilder`].
+pub enum TraversalEvent {
+    /// An entry at the given `depth` below its root path, with the root path itself being at depth 0
+    Entry { depth: usize, data: EntryData },
+    /// An entry below `root` could not be read
+    Error { root: PathBuf },
+}
+
+/// Walk all `input` paths and `send` an event for each entry we encounter, with file sizes already computed.
+/// The walk stops early if `send` fails.
+pub fn walk(
+    mut walk_options: WalkOptions,
+    input: Vec<PathBuf>,
+    mut send: impl FnMut(TraversalEvent) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let mut inodes = InodeFilter::default();
+    if walk_options.threads == 0 {
+        // avoid using the global rayon pool, as it will keep a lot of threads alive after we are done.
+        // Also means that we will spin up a bunch of threads per root path, instead of reusing them.
+        walk_options.threads = num_cpus::get_physical();
+    }
+    for path in input.into_iter() {
+        log::debug!("walking {}", path.display());
+        let walk_started = Instant::now();
+        let mut num_entries = 0_u64;
+        for entry in walk_options.iter_from_path(path.as_ref()) {
+            num_entries += 1;
+            let event = match entry {
+                Ok(entry) => {
+                    let mut data = EntryData {
+                        incomplete: entry.content_error.is_some(),
+                        ..Default::default()
+                    };
+                    let file_size = match entry.metadata {
+                        Some(Ok(ref m)) if !m.is_dir() && (walk_options.count_hard_links || inodes.add(m)) => {
+                            data.apparent_size = m.len();
+                            if walk_options.apparent_size {
+                                m.len()
+                            } else {
+                                filesize::file_real_size_fast(entry.path(), m)
+                                    .unwrap_or_else(|_| {
+                                        data.metadata_io_error = true;
+                                        0
+                                    })
+                            }
+                        },
+                        Some(Ok(ref m)) => {
+                            if m.is_dir() {
+                                data.is_dir = true;
+                                data.apparent_size = m.len();
+                                data.own_size = if walk_options.apparent_size {
+                                    m.len()
+                                } else {
+                                    filesize::file_real_size_fast(entry.path(), m).unwrap_or(0)
+                                };
+                            }
+                            0
+                        }
+                        Some(Err(_)) => {
+                            data.metadata_io_error = true;
+                            0
+                        }
+                        None => unreachable!(
+                            "we ask for metadata, so we at least have Some(Err(..))). Issue in jwalk?"
+                        ),
+                    };
+                    data.name = if entry.depth < 1 {
+                        path.clone().into()
+                    } else {
+                        entry.file_name
+                    };
+                    data.size = file_size;
+                    TraversalEvent::Entry {
+                        depth: entry.depth,
+                        data,
+                    }
+                }
+                Err(_) => TraversalEvent::Error { root: path.clone() },
+            };
+            send(event)?;
         }
+        log::info!(
+            "walked {} in {:?}: {} entries",
+            path.display(),
+            walk_started.elapsed(),
+            num_entries
+        );
+    }
+    Ok(())
+}
 
-        let mut t = {
-            let mut tree = Tree::new();
-            let root_index = tree.add_node(EntryData::default());
-            Traversal {
-                tree,
-                root_index,
-                ..Default::default()
-            }
-        };
+/// The path to walk the open directory `handle` by, which is the link to it in `/proc/self/fd` if there is one,
+/// or `path` otherwise.
+#[cfg(target_os = "linux")]
+fn walkable_path(handle: &fs::File, path: &Path) -> PathBuf {
+    use std::os::unix::io::AsRawFd;
+    let link = PathBuf::from(format!("/proc/self/fd/{}", handle.as_raw_fd()));
+    if link.is_dir() {
+        link
+    } else {
+        path.to_owned()
+    }
+}
 
-        let (mut previous_node_idx, mut parent_node_idx) = (t.root_index, t.root_index);
-        let mut sizes_per_depth_level = Vec::new();
-        let mut current_size_at_depth = 0;
-        let mut previous_depth = 0;
-        let mut inodes = InodeFilter::default();
+#[cfg(not(target_os = "linux"))]
+fn walkable_path(_handle: &fs::File, path: &Path) -> PathBuf {
+    path.to_owned()
+}
 
-        let mut last_checked = Instant::now();
+/// Builds the tree of a [`Traversal`] incrementally from the events of a [`walk()`], which makes the
+/// partial tree usable while the walk is still ongoing.
+///
+/// The size of directories is only known once all of their entries were added.
+pub struct TraversalBuilder {
+    previous_node_idx: TreeIndex,
+    parent_node_idx: TreeIndex,
+    sizes_per_depth_level: Vec<u64>,
+    current_size_at_depth: u64,
+    previous_depth: usize,
+}
 
-        const INITIAL_CHECK_INTERVAL: usize = 500;
-        let mut check_instant_every = INITIAL_CHECK_INTERVAL;
-        if walk_options.threads == 0 {
-            // avoid using the global rayon pool, as it will keep a lot of threads alive after we are done.
-            // Also means that we will spin up a bunch of threads per root path, instead of reusing them.
-            walk_options.threads = num_cpus::get_physical();
+impl TraversalBuilder {
+    /// Prepare adding entries to `t`, which must be [empty](Traversal::empty()).
+    pub fn new(t: &Traversal) -> Self {
+        TraversalBuilder {
+            previous_node_idx: t.root_index,
+            parent_node_idx: t.root_index,
+            sizes_per_depth_level: Vec::new(),
+            current_size_at_depth: 0,
+            previous_depth: 0,
         }
-        for path in input.into_iter() {
-            let mut last_seen_eid = 0;
-            for (eid, entry) in walk_options
-                .iter_from_path(path.as_ref())
-                .into_iter()
-                .enumerate()
-            {
-                t.entries_traversed += 1;
-                let mut data = EntryData::default();
-                match entry {
-                    Ok(entry) => {
-                        data.name = if entry.depth < 1 {
-                            path.clone().into()
-                        } else {
-                            entry.file_name
-                        };
-                        let file_size = match entry.metadata {
-                                Some(Ok(ref m)) if !m.is_dir() && (walk_options.count_hard_links || inodes.add(m)) => {
-                                    if walk_options.apparent_size {
-                                        m.len()
-                                    } else {
-                                        filesize::file_real_size_fast(&data.name, m)
-                                            .unwrap_or_else(|_| {
-                                                t.io_errors += 1;
-                                                data.metadata_io_error = true;
-                                                0
-                                            })
-                                    }
-                                },
-                                Some(Ok(_)) => 0,
-                                Some(Err(_)) => {
-                                    t.io_errors += 1;
-                                    data.metadata_io_error = true;
-                                    0
-                                }
-                                None => unreachable!(
-                                    "we ask for metadata, so we at least have Some(Err(..))). Issue in jwalk?"
-                                ),
-                            };
-
-                        match (entry.depth, previous_depth) {
-                            (n, p) if n > p => {
-                                sizes_per_depth_level.push(current_size_at_depth);
-                                current_size_at_depth = file_size;
-                                parent_node_idx = previous_node_idx;
-                            }
-                            (n, p) if n < p => {
-                                for _ in n..p {
-                                    set_size_or_panic(
-                                        &mut t.tree,
-                                        parent_node_idx,
-                                        current_size_at_depth,
-                                    );
-                                    current_size_at_depth +=
-                                        pop_or_panic(&mut sizes_per_depth_level);
-                                    parent_node_idx = parent_or_panic(&mut t.tree, parent_node_idx);
-                                }
-                                current_size_at_depth += file_size;
-                                set_size_or_panic(
-                                    &mut t.tree,
-                                    parent_node_idx,
-                                    current_size_at_depth,
-                                );
-                            }
-                            _ => {
-                                current_size_at_depth += file_size;
-                            }
-                        };
-
-                        data.size = file_size;
-                        let entry_index = t.tree.add_node(data);
+    }
 
-                        t.tree.add_edge(parent_node_idx, entry_index, ());
-                        previous_node_idx = entry_index;
-                        previous_depth = entry.depth;
+    /// Add the entry represented by `event` to the tree of `t`.
+    pub fn add(&mut self, t: &mut Traversal, event: TraversalEvent) {
+        t.entries_traversed += 1;
+        match event {
+            TraversalEvent::Entry { depth, data } => {
+                if data.metadata_io_error {
+                    t.io_errors += 1;
+                }
+                if data.incomplete {
+                    t.io_errors += 1;
+                }
+                let is_unreadable = data.metadata_io_error || data.incomplete;
+                let file_size = data.size;
+                match (depth, self.previous_depth) {
+                    (n, p) if n > p => {
+                        self.sizes_per_depth_level.push(self.current_size_at_depth);
+                        self.current_size_at_depth = file_size;
+                        self.parent_node_idx = self.previous_node_idx;
                     }
-                    Err(_) => {
-                        if previous_depth == 0 {
-                            data.name = path.clone().into();
-                            let entry_index = t.tree.add_node(data);
-                            t.tree.add_edge(parent_node_idx, entry_index, ());
+                    (n, p) if n < p => {
+                        for _ in n..p {
+                            set_size_or_panic(
+                                &mut t.tree,
+                                self.parent_node_idx,
+                                self.current_size_at_depth,
+                            );
+                            self.current_size_at_depth +=
+                                pop_or_panic(&mut self.sizes_per_depth_level);
+                            self.parent_node_idx =
+                                parent_or_panic(&mut t.tree, self.parent_node_idx);
                         }
-
-                        t.io_errors += 1
+                        self.current_size_at_depth += file_size;
+                        set_size_or_panic(
+                            &mut t.tree,
+                            self.parent_node_idx,
+                            self.current_size_at_depth,
+                        );
                     }
-                }
+                    _ => {
+                        self.current_size_at_depth += file_size;
+                    }
+                };
+
+                let entry_index = t.tree.add_node(data);
 
-                if eid != 0
-                    && eid % check_instant_every == 0
-                    && last_checked.elapsed() >= REFRESH_RATE
-                {
-                    let now = Instant::now();
-                    let elapsed = (now - last_checked).as_millis() as f64;
-                    check_instant_every = (INITIAL_CHECK_INTERVAL as f64
-                        * ((eid - last_seen_eid) as f64 / INITIAL_CHECK_INTERVAL as f64)
-                        * (REFRESH_RATE.as_millis() as f64 / elapsed))
-                        as usize;
-                    last_seen_eid = eid;
-                    last_checked = now;
-
-                    update(&t)?;
+                t.tree.add_edge(self.parent_node_idx, entry_index, ());
+                self.previous_node_idx = entry_index;
+                self.previous_depth = depth;
+                if is_unreadable {
+                    mark_incomplete(&mut t.tree, self.parent_node_idx);
                 }
             }
+            TraversalEvent::Error { root } => {
+                if self.previous_depth == 0 {
+                    let data = EntryData {
+                        name: root.into(),
+                        incomplete: true,
+                        ..Default::default()
+                    };
+                    let entry_index = t.tree.add_node(data);
+                    t.tree.add_edge(self.parent_node_idx, entry_index, ());
+                } else {
+                    mark_incomplete(&mut t.tree, self.parent_node_idx);
+                }
+
+                t.io_errors += 1
+            }
         }
+    }
 
-        sizes_per_depth_level.push(current_size_at_depth);
-        current_size_at_depth = 0;
-        for _ in 0..previous_depth {
-            current_size_at_depth += pop_or_panic(&mut sizes_per_depth_level);
-            set_size_or_panic(&mut t.tree, parent_node_idx, current_size_at_depth);
-            parent_node_idx = parent_or_panic(&mut t.tree, parent_node_idx);
+    /// Set the sizes of all directories which are still incomplete, as no more entries will be added to `t`.
+    pub fn finish(mut self, t: &mut Traversal) {
+        self.sizes_per_depth_level.push(self.current_size_at_depth);
+        self.current_size_at_depth = 0;
+        for _ in 0..self.previous_depth {
+            self.current_size_at_depth += pop_or_panic(&mut self.sizes_per_depth_level);
+            set_size_or_panic(
+                &mut t.tree,
+                self.parent_node_idx,
+                self.current_size_at_depth,
+            );
+            self.parent_node_idx = parent_or_panic(&mut t.tree, self.parent_node_idx);
         }
         let root_size = t.recompute_root_size();
         set_size_or_panic(&mut t.tree, t.root_index, root_size);
         t.total_bytes = Some(root_size);
+    }
+}
+
+/// Mark `node_idx` and all of its ancestors as incomplete, stopping at the first one which is marked already as all
+/// ancestors of incomplete directories are incomplete as well.
+fn mark_incomplete(tree: &mut Tree, mut node_idx: TreeIndex) {
+    loop {
+        let entry = tree
+            .node_weight_mut(node_idx)
+            .expect("node for index we just retrieved");
+        if entry.incomplete {
+            break;
+        }
+        entry.incomplete = true;
+        match tree
+            .neighbors_directed(node_idx, Direction::Incoming)
+            .next()
+        {
+            Some(parent) => node_idx = parent,
+            None => break,
+        }
+    }
+}
+
+fn set_size_or_panic(tree: &mut Tree, node_idx: TreeIndex, current_size_at_depth: u64) {
+    tree.node_weight_mut(node_idx)
+        .expect("node for parent index we just retrieved")
+        .size = current_size_at_depth;
+}
+
+fn parent_or_panic(tree: &mut Tree, parent_node_idx: TreeIndex) -> TreeIndex {
+    tree.neighbors_directed(parent_node_idx, Direction::Incoming)
+        .next()
+        .expect("every node in the iteration has a parent")
+}
+
+fn pop_or_panic(v: &mut Vec<u64>) -> u64 {
+    v.pop().expect("sizes per level to be in sync with graph")
+}
+
+impl Traversal {
+    /// A traversal without any entries, but with the virtual root node all input paths are attached to.
+    pub fn empty() -> Traversal {
+        let mut tree = Tree::new();
+        let root_index = tree.add_node(EntryData::default());
+        Traversal {
+            tree,
+            root_index,
+            ..Default::default()
+        }
+    }
 
+    /// Walk all `input` paths and build the tree, calling `update` periodically with the partial result.
+    pub fn from_walk(
+        walk_options: WalkOptions,
+        input: Vec<PathBuf>,
+        mut update: impl FnMut(&Traversal) -> Result<(), Error>,
+    ) -> Result<Traversal, Error> {
+        let mut t = Traversal::empty();
+        let mut builder = TraversalBuilder::new(&t);
+        let mut last_checked = Instant::now();
+        walk(walk_options, input, |event| {
+            builder.add(&mut t, event);
+            if last_checked.elapsed() >= REFRESH_RATE {
+                last_checked = Instant::now();
+                update(&t)?;
+            }
+            Ok(())
+        })?;
+        builder.finish(&mut t);
+        Ok(t)
+    }
+
+    /// Walk the directory `handle` refers to and build the tree like [`Traversal::from_walk()`], with `path` as the
+    /// name of its node once the walk is done.
+    ///
+    /// On Linux, the directory is walked through its handle, so it's the one which was opened even if it is renamed
+    /// or replaced while walking. Elsewhere, or if `/proc` isn't mounted, it is walked by `path`, which should be the
+    /// path it was opened from.
+    pub fn from_handle(
+        walk_options: WalkOptions,
+        handle: &fs::File,
+        path: PathBuf,
+        update: impl FnMut(&Traversal) -> Result<(), Error>,
+    ) -> Result<Traversal, Error> {
+        let walk_options = WalkOptions {
+            symlinks: Symlinks {
+                follow_root: true,
+                ..walk_options.symlinks
+            },
+            ..walk_options
+        };
+        let mut t = Traversal::from_walk(walk_options, vec![walkable_path(handle, &path)], update)?;
+        if let Some(root) = t
+            .tree
+            .neighbors_directed(t.root_index, Direction::Outgoing)
+            .next()
+        {
+            t.tree[root].name = path.into();
+        }
         Ok(t)
     }
 
@@ -203,3 +389,100 @@ impl Traversal {
             .sum()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(depth: usize, name: &str, size: u64) -> TraversalEvent {
+        TraversalEvent::Entry {
+            depth,
+            data: EntryData {
+                name: name.into(),
+                size,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn directories_with_unreadable_entries_below_them_are_incomplete() {
+        let mut t = Traversal::empty();
+        let mut builder = TraversalBuilder::new(&t);
+        for event in [
+            entry(0, "root", 0),
+            entry(1, "complete", 0),
+            entry(2, "file", 1),
+            entry(1, "partial", 0),
+            entry(2, "sub", 0),
+            TraversalEvent::Entry {
+                depth: 3,
+                data: EntryData {
+                    name: "unreadable".into(),
+                    metadata_io_error: true,
+                    ..Default::default()
+                },
+            },
+            entry(3, "file", 2),
+        ] {
+            builder.add(&mut t, event);
+        }
+        builder.finish(&mut t);
+
+        let incomplete: Vec<_> = t
+            .tree
+            .node_indices()
+            .filter(|idx| t.tree[*idx].incomplete)
+            .map(|idx| t.tree[idx].name.to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(
+            incomplete,
+            vec!["", "root", "partial", "sub"],
+            "all ancestors of the unreadable entry are marked, but not the entry itself or other directories"
+        );
+        assert_eq!(t.io_errors, 1);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn directories_are_walked_through_their_handle() -> Result<(), Error> {
+        use crate::testing::{test_options, TempDir};
+
+        let temp = TempDir::new("handle")?;
+        let dir = temp.join("dir");
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("file"), b"content")?;
+        let handle = fs::File::open(&dir)?;
+        fs::rename(&dir, temp.join("renamed"))?;
+
+        let t = Traversal::from_handle(
+            WalkOptions {
+                symlinks: Symlinks {
+                    follow_root: false,
+                    ..Default::default()
+                },
+                ..test_options()
+            },
+            &handle,
+            dir.clone(),
+            |_| Ok(()),
+        )?;
+
+        let root = t
+            .tree
+            .neighbors_directed(t.root_index, Direction::Outgoing)
+            .next()
+            .expect("the walked directory");
+        assert_eq!(
+            t.tree[root].name,
+            dir.into_os_string(),
+            "it's named by its path"
+        );
+        assert_eq!(
+            t.total_bytes,
+            Some(7),
+            "the directory is found although it was renamed after opening it"
+        );
+        Ok(())
+    }
+}
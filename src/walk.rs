@@ -0,0 +1,218 @@
+//! The entries below a path, read from the filesystem or provided by tests.
+use crate::{
+    inodefilter::{file_id_and_links, FileId},
+    owner, ErrorClass, WalkOptions,
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// What we need to know about an entry to determine the space it consumes
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryMetadata {
+    pub is_dir: bool,
+    pub is_file: bool,
+    /// The length of the content in bytes
+    pub apparent_size: u64,
+    /// The amount of bytes occupied on disk, or the class of the error which prevented determining it. Always 0 for
+    /// directories.
+    pub disk_size: Result<u64, ErrorClass>,
+    /// The device and inode of the file and its amount of hard links, if the platform provides them
+    pub file_id_and_links: Option<(FileId, u64)>,
+    /// The user id of the owner, if the platform provides it
+    pub uid: Option<u32>,
+    /// If set, the entry is a symbolic link which isn't followed
+    pub is_symlink: bool,
+    /// If set, the entry is a symbolic link to a directory which isn't followed despite
+    /// [`Symlinks::follow_dirs`](crate::Symlinks::follow_dirs), as that directory contains it or was walked already.
+    pub is_symlink_loop: bool,
+    /// If set, the entry is a file which anyone may execute
+    pub is_executable: bool,
+}
+
+/// The metadata of an empty file.
+impl Default for EntryMetadata {
+    fn default() -> Self {
+        EntryMetadata {
+            is_dir: false,
+            is_file: false,
+            apparent_size: 0,
+            disk_size: Ok(0),
+            file_id_and_links: None,
+            uid: None,
+            is_symlink: false,
+            is_symlink_loop: false,
+            is_executable: false,
+        }
+    }
+}
+
+/// An entry encountered while walking a path
+#[derive(Debug)]
+pub struct Entry {
+    /// The amount of directories between the walked path and this entry, which is 0 for the walked path itself
+    pub depth: usize,
+    /// The path of the entry, which is the walked path as given for the entry at depth 0
+    pub path: PathBuf,
+    /// The metadata of the entry, or the error which prevented obtaining it
+    pub metadata: Result<EntryMetadata, io::Error>,
+}
+
+/// A source of the entries below a path, allowing to determine sizes without touching the filesystem.
+pub trait Walk {
+    /// All entries of `path` including itself, each directory before the entries within it.
+    /// An `Err` is returned for each directory whose contents couldn't be read.
+    fn entries<'a>(
+        &'a self,
+        path: &'a Path,
+        options: &'a WalkOptions,
+    ) -> Box<dyn Iterator<Item = Result<Entry, io::Error>> + 'a>;
+}
+
+/// Walks the filesystem in parallel, as configured by the [`WalkOptions`]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Filesystem;
+
+impl Walk for Filesystem {
+    fn entries<'a>(
+        &'a self,
+        path: &'a Path,
+        options: &'a WalkOptions,
+    ) -> Box<dyn Iterator<Item = Result<Entry, io::Error>> + 'a> {
+        Box::new(options.iter_from_path(path).map(move |entry| {
+            let entry = entry?;
+            let entry_path = if entry.depth == 0 {
+                path.to_owned()
+            } else {
+                entry.path()
+            };
+            let metadata = match entry.metadata {
+                Some(Ok(m)) => Ok(EntryMetadata {
+                    is_dir: m.is_dir(),
+                    is_file: m.is_file(),
+                    apparent_size: m.len(),
+                    disk_size: if m.is_dir() {
+                        Ok(0)
+                    } else if options.apparent_size {
+                        Ok(m.len())
+                    } else {
+                        filesize::file_real_size_fast(&entry_path, &m)
+                            .map_err(|err| ErrorClass::of(&err))
+                    },
+                    file_id_and_links: file_id_and_links(&m),
+                    uid: owner::uid_of(&m),
+                    is_symlink: m.file_type().is_symlink(),
+                    // followed links describe their target, so links to directories left here would have led back
+                    is_symlink_loop: m.file_type().is_symlink()
+                        && options.symlinks.follow_dirs
+                        && fs::metadata(&entry_path).is_ok_and(|target| target.is_dir()),
+                    is_executable: is_executable(&m),
+                }),
+                Some(Err(err)) => Err(err),
+                None => unreachable!(
+                    "we ask for metadata, so we at least have Some(Err(..))). Issue in jwalk?"
+                ),
+            };
+            Ok(Entry {
+                depth: entry.depth,
+                path: entry_path,
+                metadata,
+            })
+        }))
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &std::fs::Metadata) -> bool {
+    false
+}
+
+/// A filesystem in memory, for tests which need exact control over sizes, hard links and errors.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub(crate) struct FakeFilesystem {
+    /// Paths and their metadata in the order they are walked, with the class of the error for the ones which can't be read
+    entries: Vec<(PathBuf, Result<EntryMetadata, ErrorClass>)>,
+}
+
+#[cfg(test)]
+impl FakeFilesystem {
+    /// Add a directory at `path`. Its parent has to be added before.
+    pub fn dir(mut self, path: &str) -> Self {
+        self.entries.push((
+            path.into(),
+            Ok(EntryMetadata {
+                is_dir: true,
+                disk_size: Ok(0),
+                ..Default::default()
+            }),
+        ));
+        self
+    }
+
+    /// Add a file at `path` with `metadata`, which is made to describe a file.
+    pub fn file(mut self, path: &str, metadata: EntryMetadata) -> Self {
+        self.entries.push((
+            path.into(),
+            Ok(EntryMetadata {
+                is_file: true,
+                ..metadata
+            }),
+        ));
+        self
+    }
+
+    /// Add an entry at `path` whose metadata can't be read as permissions are lacking.
+    pub fn unreadable(self, path: &str) -> Self {
+        self.failing(path, ErrorClass::PermissionDenied)
+    }
+
+    /// Add an entry at `path` whose metadata can't be read due to an error of the given `class`.
+    pub fn failing(mut self, path: &str, class: ErrorClass) -> Self {
+        self.entries.push((path.into(), Err(class)));
+        self
+    }
+}
+
+#[cfg(test)]
+impl Walk for FakeFilesystem {
+    fn entries<'a>(
+        &'a self,
+        path: &'a Path,
+        _options: &'a WalkOptions,
+    ) -> Box<dyn Iterator<Item = Result<Entry, io::Error>> + 'a> {
+        let root_depth = path.components().count();
+        Box::new(
+            self.entries
+                .iter()
+                .filter(move |(entry_path, _)| entry_path.starts_with(path))
+                .map(move |(entry_path, metadata)| {
+                    Ok(Entry {
+                        depth: entry_path.components().count() - root_depth,
+                        path: entry_path.clone(),
+                        metadata: metadata.clone().map_err(error_of),
+                    })
+                }),
+        )
+    }
+}
+
+/// An error which is of the given `class`.
+#[cfg(test)]
+pub(crate) fn error_of(class: ErrorClass) -> io::Error {
+    match class {
+        ErrorClass::PermissionDenied => io::ErrorKind::PermissionDenied.into(),
+        ErrorClass::NotFound => io::ErrorKind::NotFound.into(),
+        ErrorClass::OutOfMemory => io::ErrorKind::OutOfMemory.into(),
+        ErrorClass::Hardware => io::Error::from_raw_os_error(5),
+        ErrorClass::Other => io::Error::other("fake"),
+    }
+}
@@ -12,6 +12,7 @@ fixtures="$root/fixtures"
 
 SUCCESSFULLY=0
 WITH_FAILURE=1
+WITH_THRESHOLD_EXCEEDED=2
 
 (with "a sample directory"
   (sandbox
@@ -24,6 +25,12 @@ WITH_FAILURE=1
             expect_run ${SUCCESSFULLY} "$exe"
           }
         )
+        (with "patterns to exclude"
+          it "skips entries matching inline patterns or the ones read from a file, including paths in the current directory" && {
+            WITH_SNAPSHOT="$snapshot/success-no-arguments-exclude" \
+            expect_run ${SUCCESSFULLY} "$exe" --exclude '*.b' --exclude-from <(printf '# not needed\n\nsub\n.hidden*\n')
+          }
+        )
         (with "the aggregate sub-command"
           (with "no sorting option"
             it "produces a human-readable (metric) aggregate of everything within the current directory, with total" && {
@@ -59,24 +66,196 @@ WITH_FAILURE=1
               expect_run ${SUCCESSFULLY} "$exe" aggregate --no-sort . . dir ./dir/ ./dir/sub
             }
           )
+          (with "all sizes in the same unit"
+            it "produces a human-readable aggregate, with all sizes in the unit of the largest one" && {
+              WITH_SNAPSHOT="$snapshot/success-no-arguments-multiple-input-paths-shared-unit" \
+              expect_run ${SUCCESSFULLY} "$exe" --shared-unit aggregate . . dir ./dir/ ./dir/sub
+            }
+          )
+          (with "a custom label for the total"
+            it "produces a human-readable aggregate, with the total using the given label" && {
+              WITH_SNAPSHOT="$snapshot/success-no-arguments-multiple-input-paths-total-label" \
+              expect_run ${SUCCESSFULLY} "$exe" aggregate --total-label Summe . . dir ./dir/ ./dir/sub
+            }
+          )
+          (with "the total first"
+            it "produces a human-readable aggregate, with the total before all paths" && {
+              WITH_SNAPSHOT="$snapshot/success-no-arguments-multiple-input-paths-total-first" \
+              expect_run ${SUCCESSFULLY} "$exe" --entry-count aggregate --total first . . dir ./dir/ ./dir/sub
+            }
+          )
           (with "the --stats option set"
             it "produces a human-readable aggregate, and statistics about the iteration in RON" && {
               WITH_SNAPSHOT="$snapshot/success-no-arguments-multiple-input-paths-statistics" \
               expect_run ${SUCCESSFULLY} "$exe" aggregate --stats . . dir ./dir/ ./dir/sub
             }
           )
+          (with "the --biggest option set"
+            it "produces a human-readable aggregate of only the largest path, without total" && {
+              WITH_SNAPSHOT="$snapshot/success-no-arguments-multiple-input-paths-biggest" \
+              expect_run ${SUCCESSFULLY} "$exe" aggregate --biggest dir ./dir/sub .
+            }
+          )
+          (with "the --top option set"
+            it "produces a human-readable aggregate of only the given amount of largest paths, with total" && {
+              WITH_SNAPSHOT="$snapshot/success-no-arguments-multiple-input-paths-top" \
+              expect_run ${SUCCESSFULLY} "$exe" aggregate --top 2 dir ./dir/sub a
+            }
+          )
+          (with "the --files-only and --top options set"
+            it "produces the given amount of largest files within all paths, the largest last" && {
+              WITH_SNAPSHOT="$snapshot/success-no-arguments-files-only-top" \
+              expect_run ${SUCCESSFULLY} "$exe" aggregate --files-only --top 3
+            }
+          )
+          (with "the --skip-empty option set"
+            it "produces a human-readable aggregate without empty paths, which still count towards the total" && {
+              WITH_SNAPSHOT="$snapshot/success-no-arguments-multiple-input-paths-skip-empty" \
+              expect_run ${SUCCESSFULLY} "$exe" --entry-count aggregate --skip-empty b.empty dir ./dir/sub
+            }
+          )
+          (with "the --total-only option set"
+            it "produces only the line with the total size" && {
+              WITH_SNAPSHOT="$snapshot/success-no-arguments-multiple-input-paths-total-only" \
+              expect_run ${SUCCESSFULLY} "$exe" --columns path-size aggregate --total-only . dir ./dir/sub
+            }
+          )
+          (with "a label for one of the paths"
+            it "produces a human-readable aggregate showing the label in place of the path, padded like a path" && {
+              WITH_SNAPSHOT="$snapshot/success-no-arguments-multiple-input-paths-label" \
+              expect_run ${SUCCESSFULLY} "$exe" --label "Subdirectory:./dir/sub" --columns path-size aggregate dir ./dir/sub
+            }
+          )
+          (with "a base the paths are relative to"
+            it "produces a human-readable aggregate with paths below the base relative to it" && {
+              WITH_SNAPSHOT="$snapshot/success-no-arguments-multiple-input-paths-relative-to" \
+              expect_run ${SUCCESSFULLY} "$exe" --relative-to dir aggregate dir dir/sub a
+            }
+          )
+          (with "directories within the path rolled up"
+            it "produces a human-readable aggregate of the directories and the files directly within the path, largest first" && {
+              WITH_SNAPSHOT="$snapshot/success-no-arguments-rollup" \
+              expect_run ${SUCCESSFULLY} "$exe" aggregate --rollup .
+            }
+          )
+          (with "only a sample of directories walked"
+            it "produces an estimate of each path and the total, with their margin of error" && {
+              WITH_SNAPSHOT="$snapshot/success-sample" \
+              expect_run ${SUCCESSFULLY} "$exe" aggregate --sample 0.5 --sample-seed 1 dir .
+            }
+          )
+          (with "directories down to a maximum depth"
+            it "produces each directory before its parent, noting the levels below the deepest ones" && {
+              WITH_SNAPSHOT="$snapshot/success-max-depth-total" \
+              expect_run ${SUCCESSFULLY} "$exe" aggregate --max-depth-total 1 .
+            }
+          )
+          (with "input paths which are patterns to expand"
+            it "produces an aggregate of the matching paths" && {
+              WITH_SNAPSHOT="$snapshot/success-expand-globs" \
+              expect_run ${SUCCESSFULLY} "$exe" --expand-globs '[ab]*' 'dir/*' '*.missing'
+            }
+          )
+          (with "the path column first"
+            it "produces a human-readable aggregate with paths padded to align the sizes" && {
+              WITH_SNAPSHOT="$snapshot/success-no-arguments-multiple-input-paths-path-size" \
+              expect_run ${SUCCESSFULLY} "$exe" --columns path-size aggregate . . dir ./dir/ ./dir/sub
+            }
+          )
+          (with "the --ndjson option set"
+            it "produces one JSON object per directory, written once all directories below it were written" && {
+              WITH_SNAPSHOT="$snapshot/success-ndjson-multiple-input-paths" \
+              expect_run ${SUCCESSFULLY} "$exe" aggregate --ndjson dir .
+            }
+          )
+          (with "the --sort-by option set to size-desc"
+            it "produces a human-readable aggregate with the largest path first" && {
+              WITH_SNAPSHOT="$snapshot/success-sort-by-size-desc" \
+              expect_run ${SUCCESSFULLY} "$exe" aggregate --sort-by size-desc dir . a
+            }
+          )
+          (with "the --json option set"
+            it "produces one JSON object per path and one for the total, with sizes in bytes" && {
+              WITH_SNAPSHOT="$snapshot/success-json-multiple-input-paths" \
+              expect_run ${SUCCESSFULLY} "$exe" --json aggregate dir .
+            }
+          )
+          (with "the --csv option set"
+            it "produces a header, one row per path and one for the total, with sizes in bytes" && {
+              WITH_SNAPSHOT="$snapshot/success-csv-multiple-input-paths" \
+              expect_run ${SUCCESSFULLY} "$exe" --csv --delimiter ';' aggregate dir .
+            }
+          )
         )
         (when "specifying no subcommand"
           it "produces a human-readable aggregate" && {
             WITH_SNAPSHOT="$snapshot/success-no-arguments-multiple-input-paths" \
             expect_run ${SUCCESSFULLY} "$exe" . . dir ./dir/ ./dir/sub
           }
+          (with "the --summarize option set"
+            it "produces one line per path in the given order without total, like du -s" && {
+              WITH_SNAPSHOT="$snapshot/success-summarize-multiple-input-paths" \
+              expect_run ${SUCCESSFULLY} "$exe" --format bytes -s ./dir/sub dir .
+            }
+            it "is equivalent to aggregating without sorting and total" && {
+              WITH_SNAPSHOT="$snapshot/success-summarize-multiple-input-paths" \
+              expect_run ${SUCCESSFULLY} "$exe" --format bytes aggregate --no-sort --no-total ./dir/sub dir .
+            }
+            it "fails if the paths are to be limited by size" && {
+              WITH_SNAPSHOT="$snapshot/failure-summarize-top" \
+              expect_run ${WITH_FAILURE} "$exe" -s aggregate --top 1 ./dir/sub dir
+            }
+          )
+        )
+        (when "specifying no paths and disallowing the use of the current working directory"
+          it "fails without looking at the current working directory" && {
+            WITH_SNAPSHOT="$snapshot/failure-no-implicit-cwd" \
+            expect_run ${WITH_FAILURE} "$exe" --no-implicit-cwd
+          }
+        )
+        (when "specifying an output file"
+          it "writes the aggregate to the file instead of stdout" && {
+            WITH_SNAPSHOT="$snapshot/success-output-file" \
+            expect_run_sh ${SUCCESSFULLY} "out=\$(mktemp) && '$exe' --output \$out dir ./dir/sub && cat \$out && rm \$out"
+          }
+          it "fails if the file can't be created" && {
+            WITH_SNAPSHOT="$snapshot/failure-output-file-not-creatable" \
+            expect_run ${WITH_FAILURE} "$exe" --output does-not-exist/out dir
+          }
+        )
+        (when "asking for the size on one line"
+          it "writes only the size, without newline" && {
+            WITH_SNAPSHOT="$snapshot/success-oneline" \
+            expect_run_sh ${SUCCESSFULLY} "'$exe' --oneline dir && echo '<end>'"
+          }
+        )
+        (when "checking whether the total size exceeds a threshold"
+          it "prints nothing if it doesn't" && {
+            WITH_SNAPSHOT="$snapshot/success-fail-over-not-exceeded" \
+            expect_run ${SUCCESSFULLY} "$exe" --fail-over 2MB dir ./dir/sub
+          }
+          it "fails with its own exit code and says so if it does" && {
+            WITH_SNAPSHOT="$snapshot/failure-fail-over-exceeded" \
+            expect_run ${WITH_THRESHOLD_EXCEEDED} "$exe" --fail-over 1MB dir ./dir/sub
+          }
         )
         (when "specifying no subcommand and some of the directories don't exist"
           it "produces a human-readable aggregate, with the number of errors per root" && {
             WITH_SNAPSHOT="$snapshot/failure-no-arguments-multiple-input-paths-some-not-existing" \
             expect_run ${WITH_FAILURE} "$exe" . . foo bar baz
           }
+          (with "errors annotated as a separate field"
+            it "produces a human-readable aggregate, with the number of errors per root as last tab-separated field" && {
+              WITH_SNAPSHOT="$snapshot/failure-no-arguments-multiple-input-paths-some-not-existing-error-field" \
+              expect_run ${WITH_FAILURE} "$exe" --error-annotation field . . foo bar baz
+            }
+          )
+          (with "errors ignored"
+            it "produces the same aggregate, but succeeds" && {
+              WITH_SNAPSHOT="$snapshot/failure-no-arguments-multiple-input-paths-some-not-existing" \
+              expect_run ${SUCCESSFULLY} "$exe" --ignore-errors . . foo bar baz
+            }
+          )
         )
       )
     )
@@ -91,6 +270,18 @@ WITH_FAILURE=1
         )
       done
     )
+    (with "the -b flag known from du"
+      it "produces the same aggregate as --apparent-size --format bytes" && {
+        WITH_SNAPSHOT="$snapshot/success-bytes-bytes" \
+        expect_run ${SUCCESSFULLY} "$exe" -b
+      }
+    )
+  )
+  (with "the capabilities requested"
+    it "prints the capabilities of the build as JSON" && {
+      WITH_SNAPSHOT="$snapshot/success-print-capabilities" \
+      expect_run ${SUCCESSFULLY} "$exe" --print-capabilities
+    }
   )
   (with "interactive mode"
     it "fails as there is no TTY connected" && {
@@ -6,14 +6,13 @@ mod terminal;
 pub use list::*;
 pub use terminal::*;
 
-use std::iter::repeat;
 use tui::{self, buffer::Buffer, layout::Rect, style::Color};
 
 pub fn fill_background_to_right(mut s: String, entire_width: u16) -> String {
     match (s.len(), entire_width as usize) {
         (x, y) if x >= y => s,
         (x, y) => {
-            s.extend(repeat(' ').take(y - x));
+            s.extend(std::iter::repeat_n(' ', y - x));
             s
         }
     }
@@ -13,7 +13,8 @@ pub struct List {
 impl List {
     fn list_offset_for(&self, entry_in_view: Option<usize>, height: usize) -> usize {
         match entry_in_view {
-            Some(pos) => match height as usize {
+            // an empty area shows nothing, but scrolls as if it could show one entry
+            Some(pos) => match height.max(1) {
                 h if self.offset + h - 1 < pos => pos - h + 1,
                 _ if self.offset > pos => pos,
                 _ => self.offset,
@@ -139,6 +139,7 @@ mod tests {
     use tui::backend::TestBackend;
 
     #[derive(Default, Clone)]
+    #[allow(dead_code)]
     struct ComplexProps {
         x: usize,
         y: String,
@@ -175,7 +176,7 @@ mod tests {
         term.render(&mut c, 3usize).ok();
         assert_eq!(c.x, 3);
 
-        let mut c = StatelessComponent::default();
+        let mut c = StatelessComponent;
         term.render(&mut c, ComplexProps::default()).ok();
     }
 }